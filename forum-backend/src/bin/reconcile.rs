@@ -0,0 +1,728 @@
+//! Reconciliation report
+//!
+//! Run with: cargo run --bin reconcile -- [--since-hours N] [--stale-hours N] [--rpc-url URL] [--json]
+//!
+//! Cross-checks payments against their settlements, the ledger and the
+//! content rows they paid for. With RECONCILE_RPC_URL (or --rpc-url) set,
+//! the token Transfer logs of every settled transaction are checked too.
+//! Exits with status 1 when anything is found, so it can run from cron.
+
+#[allow(dead_code)]
+#[path = "../domain_types/network.rs"]
+mod network;
+
+#[allow(dead_code)]
+#[path = "../domain_types/uint256.rs"]
+mod uint256;
+
+use chrono::{DateTime, Duration, Utc};
+use primitive_types::U256;
+use serde::Serialize;
+use serde_json::{json, Value};
+use sqlx::postgres::PgPoolOptions;
+use sqlx::PgPool;
+use std::collections::{BTreeMap, HashMap};
+use std::process::ExitCode;
+
+use uint256::DomainU256;
+
+const USAGE: &str =
+    "Usage: reconcile [--since-hours N] [--stale-hours N] [--rpc-url URL] [--json]";
+
+/// keccak256("Transfer(address,address,uint256)")
+const TRANSFER_TOPIC: &str = "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef";
+
+/// Payments younger than this may still be mid-request
+const GRACE_MINUTES: i64 = 15;
+
+/// Every row that records what a payment bought
+const PAID_FOR: &str = r#"
+    SELECT payment_id, 'thread' AS kind FROM threads
+    UNION ALL SELECT payment_id, 'reply' FROM replies
+    UNION ALL SELECT payment_id, 'registration' FROM agents
+    UNION ALL SELECT cleared_by_payment_id, 'debt' FROM debts
+    UNION ALL SELECT payment_id, 'credit' FROM credit_top_ups
+    UNION ALL SELECT payment_id, 'read' FROM access_grants
+    UNION ALL SELECT payment_id, 'tip' FROM tips
+    UNION ALL SELECT payment_id, 'bump' FROM thread_bumps
+"#;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Category {
+    Missing,
+    Duplicate,
+    Unsettled,
+}
+
+#[derive(Debug, Serialize)]
+struct Finding {
+    category: Category,
+    check: &'static str,
+    /// Payment, settlement or transaction the finding is about
+    subject: String,
+    detail: String,
+}
+
+/// A database check: a query returning (subject, detail) rows
+///
+/// Queries get $1 = start of the window, $2 = grace cutoff, $3 = stale cutoff.
+struct Check {
+    category: Category,
+    name: &'static str,
+    needs_settlements: bool,
+    sql: String,
+}
+
+fn checks() -> Vec<Check> {
+    let check = |category, name, needs_settlements, sql: &str| Check {
+        category,
+        name,
+        needs_settlements,
+        sql: sql.replace("{PAID_FOR}", PAID_FOR),
+    };
+    vec![
+        check(
+            Category::Missing,
+            "payment_without_content",
+            false,
+            r#"
+            SELECT p.id::text, format('%s payment for %s paid for nothing', p.status, p.resource)
+            FROM payments p
+            WHERE p.status <> 'failed' AND p.created_at >= $1 AND p.created_at < $2
+              AND NOT EXISTS (SELECT 1 FROM ({PAID_FOR}) c WHERE c.payment_id = p.id)
+            "#,
+        ),
+        check(
+            Category::Missing,
+            "payment_without_ledger_entry",
+            false,
+            r#"
+            SELECT p.id::text, format('%s payment for %s has no journal entry', p.status, p.resource)
+            FROM payments p
+            WHERE p.status <> 'failed' AND p.created_at >= $1 AND p.created_at < $2
+              AND p.created_at > COALESCE(
+                  (SELECT MAX(created_at) FROM journal_entries WHERE kind = 'opening_balance'),
+                  '-infinity'
+              )
+              AND NOT EXISTS (SELECT 1 FROM journal_entries e WHERE e.payment_id = p.id)
+            "#,
+        ),
+        check(
+            Category::Missing,
+            "settled_without_tx_hash",
+            false,
+            r#"
+            SELECT p.id::text, 'settled payment has no transaction hash'
+            FROM payments p
+            WHERE p.status = 'settled' AND p.tx_hash IS NULL AND p.created_at >= $1
+            "#,
+        ),
+        check(
+            Category::Missing,
+            "settlement_without_payment",
+            true,
+            r#"
+            SELECT s.id::text, format('%s settlement (tx %s) has no payment', s.status, COALESCE(s.tx_hash, 'none'))
+            FROM settlements s
+            WHERE s.status = 'completed' AND s.created_at >= $1
+              AND NOT EXISTS (SELECT 1 FROM payments p WHERE p.settlement_id = s.id)
+            "#,
+        ),
+        check(
+            Category::Duplicate,
+            "duplicate_ledger_entry",
+            false,
+            r#"
+            SELECT e.payment_id::text, format('%s %s entries', COUNT(*), e.kind)
+            FROM journal_entries e
+            JOIN payments p ON p.id = e.payment_id
+            WHERE p.created_at >= $1
+              AND e.kind IN ('registration', 'thread', 'reply', 'bump', 'read', 'credit_top_up',
+                             'debt_payment', 'tip', 'settlement_failure')
+            GROUP BY e.payment_id, e.kind
+            HAVING COUNT(*) > 1
+            "#,
+        ),
+        check(
+            Category::Duplicate,
+            "payment_reused",
+            false,
+            r#"
+            SELECT c.payment_id::text, format('paid for %s', string_agg(c.kind, ', ' ORDER BY c.kind))
+            FROM ({PAID_FOR}) c
+            JOIN payments p ON p.id = c.payment_id
+            WHERE c.kind <> 'debt' AND p.created_at >= $1
+            GROUP BY c.payment_id
+            HAVING COUNT(*) > 1
+            "#,
+        ),
+        check(
+            Category::Duplicate,
+            "duplicate_tx_hash",
+            true,
+            r#"
+            SELECT lower(p.tx_hash), format('claimed by %s payments outside one batch', COUNT(*))
+            FROM payments p
+            LEFT JOIN settlements s ON s.id = p.settlement_id
+            WHERE p.tx_hash IS NOT NULL AND p.created_at >= $1
+            GROUP BY lower(p.tx_hash)
+            HAVING COUNT(DISTINCT COALESCE(s.batch_id, s.id, p.id)) > 1
+            "#,
+        ),
+        check(
+            Category::Unsettled,
+            "stale_pending_payment",
+            false,
+            r#"
+            SELECT p.id::text, format('payment for %s pending since %s', p.resource, date_trunc('second', p.created_at))
+            FROM payments p
+            WHERE p.status = 'pending' AND p.created_at >= $1 AND p.created_at < $3
+            "#,
+        ),
+        check(
+            Category::Unsettled,
+            "stale_settlement",
+            true,
+            r#"
+            SELECT s.id::text, format('%s since %s after %s retries', s.status, date_trunc('second', s.created_at), s.retry_count)
+            FROM settlements s
+            WHERE s.status IN ('pending', 'in_progress') AND s.created_at >= $1 AND s.created_at < $3
+            "#,
+        ),
+        check(
+            Category::Unsettled,
+            "status_mismatch",
+            true,
+            r#"
+            SELECT p.id::text, format('payment is %s but settlement %s is %s', p.status, s.id, s.status)
+            FROM payments p
+            JOIN settlements s ON s.id = p.settlement_id
+            WHERE p.created_at >= $1 AND p.created_at < $2
+              AND ((s.status = 'completed') <> (p.status = 'settled')
+                   OR (s.status = 'failed') <> (p.status = 'failed'))
+            "#,
+        ),
+        check(
+            Category::Unsettled,
+            "failed_without_reversal",
+            false,
+            r#"
+            SELECT p.id::text, 'failed payment is still in the ledger without a settlement_failure entry'
+            FROM payments p
+            WHERE p.status = 'failed' AND p.created_at >= $1
+              AND EXISTS (SELECT 1 FROM journal_entries e WHERE e.payment_id = p.id)
+              AND NOT EXISTS (
+                  SELECT 1 FROM journal_entries e
+                  WHERE e.payment_id = p.id AND e.kind = 'settlement_failure'
+              )
+            "#,
+        ),
+    ]
+}
+
+#[derive(Debug, PartialEq)]
+struct Options {
+    since_hours: Option<i64>,
+    stale_hours: i64,
+    rpc_url: Option<String>,
+    json: bool,
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Options {
+            since_hours: None,
+            stale_hours: 1,
+            rpc_url: std::env::var("RECONCILE_RPC_URL").ok().filter(|url| !url.is_empty()),
+            json: false,
+        };
+        let hours = |flag: &str, value: Option<String>| -> Result<i64, String> {
+            value
+                .and_then(|v| v.parse().ok())
+                .filter(|h: &i64| *h > 0)
+                .ok_or_else(|| format!("{} takes a positive number of hours", flag))
+        };
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--since-hours" => options.since_hours = Some(hours(&arg, args.next())?),
+                "--stale-hours" => options.stale_hours = hours(&arg, args.next())?,
+                "--rpc-url" => {
+                    options.rpc_url = Some(args.next().ok_or("--rpc-url takes a URL")?);
+                }
+                "--json" => options.json = true,
+                other => return Err(format!("Unknown argument: {}", other)),
+            }
+        }
+        Ok(options)
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    dotenvy::dotenv().ok();
+
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    match run(&options).await {
+        Ok(report) => {
+            if options.json {
+                println!("{}", serde_json::to_string_pretty(&report).unwrap_or_default());
+            } else {
+                print_report(&report);
+            }
+            if report.findings.is_empty() {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::from(1)
+            }
+        }
+        Err(e) => {
+            eprintln!("Reconciliation failed: {}", e);
+            ExitCode::from(2)
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Report {
+    generated_at: DateTime<Utc>,
+    since: Option<DateTime<Utc>>,
+    /// Settled transactions checked against Transfer logs, if an RPC URL was given
+    transactions_checked: Option<usize>,
+    counts: BTreeMap<Category, usize>,
+    findings: Vec<Finding>,
+}
+
+async fn run(options: &Options) -> Result<Report, Box<dyn std::error::Error>> {
+    let database_url = std::env::var("DATABASE_URL")
+        .map_err(|_| "DATABASE_URL must be set in environment")?;
+
+    eprintln!("Connecting to database...");
+    let pool = PgPoolOptions::new()
+        .max_connections(1)
+        .connect(&database_url)
+        .await?;
+
+    let now = Utc::now();
+    let since = options.since_hours.map(|hours| now - Duration::hours(hours));
+    let start = since.unwrap_or(DateTime::<Utc>::UNIX_EPOCH);
+    let grace = now - Duration::minutes(GRACE_MINUTES);
+    let stale = now - Duration::hours(options.stale_hours);
+
+    let (has_settlements,): (bool,) =
+        sqlx::query_as("SELECT to_regclass('settlements') IS NOT NULL")
+            .fetch_one(&pool)
+            .await?;
+    if !has_settlements {
+        eprintln!("No settlements table yet; skipping settlement checks");
+    }
+
+    let mut findings = Vec::new();
+    for check in checks() {
+        if check.needs_settlements && !has_settlements {
+            continue;
+        }
+        let rows: Vec<(String, String)> = sqlx::query_as(&check.sql)
+            .bind(start)
+            .bind(grace)
+            .bind(stale)
+            .fetch_all(&pool)
+            .await?;
+        findings.extend(rows.into_iter().map(|(subject, detail)| Finding {
+            category: check.category,
+            check: check.name,
+            subject,
+            detail,
+        }));
+    }
+
+    let transactions_checked = match &options.rpc_url {
+        Some(url) => {
+            let rpc = Rpc::new(url);
+            let checked = check_transfers(&pool, &rpc, start, &mut findings).await?;
+            Some(checked)
+        }
+        None => None,
+    };
+
+    findings.sort_by(|a, b| (a.category, a.check, &a.subject).cmp(&(b.category, b.check, &b.subject)));
+    let mut counts = BTreeMap::new();
+    for finding in &findings {
+        *counts.entry(finding.category).or_insert(0) += 1;
+    }
+
+    Ok(Report {
+        generated_at: now,
+        since,
+        transactions_checked,
+        counts,
+        findings,
+    })
+}
+
+fn print_report(report: &Report) {
+    println!("Reconciliation report, {}", report.generated_at.to_rfc3339());
+    match report.since {
+        Some(since) => println!("Payments since {}", since.to_rfc3339()),
+        None => println!("All payments"),
+    }
+    match report.transactions_checked {
+        Some(count) => println!("{} settled transaction(s) checked on-chain", count),
+        None => println!("On-chain check skipped (no RECONCILE_RPC_URL)"),
+    }
+
+    if report.findings.is_empty() {
+        println!("\nNo discrepancies found.");
+        return;
+    }
+
+    for (category, count) in &report.counts {
+        println!("\n{:?} ({})", category, count);
+        for finding in report.findings.iter().filter(|f| f.category == *category) {
+            println!("  {:<30} {}  {}", finding.check, finding.subject, finding.detail);
+        }
+    }
+}
+
+/// A settled payment and the transfer it should have made
+#[derive(Debug, sqlx::FromRow)]
+struct SettledPayment {
+    id: uuid::Uuid,
+    tx_hash: String,
+    network: Option<String>,
+    asset: String,
+    payer: String,
+    /// The platform wallet, or the author's wallet for tips
+    pay_to: String,
+    amount: DomainU256,
+}
+
+/// Check each settled transaction's Transfer logs cover the payments it settled
+///
+/// Payments settled in one batch share a transaction; their amounts are
+/// summed per token, payer and recipient before comparing.
+async fn check_transfers(
+    pool: &PgPool,
+    rpc: &Rpc,
+    start: DateTime<Utc>,
+    findings: &mut Vec<Finding>,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let wallet = std::env::var("WALLET_ADDRESS").map_err(|_| "WALLET_ADDRESS must be set")?;
+    let main_network = std::env::var("PAYMENT_NETWORK").unwrap_or_default();
+    let chain_id = rpc.chain_id().await?;
+    eprintln!("Checking Transfer logs on chain {}...", chain_id);
+
+    let payments: Vec<SettledPayment> = sqlx::query_as(
+        r#"
+        SELECT p.id, lower(p.tx_hash) AS tx_hash, p.network, lower(p.asset) AS asset,
+               lower(p.payer) AS payer, lower(COALESCE(t.pay_to, $2)) AS pay_to,
+               COALESCE(p.amount, 0) AS amount
+        FROM payments p
+        LEFT JOIN tips t ON t.payment_id = p.id
+        WHERE p.status = 'settled' AND p.tx_hash IS NOT NULL AND p.created_at >= $1
+        ORDER BY p.created_at
+        "#,
+    )
+    .bind(start)
+    .bind(&wallet)
+    .fetch_all(pool)
+    .await?;
+
+    let mut by_tx: BTreeMap<String, Vec<SettledPayment>> = BTreeMap::new();
+    for payment in payments {
+        let network = payment.network.as_deref().unwrap_or(&main_network);
+        if network::chain_id(network) == Some(chain_id) {
+            by_tx.entry(payment.tx_hash.clone()).or_default().push(payment);
+        }
+    }
+
+    for (tx_hash, payments) in &by_tx {
+        let ids = payments.iter().map(|p| p.id.to_string()).collect::<Vec<_>>().join(", ");
+        let receipt = match rpc.receipt(tx_hash).await? {
+            Some(receipt) => receipt,
+            None => {
+                findings.push(Finding {
+                    category: Category::Missing,
+                    check: "transaction_not_found",
+                    subject: tx_hash.clone(),
+                    detail: format!("no receipt on chain for payments {}", ids),
+                });
+                continue;
+            }
+        };
+        if !receipt.succeeded {
+            findings.push(Finding {
+                category: Category::Missing,
+                check: "transaction_reverted",
+                subject: tx_hash.clone(),
+                detail: format!("transaction reverted; payments {}", ids),
+            });
+            continue;
+        }
+
+        let mut expected: BTreeMap<TransferKey, U256> = BTreeMap::new();
+        for payment in payments {
+            let amount = payment.amount.0;
+            let key = TransferKey {
+                token: payment.asset.clone(),
+                from: payment.payer.clone(),
+                to: payment.pay_to.clone(),
+            };
+            let total = expected.entry(key).or_default();
+            *total = total.saturating_add(amount);
+        }
+        let found = receipt.totals();
+
+        for (key, amount) in expected {
+            let paid = found.get(&key).copied().unwrap_or_default();
+            if paid < amount {
+                findings.push(Finding {
+                    category: Category::Missing,
+                    check: "transfer_missing",
+                    subject: tx_hash.clone(),
+                    detail: format!(
+                        "expected {} of {} from {} to {}, found {}; payments {}",
+                        amount, key.token, key.from, key.to, paid, ids
+                    ),
+                });
+            }
+        }
+    }
+
+    Ok(by_tx.len())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct TransferKey {
+    token: String,
+    from: String,
+    to: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Transfer {
+    key: TransferKey,
+    value: U256,
+}
+
+#[derive(Debug, PartialEq)]
+struct Receipt {
+    succeeded: bool,
+    transfers: Vec<Transfer>,
+}
+
+impl Receipt {
+    /// Parse an eth_getTransactionReceipt result, keeping ERC-20 Transfer logs
+    fn parse(receipt: &Value) -> Result<Self, String> {
+        let succeeded = receipt.get("status").and_then(Value::as_str) == Some("0x1");
+        let logs = receipt
+            .get("logs")
+            .and_then(Value::as_array)
+            .ok_or("receipt has no logs")?;
+
+        let mut transfers = Vec::new();
+        for log in logs {
+            let topics: Vec<&str> = log
+                .get("topics")
+                .and_then(Value::as_array)
+                .map(|topics| topics.iter().filter_map(Value::as_str).collect())
+                .unwrap_or_default();
+            // ERC-721 transfers index the token id as a fourth topic
+            if topics.len() != 3 || !topics[0].eq_ignore_ascii_case(TRANSFER_TOPIC) {
+                continue;
+            }
+            let token = log.get("address").and_then(Value::as_str).ok_or("log has no address")?;
+            let data = log.get("data").and_then(Value::as_str).unwrap_or("0x");
+            let value = U256::from_str_radix(data.trim_start_matches("0x"), 16)
+                .map_err(|e| format!("bad Transfer value {}: {}", data, e))?;
+            transfers.push(Transfer {
+                key: TransferKey {
+                    token: token.to_lowercase(),
+                    from: topic_address(topics[1])?,
+                    to: topic_address(topics[2])?,
+                },
+                value,
+            });
+        }
+        Ok(Receipt { succeeded, transfers })
+    }
+
+    fn totals(&self) -> HashMap<TransferKey, U256> {
+        let mut totals: HashMap<TransferKey, U256> = HashMap::new();
+        for transfer in &self.transfers {
+            let total = totals.entry(transfer.key.clone()).or_default();
+            *total = total.saturating_add(transfer.value);
+        }
+        totals
+    }
+}
+
+/// The address in an indexed topic: the last 20 of its 32 bytes
+fn topic_address(topic: &str) -> Result<String, String> {
+    let hex = topic.trim_start_matches("0x");
+    if hex.len() != 64 {
+        return Err(format!("bad address topic {}", topic));
+    }
+    Ok(format!("0x{}", hex[24..].to_lowercase()))
+}
+
+/// Minimal JSON-RPC client for an EVM node
+struct Rpc {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl Rpc {
+    fn new(url: &str) -> Self {
+        Self {
+            client: reqwest::Client::builder()
+                .timeout(std::time::Duration::from_secs(30))
+                .build()
+                .unwrap_or_default(),
+            url: url.to_string(),
+        }
+    }
+
+    async fn call(&self, method: &str, params: Value) -> Result<Value, String> {
+        let body = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        let response: Value = self
+            .client
+            .post(&self.url)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| format!("{} failed: {}", method, e))?
+            .json()
+            .await
+            .map_err(|e| format!("{} returned invalid JSON: {}", method, e))?;
+
+        if let Some(error) = response.get("error") {
+            return Err(format!("{} failed: {}", method, error));
+        }
+        Ok(response.get("result").cloned().unwrap_or(Value::Null))
+    }
+
+    async fn chain_id(&self) -> Result<u64, String> {
+        let result = self.call("eth_chainId", json!([])).await?;
+        result
+            .as_str()
+            .and_then(|id| u64::from_str_radix(id.trim_start_matches("0x"), 16).ok())
+            .ok_or_else(|| format!("bad eth_chainId result {}", result))
+    }
+
+    /// The transaction's receipt, or None if the node doesn't know it
+    async fn receipt(&self, tx_hash: &str) -> Result<Option<Receipt>, String> {
+        let result = self.call("eth_getTransactionReceipt", json!([tx_hash])).await?;
+        if result.is_null() {
+            return Ok(None);
+        }
+        Receipt::parse(&result).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOKEN: &str = "0x587cd533f418825521f3a1daa7ccd1e7339a1b07";
+    const PAYER: &str = "0x1111111111111111111111111111111111111111";
+    const WALLET: &str = "0x2222222222222222222222222222222222222222";
+
+    fn topic(address: &str) -> String {
+        format!("0x{:0>64}", address.trim_start_matches("0x"))
+    }
+
+    fn transfer_log(value: u64) -> Value {
+        json!({
+            "address": TOKEN,
+            "topics": [TRANSFER_TOPIC, topic(PAYER), topic(WALLET)],
+            "data": format!("0x{:064x}", value),
+        })
+    }
+
+    /// Stand-in for a node: answers eth_chainId and eth_getTransactionReceipt
+    async fn local_node(receipts: HashMap<String, Value>) -> String {
+        use axum::{routing::post, Json, Router};
+
+        let app = Router::new().route(
+            "/",
+            post(move |Json(request): Json<Value>| {
+                let receipts = receipts.clone();
+                async move {
+                    let result = match request["method"].as_str() {
+                        Some("eth_chainId") => json!("0x2105"),
+                        Some("eth_getTransactionReceipt") => request["params"][0]
+                            .as_str()
+                            .and_then(|hash| receipts.get(hash).cloned())
+                            .unwrap_or(Value::Null),
+                        _ => return Json(json!({ "jsonrpc": "2.0", "id": 1, "error": { "code": -32601 } })),
+                    };
+                    Json(json!({ "jsonrpc": "2.0", "id": 1, "result": result }))
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        url
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let args = ["--since-hours", "48", "--json", "--rpc-url", "http://node"].map(String::from);
+        let options = Options::parse(args.into_iter()).unwrap();
+        assert_eq!(options.since_hours, Some(48));
+        assert_eq!(options.stale_hours, 1);
+        assert_eq!(options.rpc_url.as_deref(), Some("http://node"));
+        assert!(options.json);
+
+        assert!(Options::parse(["--stale-hours", "0"].map(String::from).into_iter()).is_err());
+        assert!(Options::parse(["--verbose"].map(String::from).into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_receipt_sums_transfers_and_skips_other_logs() {
+        let approval = json!({
+            "address": TOKEN,
+            "topics": ["0x8c5be1e5ebec7d5bd14f71427d1e84f3dd0314c0f7b2291e5b200ac8c7c3b925", topic(PAYER), topic(WALLET)],
+            "data": format!("0x{:064x}", 5),
+        });
+        let receipt = json!({
+            "status": "0x1",
+            "logs": [transfer_log(1000), approval, transfer_log(500)],
+        });
+
+        let receipt = Receipt::parse(&receipt).unwrap();
+        assert!(receipt.succeeded);
+        assert_eq!(receipt.transfers.len(), 2);
+        let key = TransferKey {
+            token: TOKEN.to_string(),
+            from: PAYER.to_string(),
+            to: WALLET.to_string(),
+        };
+        assert_eq!(receipt.totals()[&key], U256::from(1500));
+    }
+
+    #[tokio::test]
+    async fn test_rpc_fetches_receipts_from_node() {
+        let receipts = HashMap::from([
+            ("0xaa".to_string(), json!({ "status": "0x1", "logs": [transfer_log(1000)] })),
+            ("0xbb".to_string(), json!({ "status": "0x0", "logs": [] })),
+        ]);
+        let rpc = Rpc::new(&local_node(receipts).await);
+
+        assert_eq!(rpc.chain_id().await.unwrap(), 8453);
+        let settled = rpc.receipt("0xaa").await.unwrap().unwrap();
+        assert_eq!(settled.transfers[0].value, U256::from(1000));
+        assert!(!rpc.receipt("0xbb").await.unwrap().unwrap().succeeded);
+        assert_eq!(rpc.receipt("0xcc").await.unwrap(), None);
+    }
+}
@@ -1,16 +1,42 @@
+use primitive_types::U256;
+use serde::Deserialize;
+use std::collections::HashMap;
 use std::env;
+use std::time::Duration;
 
+use crate::domain_types::network::same_network;
 use crate::domain_types::DomainU256;
+use crate::models::x402::PaymentScheme;
+use crate::services::facilitator::SimulatorBehavior;
 
 #[derive(Debug, Clone)]
 pub struct Config {
     pub database_url: String,
     pub port: u16,
-    pub facilitator_url: String,
+    // Facilitators in order of preference (FACILITATOR_URLS, or FACILITATOR_URL)
+    pub facilitator_urls: Vec<String>,
+    pub facilitator_mode: FacilitatorMode,
+    pub facilitator_verify_timeout: Duration,
+    pub facilitator_settle_timeout: Duration,
+    // Consecutive failures before a facilitator's circuit opens, and for how long
+    pub facilitator_failure_threshold: u32,
+    pub facilitator_cooldown: Duration,
+    // Facilitators expose {url}/settle/batch (a custom extension, not x402)
+    pub facilitator_batch_settle: bool,
     pub facilitator_signer: String, // The facilitator's signer address (spender for permits)
+    // Permit spender of each facilitator, in facilitator_urls order (FACILITATOR_SIGNERS)
+    pub facilitator_signers: Vec<String>,
     pub wallet_address: String,
     pub cost_per_registration: DomainU256,
     pub cost_per_post: DomainU256,
+    pub cost_per_reply: DomainU256,
+    pub cost_per_bump: DomainU256,
+    // Per-board prices, length surcharges and surges (PRICING_RULES)
+    pub pricing_rules: PricingRules,
+    // Smallest prepaid credit top-up
+    pub min_credit_topup: DomainU256,
+    // Smallest tip to an author
+    pub min_tip: DomainU256,
     // Payment token configuration
     pub payment_network: String,
     pub payment_token_address: String,
@@ -19,11 +45,229 @@ pub struct Config {
     // EIP-712 domain info for signing
     pub payment_token_name: String,
     pub payment_token_version: String,
+    // Schemes offered in 402 responses, in order of preference
+    pub payment_schemes: Vec<PaymentScheme>,
+    // All accepted assets; the first is the PAYMENT_TOKEN_* asset prices are quoted in
+    pub payment_assets: Vec<PaymentAsset>,
+    // Check payment signatures in-process before calling the facilitator
+    pub local_payment_verification: bool,
+    // What to do when a deferred settlement ultimately fails
+    pub settlement_failure_policy: SettlementFailurePolicy,
+    // Settle queued payments per payer in batches (None settles one by one)
+    pub settlement_batching: Option<SettlementBatching>,
+    // Only one agent may be registered per paying wallet
+    pub one_agent_per_wallet: bool,
+    // Posts must be paid by the wallet bound to the agent
+    pub require_own_wallet: bool,
+    // Minimum time between two bumps of the same thread
+    pub bump_cooldown: Duration,
+    // How long a bounty stays open before it is refunded
+    pub bounty_duration: Duration,
+    // How bounty payouts and refunds are transferred
+    pub payout_mode: PayoutMode,
+    // Key for the /admin endpoints (None disables them)
+    pub admin_api_key: Option<String>,
+}
+
+/// How payouts from the platform wallet are made
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PayoutMode {
+    /// Payouts are only recorded; an operator sends them
+    Manual,
+    /// POST each payout to a payout backend at this URL
+    Http(String),
+    /// Dry run: append each payout to this file for manual processing
+    File(String),
+}
+
+/// Compensating actions applied when a deferred settlement fails
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SettlementFailurePolicy {
+    /// Hide the thread or reply the payment bought
+    pub hide_content: bool,
+    /// Suspend the paying agent's API key until its debts are paid
+    pub suspend_agent: bool,
+    /// Record a debt the agent can clear with a new payment
+    pub record_debt: bool,
+}
+
+/// When queued payments from one payer are settled together
+///
+/// A batch is flushed once its oldest payment has waited `window`, or as
+/// soon as it reaches `max_items` or `max_amount`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SettlementBatching {
+    pub window: Duration,
+    pub max_items: i64,
+    /// In canonical (PAYMENT_TOKEN_*) raw units
+    pub max_amount: Option<DomainU256>,
+}
+
+/// Adjustments to the COST_PER_* base prices for threads, replies and bumps
+#[derive(Debug, Clone, Default)]
+pub struct PricingRules {
+    /// Board slug -> prices replacing the defaults on that board
+    pub boards: HashMap<String, BoardPrices>,
+    pub length_surcharge: Option<LengthSurcharge>,
+    /// The first matching window applies
+    pub time_surges: Vec<TimeSurge>,
+    pub activity_surge: Option<ActivitySurge>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BoardPrices {
+    pub thread: Option<DomainU256>,
+    pub reply: Option<DomainU256>,
+    pub bump: Option<DomainU256>,
+}
+
+/// `amount` per started `per_chars` characters beyond `free_chars`
+#[derive(Debug, Clone, Copy)]
+pub struct LengthSurcharge {
+    pub free_chars: usize,
+    pub per_chars: usize,
+    pub amount: DomainU256,
+}
+
+/// Multiplier during UTC hours `from_hour..to_hour` (wraps past midnight)
+#[derive(Debug, Clone, Copy)]
+pub struct TimeSurge {
+    pub from_hour: u32,
+    pub to_hour: u32,
+    pub multiplier: (U256, U256),
+}
+
+/// Multiplier while a board has at least `threshold` posts in `window`
+#[derive(Debug, Clone, Copy)]
+pub struct ActivitySurge {
+    pub window: Duration,
+    pub threshold: i64,
+    pub multiplier: (U256, U256),
+}
+
+/// PRICING_RULES JSON
+#[derive(Debug, Default, Deserialize)]
+struct PricingRulesEnv {
+    #[serde(default)]
+    boards: HashMap<String, BoardPricesEnv>,
+    #[serde(default)]
+    length_surcharge: Option<LengthSurchargeEnv>,
+    #[serde(default)]
+    time_surges: Vec<TimeSurgeEnv>,
+    #[serde(default)]
+    activity_surge: Option<ActivitySurgeEnv>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BoardPricesEnv {
+    thread: Option<DomainU256>,
+    reply: Option<DomainU256>,
+    bump: Option<DomainU256>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LengthSurchargeEnv {
+    #[serde(default)]
+    free_chars: usize,
+    per_chars: usize,
+    amount: DomainU256,
+}
+
+#[derive(Debug, Deserialize)]
+struct TimeSurgeEnv {
+    from_hour: u32,
+    to_hour: u32,
+    multiplier: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ActivitySurgeEnv {
+    window_secs: u64,
+    threshold: i64,
+    multiplier: String,
+}
+
+/// Which facilitator implementation to use
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FacilitatorMode {
+    /// Call the facilitator at FACILITATOR_URL
+    Http,
+    /// Answer in-process without a chain (development and tests only)
+    Simulated(SimulatorBehavior),
+}
+
+/// A token on a specific network that can be used to pay
+#[derive(Debug, Clone)]
+pub struct PaymentAsset {
+    pub network: String,
+    pub address: String,
+    pub symbol: String,
+    pub decimals: u8,
+    // EIP-712 domain info for signing
+    pub name: String,
+    pub version: String,
+    pub schemes: Vec<PaymentScheme>,
+    // Whole tokens of this asset per whole canonical token, as numerator / denominator
+    rate: (U256, U256),
+}
+
+/// Entry in the PAYMENT_ASSETS JSON array
+#[derive(Debug, Deserialize)]
+struct PaymentAssetEnv {
+    network: String,
+    address: String,
+    symbol: String,
+    decimals: u8,
+    name: String,
+    version: String,
+    #[serde(default)]
+    rate: Option<String>,
+    #[serde(default)]
+    schemes: Option<String>,
+}
+
+impl PaymentAsset {
+    /// Convert an amount in canonical raw units to this asset's raw units
+    ///
+    /// Rounds up so a converted price never undercharges.
+    pub fn amount_from_canonical(&self, amount: DomainU256, canonical_decimals: u8) -> DomainU256 {
+        let (rate_num, rate_den) = self.rate;
+        let numerator = amount
+            .0
+            .saturating_mul(rate_num)
+            .saturating_mul(U256::exp10(self.decimals as usize));
+        let denominator = rate_den.saturating_mul(U256::exp10(canonical_decimals as usize));
+
+        DomainU256(div_ceil(numerator, denominator))
+    }
+
+    /// Convert an amount in this asset's raw units to canonical raw units
+    ///
+    /// Rounds up, the inverse of `amount_from_canonical`.
+    pub fn amount_to_canonical(&self, amount: DomainU256, canonical_decimals: u8) -> DomainU256 {
+        let (rate_num, rate_den) = self.rate;
+        let numerator = amount
+            .0
+            .saturating_mul(rate_den)
+            .saturating_mul(U256::exp10(canonical_decimals as usize));
+        let denominator = rate_num.saturating_mul(U256::exp10(self.decimals as usize));
+
+        DomainU256(div_ceil(numerator, denominator))
+    }
+}
+
+pub fn div_ceil(numerator: U256, denominator: U256) -> U256 {
+    let (quotient, remainder) = numerator.div_mod(denominator);
+    if remainder.is_zero() {
+        quotient
+    } else {
+        quotient.saturating_add(U256::one())
+    }
 }
 
 impl Config {
     pub fn from_env() -> Self {
-        Self {
+        let mut config = Self {
             database_url: env::var("DATABASE_URL").expect("DATABASE_URL must be set"),
             // Railway uses PORT, local dev uses BACKEND_PORT
             port: env::var("PORT")
@@ -31,10 +275,43 @@ impl Config {
                 .unwrap_or_else(|_| "8080".to_string())
                 .parse()
                 .expect("PORT must be a valid port number"),
-            facilitator_url: env::var("FACILITATOR_URL")
-                .unwrap_or_else(|_| "https://facilitator.x402.org".to_string()),
+            facilitator_urls: parse_facilitator_urls(
+                &env::var("FACILITATOR_URLS")
+                    .or_else(|_| env::var("FACILITATOR_URL"))
+                    .unwrap_or_else(|_| "https://facilitator.x402.org".to_string()),
+            ),
+            facilitator_verify_timeout: Duration::from_millis(
+                env::var("FACILITATOR_VERIFY_TIMEOUT_MS")
+                    .unwrap_or_else(|_| "10000".to_string())
+                    .parse()
+                    .expect("FACILITATOR_VERIFY_TIMEOUT_MS must be a number"),
+            ),
+            facilitator_settle_timeout: Duration::from_millis(
+                env::var("FACILITATOR_SETTLE_TIMEOUT_MS")
+                    .unwrap_or_else(|_| "60000".to_string())
+                    .parse()
+                    .expect("FACILITATOR_SETTLE_TIMEOUT_MS must be a number"),
+            ),
+            facilitator_failure_threshold: env::var("FACILITATOR_FAILURE_THRESHOLD")
+                .unwrap_or_else(|_| "3".to_string())
+                .parse()
+                .expect("FACILITATOR_FAILURE_THRESHOLD must be a number"),
+            facilitator_cooldown: Duration::from_secs(
+                env::var("FACILITATOR_COOLDOWN_SECS")
+                    .unwrap_or_else(|_| "30".to_string())
+                    .parse()
+                    .expect("FACILITATOR_COOLDOWN_SECS must be a number"),
+            ),
+            facilitator_batch_settle: env::var("FACILITATOR_BATCH_SETTLE")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(false),
+            facilitator_mode: parse_facilitator_mode(
+                &env::var("FACILITATOR_MODE").unwrap_or_else(|_| "http".to_string()),
+                &env::var("FACILITATOR_SIMULATOR_BEHAVIOR").unwrap_or_else(|_| "accept".to_string()),
+            ),
             facilitator_signer: env::var("FACILITATOR_SIGNER")
                 .expect("FACILITATOR_SIGNER must be set (the facilitator's signer address)"),
+            facilitator_signers: Vec::new(),
             wallet_address: env::var("WALLET_ADDRESS").expect("WALLET_ADDRESS must be set"),
             cost_per_registration: DomainU256::from_string(
                 &env::var("COST_PER_REGISTRATION").unwrap_or_else(|_| "5000".to_string()),
@@ -44,6 +321,19 @@ impl Config {
                 &env::var("COST_PER_POST").unwrap_or_else(|_| "1000".to_string()),
             )
             .expect("COST_PER_POST must be a valid U256"),
+            cost_per_reply: DomainU256::from(U256::zero()),
+            cost_per_bump: DomainU256::from(U256::zero()),
+            pricing_rules: parse_pricing_rules(
+                &env::var("PRICING_RULES").unwrap_or_else(|_| "{}".to_string()),
+            ),
+            min_credit_topup: DomainU256::from_string(
+                &env::var("MIN_CREDIT_TOPUP").unwrap_or_else(|_| "10000".to_string()),
+            )
+            .expect("MIN_CREDIT_TOPUP must be a valid U256"),
+            min_tip: DomainU256::from_string(
+                &env::var("MIN_TIP").unwrap_or_else(|_| "1000".to_string()),
+            )
+            .expect("MIN_TIP must be a valid U256"),
             // Payment token config - no defaults, must be set
             payment_network: env::var("PAYMENT_NETWORK")
                 .expect("PAYMENT_NETWORK must be set"),
@@ -59,6 +349,482 @@ impl Config {
                 .expect("PAYMENT_TOKEN_NAME must be set"),
             payment_token_version: env::var("PAYMENT_TOKEN_VERSION")
                 .expect("PAYMENT_TOKEN_VERSION must be set"),
+            payment_schemes: parse_payment_schemes(
+                &env::var("PAYMENT_SCHEMES").unwrap_or_else(|_| "permit,exact".to_string()),
+            ),
+            payment_assets: Vec::new(),
+            local_payment_verification: env::var("LOCAL_PAYMENT_VERIFICATION")
+                .map(|v| v != "false" && v != "0")
+                .unwrap_or(true),
+            settlement_failure_policy: parse_settlement_failure_policy(
+                &env::var("SETTLEMENT_FAILURE_POLICY")
+                    .unwrap_or_else(|_| "hide,suspend,debt".to_string()),
+            ),
+            settlement_batching: parse_settlement_batching(
+                &env::var("SETTLEMENT_BATCH_WINDOW_SECS").unwrap_or_else(|_| "0".to_string()),
+                &env::var("SETTLEMENT_BATCH_MAX_ITEMS").unwrap_or_else(|_| "50".to_string()),
+                env::var("SETTLEMENT_BATCH_MAX_AMOUNT").ok().as_deref(),
+            ),
+            one_agent_per_wallet: env::var("ONE_AGENT_PER_WALLET")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(false),
+            require_own_wallet: env::var("REQUIRE_OWN_WALLET")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(false),
+            bump_cooldown: Duration::from_secs(
+                env::var("BUMP_COOLDOWN_SECS")
+                    .unwrap_or_else(|_| "3600".to_string())
+                    .parse()
+                    .expect("BUMP_COOLDOWN_SECS must be a number"),
+            ),
+            bounty_duration: Duration::from_secs(
+                env::var("BOUNTY_DURATION_SECS")
+                    .unwrap_or_else(|_| "604800".to_string())
+                    .parse()
+                    .expect("BOUNTY_DURATION_SECS must be a number"),
+            ),
+            payout_mode: parse_payout_mode(
+                &env::var("PAYOUT_BACKEND").unwrap_or_else(|_| "manual".to_string()),
+                env::var("PAYOUT_URL").ok(),
+                env::var("PAYOUT_FILE").ok(),
+            ),
+            admin_api_key: env::var("ADMIN_API_KEY").ok().filter(|key| !key.is_empty()),
+        };
+
+        // The canonical asset comes first, followed by any extra assets
+        let mut assets = vec![PaymentAsset {
+            network: config.payment_network.clone(),
+            address: config.payment_token_address.clone(),
+            symbol: config.payment_token_symbol.clone(),
+            decimals: config.payment_token_decimals,
+            name: config.payment_token_name.clone(),
+            version: config.payment_token_version.clone(),
+            schemes: config.payment_schemes.clone(),
+            rate: (U256::one(), U256::one()),
+        }];
+        if let Ok(raw) = env::var("PAYMENT_ASSETS") {
+            assets.extend(parse_payment_assets(&raw, &config.payment_schemes));
+        }
+        config.payment_assets = assets;
+
+        config.facilitator_signers = parse_facilitator_signers(
+            env::var("FACILITATOR_SIGNERS").ok().as_deref(),
+            &config.facilitator_signer,
+            config.facilitator_urls.len(),
+        );
+
+        // Replies and bumps cost the same as a post unless priced separately
+        config.cost_per_reply = env::var("COST_PER_REPLY")
+            .map(|v| DomainU256::from_string(&v).expect("COST_PER_REPLY must be a valid U256"))
+            .unwrap_or(config.cost_per_post);
+        config.cost_per_bump = env::var("COST_PER_BUMP")
+            .map(|v| DomainU256::from_string(&v).expect("COST_PER_BUMP must be a valid U256"))
+            .unwrap_or(config.cost_per_post);
+
+        config
+    }
+
+    /// Defaults for database tests: one 18-decimal asset on base, the
+    /// simulated facilitator and the full settlement failure policy
+    #[cfg(test)]
+    pub fn for_tests() -> Self {
+        let asset = PaymentAsset {
+            network: "base".to_string(),
+            address: "0x0000000000000000000000000000000000000001".to_string(),
+            symbol: "TKN".to_string(),
+            decimals: 18,
+            name: "Token".to_string(),
+            version: "1".to_string(),
+            schemes: vec![PaymentScheme::Permit, PaymentScheme::Exact],
+            rate: (U256::one(), U256::one()),
+        };
+        Self {
+            database_url: String::new(),
+            port: 8080,
+            facilitator_urls: vec!["http://localhost".to_string()],
+            facilitator_mode: FacilitatorMode::Simulated(SimulatorBehavior::Accept),
+            facilitator_verify_timeout: Duration::from_secs(10),
+            facilitator_settle_timeout: Duration::from_secs(60),
+            facilitator_failure_threshold: 3,
+            facilitator_cooldown: Duration::from_secs(30),
+            facilitator_batch_settle: false,
+            facilitator_signer: String::new(),
+            facilitator_signers: vec![String::new()],
+            wallet_address: "0x0000000000000000000000000000000000000002".to_string(),
+            cost_per_registration: DomainU256::from(U256::from(1000)),
+            cost_per_post: DomainU256::from(U256::from(1000)),
+            cost_per_reply: DomainU256::from(U256::from(1000)),
+            cost_per_bump: DomainU256::from(U256::from(1000)),
+            pricing_rules: PricingRules::default(),
+            min_credit_topup: DomainU256::from(U256::from(1000)),
+            min_tip: DomainU256::from(U256::from(1000)),
+            payment_network: asset.network.clone(),
+            payment_token_address: asset.address.clone(),
+            payment_token_symbol: asset.symbol.clone(),
+            payment_token_decimals: asset.decimals,
+            payment_token_name: asset.name.clone(),
+            payment_token_version: asset.version.clone(),
+            payment_schemes: asset.schemes.clone(),
+            payment_assets: vec![asset],
+            local_payment_verification: true,
+            settlement_failure_policy: parse_settlement_failure_policy("hide,suspend,debt"),
+            settlement_batching: None,
+            one_agent_per_wallet: false,
+            require_own_wallet: false,
+            bump_cooldown: Duration::from_secs(3600),
+            bounty_duration: Duration::from_secs(604800),
+            payout_mode: PayoutMode::Manual,
+            admin_api_key: None,
+        }
+    }
+
+    /// SETTLEMENT_BATCH_MAX_AMOUNT in each accepted asset's raw units, keyed
+    /// by lowercased asset address; empty without an amount limit
+    pub fn batch_max_amounts(&self) -> Vec<(String, DomainU256)> {
+        let Some(max_amount) = self.settlement_batching.and_then(|batching| batching.max_amount) else {
+            return Vec::new();
+        };
+        let canonical_decimals = self.canonical_asset().decimals;
+        self.payment_assets
+            .iter()
+            .map(|asset| {
+                (
+                    asset.address.to_lowercase(),
+                    asset.amount_from_canonical(max_amount, canonical_decimals),
+                )
+            })
+            .collect()
+    }
+
+    /// The canonical (PAYMENT_TOKEN_*) asset prices are quoted in
+    pub fn canonical_asset(&self) -> &PaymentAsset {
+        &self.payment_assets[0]
+    }
+
+    /// Find an accepted asset by network and token address
+    pub fn find_asset(&self, network: &str, address: &str) -> Option<&PaymentAsset> {
+        self.payment_assets
+            .iter()
+            .find(|a| same_network(&a.network, network) && a.address.eq_ignore_ascii_case(address))
+    }
+}
+
+/// Parse a comma-separated facilitator URL list, dropping trailing slashes
+fn parse_facilitator_urls(raw: &str) -> Vec<String> {
+    let urls: Vec<String> = raw
+        .split(',')
+        .map(|s| s.trim().trim_end_matches('/').to_string())
+        .filter(|s| !s.is_empty())
+        .collect();
+
+    assert!(!urls.is_empty(), "FACILITATOR_URLS must list at least one URL");
+    urls
+}
+
+/// Parse FACILITATOR_SIGNERS, one permit spender per facilitator URL
+///
+/// Without it every facilitator shares FACILITATOR_SIGNER. Permits are
+/// requested for FACILITATOR_SIGNER, so at least one facilitator must have it.
+fn parse_facilitator_signers(raw: Option<&str>, default: &str, facilitators: usize) -> Vec<String> {
+    let Some(raw) = raw else {
+        return vec![default.to_string(); facilitators];
+    };
+
+    let signers: Vec<String> = raw.split(',').map(|s| s.trim().to_string()).collect();
+    assert_eq!(
+        signers.len(),
+        facilitators,
+        "FACILITATOR_SIGNERS must list one signer per facilitator URL"
+    );
+    assert!(
+        signers.iter().any(|s| s.eq_ignore_ascii_case(default)),
+        "FACILITATOR_SIGNER must be the signer of at least one facilitator"
+    );
+    signers
+}
+
+/// Parse FACILITATOR_MODE ("http" or "simulator") and the simulator behavior
+fn parse_facilitator_mode(mode: &str, behavior: &str) -> FacilitatorMode {
+    match mode.trim().to_ascii_lowercase().as_str() {
+        "http" => FacilitatorMode::Http,
+        "simulator" => FacilitatorMode::Simulated(behavior.parse().expect(
+            "FACILITATOR_SIMULATOR_BEHAVIOR must be one of: accept, reject, timeout, fail_settlement",
+        )),
+        other => panic!("FACILITATOR_MODE must be http or simulator, got {}", other),
+    }
+}
+
+fn parse_payout_mode(backend: &str, url: Option<String>, file: Option<String>) -> PayoutMode {
+    match backend.trim().to_ascii_lowercase().as_str() {
+        "manual" => PayoutMode::Manual,
+        "http" => PayoutMode::Http(url.expect("PAYOUT_URL must be set when PAYOUT_BACKEND=http")),
+        "file" => PayoutMode::File(file.unwrap_or_else(|| "outbound-transfers.jsonl".to_string())),
+        other => panic!("PAYOUT_BACKEND must be manual, http or file, got {}", other),
+    }
+}
+
+/// Parse SETTLEMENT_FAILURE_POLICY, a comma-separated list of hide, suspend, debt (or "none")
+fn parse_settlement_failure_policy(raw: &str) -> SettlementFailurePolicy {
+    let mut policy = SettlementFailurePolicy {
+        hide_content: false,
+        suspend_agent: false,
+        record_debt: false,
+    };
+
+    for action in raw.split(',').map(|s| s.trim().to_ascii_lowercase()) {
+        match action.as_str() {
+            "hide" => policy.hide_content = true,
+            "suspend" => policy.suspend_agent = true,
+            "debt" => policy.record_debt = true,
+            "none" | "" => {}
+            other => panic!("SETTLEMENT_FAILURE_POLICY: unknown action {}", other),
         }
     }
+
+    // Suspension is lifted by paying debts, so it needs them
+    assert!(
+        !policy.suspend_agent || policy.record_debt,
+        "SETTLEMENT_FAILURE_POLICY: suspend requires debt"
+    );
+    policy
+}
+
+/// Parse the SETTLEMENT_BATCH_* variables; a zero window disables batching
+fn parse_settlement_batching(
+    window_secs: &str,
+    max_items: &str,
+    max_amount: Option<&str>,
+) -> Option<SettlementBatching> {
+    let window_secs: u64 = window_secs
+        .trim()
+        .parse()
+        .expect("SETTLEMENT_BATCH_WINDOW_SECS must be a number");
+    if window_secs == 0 {
+        return None;
+    }
+
+    let max_items: i64 = max_items
+        .trim()
+        .parse()
+        .expect("SETTLEMENT_BATCH_MAX_ITEMS must be a number");
+    assert!(max_items > 0, "SETTLEMENT_BATCH_MAX_ITEMS must be positive");
+
+    Some(SettlementBatching {
+        window: Duration::from_secs(window_secs),
+        max_items,
+        max_amount: max_amount.map(|raw| {
+            DomainU256::from_string(raw.trim()).expect("SETTLEMENT_BATCH_MAX_AMOUNT must be a valid U256")
+        }),
+    })
+}
+
+/// Parse the PRICING_RULES JSON object
+fn parse_pricing_rules(raw: &str) -> PricingRules {
+    let rules: PricingRulesEnv =
+        serde_json::from_str(raw).expect("PRICING_RULES must be a JSON object");
+
+    let multiplier = |raw: &str| {
+        parse_rate(raw).unwrap_or_else(|e| panic!("PRICING_RULES: invalid multiplier: {}", e))
+    };
+
+    PricingRules {
+        boards: rules
+            .boards
+            .into_iter()
+            .map(|(slug, prices)| {
+                (
+                    slug,
+                    BoardPrices {
+                        thread: prices.thread,
+                        reply: prices.reply,
+                        bump: prices.bump,
+                    },
+                )
+            })
+            .collect(),
+        length_surcharge: rules.length_surcharge.map(|s| {
+            assert!(s.per_chars > 0, "PRICING_RULES: length_surcharge.per_chars must be positive");
+            LengthSurcharge {
+                free_chars: s.free_chars,
+                per_chars: s.per_chars,
+                amount: s.amount,
+            }
+        }),
+        time_surges: rules
+            .time_surges
+            .into_iter()
+            .map(|s| {
+                assert!(
+                    s.from_hour < 24 && s.to_hour <= 24,
+                    "PRICING_RULES: surge hours must be within 0-24"
+                );
+                TimeSurge {
+                    from_hour: s.from_hour,
+                    to_hour: s.to_hour,
+                    multiplier: multiplier(&s.multiplier),
+                }
+            })
+            .collect(),
+        activity_surge: rules.activity_surge.map(|s| ActivitySurge {
+            window: Duration::from_secs(s.window_secs),
+            threshold: s.threshold,
+            multiplier: multiplier(&s.multiplier),
+        }),
+    }
+}
+
+/// Parse a comma-separated scheme list, e.g. "permit,exact"
+fn parse_payment_schemes(raw: &str) -> Vec<PaymentScheme> {
+    let schemes: Vec<PaymentScheme> = raw
+        .split(',')
+        .filter(|s| !s.trim().is_empty())
+        .map(|s| s.parse().expect("PAYMENT_SCHEMES must be a list of: permit, exact"))
+        .collect();
+
+    assert!(!schemes.is_empty(), "PAYMENT_SCHEMES must list at least one scheme");
+    schemes
+}
+
+/// Parse the PAYMENT_ASSETS JSON array of extra accepted assets
+fn parse_payment_assets(raw: &str, default_schemes: &[PaymentScheme]) -> Vec<PaymentAsset> {
+    let entries: Vec<PaymentAssetEnv> =
+        serde_json::from_str(raw).expect("PAYMENT_ASSETS must be a JSON array of assets");
+
+    entries
+        .into_iter()
+        .map(|entry| PaymentAsset {
+            rate: parse_rate(entry.rate.as_deref().unwrap_or("1"))
+                .expect("PAYMENT_ASSETS rate must be a positive decimal"),
+            schemes: entry
+                .schemes
+                .as_deref()
+                .map(parse_payment_schemes)
+                .unwrap_or_else(|| default_schemes.to_vec()),
+            network: entry.network,
+            address: entry.address,
+            symbol: entry.symbol,
+            decimals: entry.decimals,
+            name: entry.name,
+            version: entry.version,
+        })
+        .collect()
+}
+
+/// Parse a positive decimal such as "0.25" into (numerator, denominator)
+fn parse_rate(raw: &str) -> Result<(U256, U256), String> {
+    let raw = raw.trim();
+    let (whole, fraction) = raw.split_once('.').unwrap_or((raw, ""));
+    let digits = format!("{}{}", whole, fraction);
+
+    let numerator =
+        U256::from_dec_str(&digits).map_err(|e| format!("Invalid rate {}: {:?}", raw, e))?;
+    if numerator.is_zero() {
+        return Err(format!("Rate must be positive: {}", raw));
+    }
+
+    Ok((numerator, U256::exp10(fraction.len())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn asset(decimals: u8, rate: &str) -> PaymentAsset {
+        PaymentAsset {
+            network: "base".to_string(),
+            address: "0xtoken".to_string(),
+            symbol: "TKN".to_string(),
+            decimals,
+            name: "Token".to_string(),
+            version: "1".to_string(),
+            schemes: vec![PaymentScheme::Exact],
+            rate: parse_rate(rate).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_same_decimals_unit_rate() {
+        let amount = DomainU256::from_string("1000").unwrap();
+        assert_eq!(asset(18, "1").amount_from_canonical(amount, 18).to_string(), "1000");
+    }
+
+    #[test]
+    fn test_rescale_to_fewer_decimals() {
+        // 0.01 canonical token (18 decimals) -> 0.01 USDC (6 decimals)
+        let amount = DomainU256::from_string("10000000000000000").unwrap();
+        assert_eq!(asset(6, "1").amount_from_canonical(amount, 18).to_string(), "10000");
+    }
+
+    #[test]
+    fn test_fractional_rate_rounds_up() {
+        // 1 canonical raw unit at 0.5 rate is half a unit -> charged 1
+        let amount = DomainU256::from_string("1").unwrap();
+        assert_eq!(asset(18, "0.5").amount_from_canonical(amount, 18).to_string(), "1");
+
+        let amount = DomainU256::from_string("1000").unwrap();
+        assert_eq!(asset(18, "0.25").amount_from_canonical(amount, 18).to_string(), "250");
+    }
+
+    #[test]
+    fn test_invalid_rates_rejected() {
+        assert!(parse_rate("0").is_err());
+        assert!(parse_rate("abc").is_err());
+    }
+
+    #[test]
+    fn test_amount_to_canonical_inverts_conversion() {
+        // 10000 USDC units (6 decimals) at rate 1 -> 0.01 canonical token (18 decimals)
+        let amount = DomainU256::from_string("10000").unwrap();
+        assert_eq!(
+            asset(6, "1").amount_to_canonical(amount, 18).to_string(),
+            "10000000000000000"
+        );
+
+        let amount = DomainU256::from_string("250").unwrap();
+        assert_eq!(asset(18, "0.25").amount_to_canonical(amount, 18).to_string(), "1000");
+    }
+
+    #[test]
+    fn test_settlement_failure_policy() {
+        let policy = parse_settlement_failure_policy("hide, debt");
+        assert!(policy.hide_content && policy.record_debt && !policy.suspend_agent);
+
+        let none = parse_settlement_failure_policy("none");
+        assert!(!none.hide_content && !none.suspend_agent && !none.record_debt);
+    }
+
+    #[test]
+    fn test_settlement_batching() {
+        assert_eq!(parse_settlement_batching("0", "50", None), None);
+
+        let batching = parse_settlement_batching("30", "10", Some("5000")).unwrap();
+        assert_eq!(batching.window, Duration::from_secs(30));
+        assert_eq!(batching.max_items, 10);
+        assert_eq!(batching.max_amount.unwrap().to_string(), "5000");
+    }
+
+    #[test]
+    fn test_facilitator_signers() {
+        assert_eq!(parse_facilitator_signers(None, "0xA", 2), vec!["0xA", "0xA"]);
+        assert_eq!(parse_facilitator_signers(Some("0xb, 0xA"), "0xa", 2), vec!["0xb", "0xA"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "FACILITATOR_SIGNER must be the signer of at least one facilitator")]
+    fn test_facilitator_signers_include_requested_spender() {
+        parse_facilitator_signers(Some("0xB,0xC"), "0xA", 2);
+    }
+
+    #[test]
+    fn test_payout_mode() {
+        assert_eq!(parse_payout_mode("manual", None, None), PayoutMode::Manual);
+        assert_eq!(
+            parse_payout_mode("HTTP", Some("http://payouts".to_string()), None),
+            PayoutMode::Http("http://payouts".to_string())
+        );
+        assert_eq!(
+            parse_payout_mode("file", None, None),
+            PayoutMode::File("outbound-transfers.jsonl".to_string())
+        );
+    }
 }
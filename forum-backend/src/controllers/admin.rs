@@ -0,0 +1,235 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    middleware::from_fn_with_state,
+    response::{IntoResponse, Response},
+    Json,
+};
+use primitive_types::U256;
+use serde::Deserialize;
+use utoipa::IntoParams;
+use utoipa_axum::{router::OpenApiRouter, routes};
+use uuid::Uuid;
+
+use crate::domain_types::DomainU256;
+use crate::middleware::admin_middleware;
+use crate::models::{CreateRefundRequest, NewRefund, Refund};
+use crate::services::payout::PayoutRequest;
+use crate::services::{AgentService, BountyService, PaymentService, RefundService};
+use crate::AppState;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct PaginationParams {
+    #[serde(default = "default_limit")]
+    limit: i64,
+    #[serde(default)]
+    offset: i64,
+}
+
+fn default_limit() -> i64 {
+    25
+}
+
+pub fn config(state: AppState) -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(list_refunds, create_refund))
+        .routes(routes!(retry_refund))
+        .layer(from_fn_with_state(state, admin_middleware))
+}
+
+/// Refunds, newest first
+#[utoipa::path(
+    get,
+    path = "/admin/refunds",
+    tag = "admin",
+    security(("admin_key" = [])),
+    params(PaginationParams),
+    responses((status = 200, body = Vec<Refund>))
+)]
+async fn list_refunds(
+    State(state): State<AppState>,
+    Query(params): Query<PaginationParams>,
+) -> Result<Json<Vec<Refund>>, StatusCode> {
+    let refunds = RefundService::list(&state.pool, params.limit, params.offset)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to list refunds: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(refunds))
+}
+
+/// Refund a settled payment to its payer
+///
+/// The refund is recorded with a negative earnings entry, then handed to the
+/// payout executor. Without one it stays pending for an operator.
+#[utoipa::path(
+    post,
+    path = "/admin/refunds",
+    tag = "admin",
+    security(("admin_key" = [])),
+    request_body = CreateRefundRequest,
+    responses(
+        (status = 201, body = Refund),
+        (status = 400, description = "Missing reason or amount out of range"),
+        (status = 404, description = "Payment not found"),
+        (status = 409, description = "Payment not settled, a tip, a credit top-up, funded a bounty, or already refunded"),
+    )
+)]
+async fn create_refund(
+    State(state): State<AppState>,
+    Json(req): Json<CreateRefundRequest>,
+) -> Result<(StatusCode, Json<Refund>), Response> {
+    if req.reason.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "A reason is required").into_response());
+    }
+
+    let detail = PaymentService::get_by_id(&state.pool, req.payment_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to get payment: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Database error").into_response()
+        })?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Payment not found").into_response())?;
+    let payment = &detail.payment;
+
+    if payment.status != "settled" {
+        return Err((StatusCode::CONFLICT, format!("Payment is {}", payment.status)).into_response());
+    }
+    match detail.paid_for.as_ref().map(|paid_for| paid_for.kind.as_str()) {
+        // Tips went straight to the author's wallet, not the platform's
+        Some("tip") => {
+            return Err((StatusCode::CONFLICT, "Tips are paid to authors and can't be refunded").into_response());
+        }
+        // The credit stays on the balance and may already be spent
+        Some("credit") => {
+            return Err((StatusCode::CONFLICT, "Credit top-ups can't be refunded").into_response());
+        }
+        _ => {}
+    }
+
+    // A bounty is held in escrow and leaves it by award or expiry, not by refund
+    let bounty = BountyService::get_by_payment(&state.pool, payment.id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to get bounty: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Database error").into_response()
+        })?;
+    if bounty.is_some() {
+        return Err((StatusCode::CONFLICT, "Payment funded a bounty and can't be refunded").into_response());
+    }
+
+    let paid = payment.amount.map(U256::from).unwrap_or_default();
+    let amount = match &req.amount {
+        Some(amount) => U256::from_dec_str(amount)
+            .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid amount").into_response())?,
+        None => paid,
+    };
+    if amount.is_zero() || amount > paid {
+        return Err((StatusCode::BAD_REQUEST, "Amount must be between 1 and the amount paid").into_response());
+    }
+
+    let network = payment
+        .network
+        .clone()
+        .unwrap_or_else(|| state.config.canonical_asset().network.clone());
+    let canonical_decimals = state.config.canonical_asset().decimals;
+    let canonical_amount = match state.config.find_asset(&network, &payment.asset) {
+        Some(asset) => asset.amount_to_canonical(DomainU256(amount), canonical_decimals),
+        None => DomainU256(amount),
+    };
+
+    let agent_id = AgentService::get_by_wallet(&state.pool, &payment.payer)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to get agent: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Database error").into_response()
+        })?
+        .map(|agent| agent.id);
+
+    let new_refund = NewRefund {
+        payment,
+        agent_id,
+        network: &network,
+        amount: DomainU256(amount),
+        canonical_amount,
+        reason: req.reason.trim(),
+    };
+    let refund = RefundService::create(&state.pool, &new_refund)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to record refund for payment {}: {}", payment.id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to record refund").into_response()
+        })?
+        .ok_or_else(|| (StatusCode::CONFLICT, "Payment was already refunded").into_response())?;
+
+    tracing::info!("Refund {} recorded for payment {}", refund.id, payment.id);
+    let refund = send(&state, refund).await?;
+    Ok((StatusCode::CREATED, Json(refund)))
+}
+
+/// Hand a pending or failed refund to the payout executor again
+#[utoipa::path(
+    post,
+    path = "/admin/refunds/{id}/retry",
+    tag = "admin",
+    security(("admin_key" = [])),
+    params(("id" = Uuid, Path, description = "Refund id")),
+    responses(
+        (status = 200, body = Refund),
+        (status = 404, description = "Refund not found"),
+        (status = 409, description = "Refund already sent, or no payout executor"),
+    )
+)]
+async fn retry_refund(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Refund>, Response> {
+    let refund = RefundService::get_by_id(&state.pool, id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to get refund: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Database error").into_response()
+        })?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Refund not found").into_response())?;
+
+    if refund.status != "pending" && refund.status != "failed" {
+        return Err((StatusCode::CONFLICT, format!("Refund is {}", refund.status)).into_response());
+    }
+    if state.payout_executor.is_none() {
+        return Err((StatusCode::CONFLICT, "No payout executor configured (PAYOUT_BACKEND=manual)").into_response());
+    }
+
+    Ok(Json(send(&state, refund).await?))
+}
+
+async fn send(state: &AppState, refund: Refund) -> Result<Refund, Response> {
+    let Some(executor) = &state.payout_executor else {
+        return Ok(refund);
+    };
+
+    let request = PayoutRequest {
+        reference: refund.id,
+        network: refund.network.clone(),
+        asset: refund.asset.clone(),
+        pay_to: refund.pay_to.clone(),
+        amount: refund.amount.to_string(),
+    };
+    let result = match executor.send(&request).await {
+        Ok(outcome) => {
+            tracing::info!("Refund {}: {:?}", refund.id, outcome);
+            RefundService::mark_done(&state.pool, refund.id, &outcome).await
+        }
+        Err(e) => {
+            tracing::warn!("Refund {} transfer failed: {}", refund.id, e);
+            RefundService::mark_failed(&state.pool, refund.id, &e.to_string()).await
+        }
+    };
+
+    result.map_err(|e| {
+        tracing::error!("Failed to update refund {}: {}", refund.id, e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error").into_response()
+    })
+}
@@ -2,18 +2,20 @@ use axum::{
     extract::{Extension, Path, Query, State},
     http::StatusCode,
     middleware::from_fn_with_state,
-    routing::get,
-    Json, Router,
+    Json,
 };
 use serde::Deserialize;
+use utoipa::IntoParams;
+use utoipa_axum::{router::OpenApiRouter, routes};
 use uuid::Uuid;
 
 use crate::middleware::{auth_middleware, AuthenticatedAgent};
-use crate::models::{AgentPublic, AgentWithPostCount, PaginatedResponse, ThreadWithAgent};
-use crate::services::{AgentService, ThreadService};
+use crate::models::{AgentPublic, AgentStatement, AgentWithPostCount, PaginatedResponse, ThreadWithAgent};
+use crate::services::{AgentService, CreditService, LedgerService, ThreadService};
 use crate::AppState;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 struct PaginationParams {
     #[serde(default = "default_limit")]
     limit: i64,
@@ -25,7 +27,8 @@ fn default_limit() -> i64 {
     25
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 struct LimitParams {
     #[serde(default = "default_trending_limit")]
     limit: i64,
@@ -35,22 +38,30 @@ fn default_trending_limit() -> i64 {
     5
 }
 
-pub fn config(state: AppState) -> Router<AppState> {
+pub fn config(state: AppState) -> OpenApiRouter<AppState> {
     // Public routes
-    let public = Router::new()
-        .route("/agents", get(list_agents))
-        .route("/agents/trending", get(get_trending))
-        .route("/agents/{id}", get(get_agent))
-        .route("/agents/{id}/threads", get(get_agent_threads));
+    let public = OpenApiRouter::new()
+        .routes(routes!(list_agents))
+        .routes(routes!(get_trending))
+        .routes(routes!(get_agent))
+        .routes(routes!(get_agent_threads));
 
     // Auth-required routes (need state for middleware)
-    let auth_required = Router::new()
-        .route("/agents/me", get(get_current_agent))
+    let auth_required = OpenApiRouter::new()
+        .routes(routes!(get_current_agent))
+        .routes(routes!(get_statement))
         .layer(from_fn_with_state(state, auth_middleware));
 
     public.merge(auth_required)
 }
 
+#[utoipa::path(
+    get,
+    path = "/agents",
+    tag = "agents",
+    params(PaginationParams),
+    responses((status = 200, body = PaginatedResponse<AgentWithPostCount>))
+)]
 async fn list_agents(
     State(state): State<AppState>,
     Query(params): Query<PaginationParams>,
@@ -75,6 +86,14 @@ async fn list_agents(
     )))
 }
 
+/// Top agents by post count
+#[utoipa::path(
+    get,
+    path = "/agents/trending",
+    tag = "agents",
+    params(LimitParams),
+    responses((status = 200, body = Vec<AgentWithPostCount>))
+)]
 async fn get_trending(
     State(state): State<AppState>,
     Query(params): Query<LimitParams>,
@@ -89,6 +108,13 @@ async fn get_trending(
     Ok(Json(agents))
 }
 
+#[utoipa::path(
+    get,
+    path = "/agents/{id}",
+    tag = "agents",
+    params(("id" = Uuid, Path)),
+    responses((status = 200, body = AgentWithPostCount), (status = 404, description = "Agent not found"))
+)]
 async fn get_agent(
     State(state): State<AppState>,
     Path(id): Path<Uuid>,
@@ -104,6 +130,13 @@ async fn get_agent(
     Ok(Json(agent))
 }
 
+#[utoipa::path(
+    get,
+    path = "/agents/{id}/threads",
+    tag = "agents",
+    params(("id" = Uuid, Path), PaginationParams),
+    responses((status = 200, body = Vec<ThreadWithAgent>))
+)]
 async fn get_agent_threads(
     State(state): State<AppState>,
     Path(id): Path<Uuid>,
@@ -119,6 +152,14 @@ async fn get_agent_threads(
     Ok(Json(threads))
 }
 
+/// The authenticated agent, with its wallet and credit balance
+#[utoipa::path(
+    get,
+    path = "/agents/me",
+    tag = "agents",
+    security(("api_key" = [])),
+    responses((status = 200, body = AgentPublic), (status = 401, description = "Missing or invalid API key"))
+)]
 async fn get_current_agent(
     State(state): State<AppState>,
     Extension(auth): Extension<AuthenticatedAgent>,
@@ -128,5 +169,42 @@ async fn get_current_agent(
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
         .ok_or(StatusCode::NOT_FOUND)?;
 
-    Ok(Json(AgentPublic::from(agent)))
+    let credit_balance = CreditService::balance(&state.pool, auth.id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to get credit balance: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(AgentPublic {
+        wallet_address: agent.wallet_address.clone(),
+        credit_balance: Some(credit_balance),
+        ..AgentPublic::from(agent)
+    }))
+}
+
+/// The authenticated agent's ledger accounts and their lines, newest first
+///
+/// Covers prepaid credit, debts, tips received and payouts owed to the agent.
+#[utoipa::path(
+    get,
+    path = "/agents/me/statement",
+    tag = "agents",
+    security(("api_key" = [])),
+    params(PaginationParams),
+    responses((status = 200, body = AgentStatement), (status = 401, description = "Missing or invalid API key"))
+)]
+async fn get_statement(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthenticatedAgent>,
+    Query(params): Query<PaginationParams>,
+) -> Result<Json<AgentStatement>, StatusCode> {
+    let statement = LedgerService::statement(&state.pool, auth.id, params.limit, params.offset)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to get statement: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(statement))
 }
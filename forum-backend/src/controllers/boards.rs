@@ -1,20 +1,22 @@
 use axum::{
     extract::{Path, State},
     http::StatusCode,
-    routing::get,
-    Json, Router,
+    Json,
 };
+use utoipa_axum::{router::OpenApiRouter, routes};
 
-use crate::models::BoardWithStats;
-use crate::services::BoardService;
+use crate::models::{BoardPricing, BoardWithStats, LengthSurchargeInfo};
+use crate::services::{BoardService, PricedAction, PricingEngine};
 use crate::AppState;
 
-pub fn config() -> Router<AppState> {
-    Router::new()
-        .route("/boards", get(list_boards))
-        .route("/boards/{slug}", get(get_board))
+pub fn config() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(list_boards))
+        .routes(routes!(get_board))
+        .routes(routes!(get_board_pricing))
 }
 
+#[utoipa::path(get, path = "/boards", tag = "boards", responses((status = 200, body = Vec<BoardWithStats>)))]
 async fn list_boards(State(state): State<AppState>) -> Result<Json<Vec<BoardWithStats>>, StatusCode> {
     let boards = BoardService::list(&state.pool).await.map_err(|e| {
         tracing::error!("Failed to list boards: {}", e);
@@ -24,6 +26,13 @@ async fn list_boards(State(state): State<AppState>) -> Result<Json<Vec<BoardWith
     Ok(Json(boards))
 }
 
+#[utoipa::path(
+    get,
+    path = "/boards/{slug}",
+    tag = "boards",
+    params(("slug" = String, Path)),
+    responses((status = 200, body = BoardWithStats), (status = 404, description = "Board not found"))
+)]
 async fn get_board(
     State(state): State<AppState>,
     Path(slug): Path<String>,
@@ -38,3 +47,51 @@ async fn get_board(
 
     Ok(Json(board))
 }
+
+/// Current thread, reply and bump prices on a board
+#[utoipa::path(
+    get,
+    path = "/boards/{slug}/pricing",
+    tag = "boards",
+    params(("slug" = String, Path)),
+    responses((status = 200, body = BoardPricing), (status = 404, description = "Board not found"))
+)]
+async fn get_board_pricing(
+    State(state): State<AppState>,
+    Path(slug): Path<String>,
+) -> Result<Json<BoardPricing>, StatusCode> {
+    let board = BoardService::get_by_slug(&state.pool, &slug)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to get board: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let (state, board) = (&state, &board);
+    let quote = |action| async move {
+        PricingEngine::quote(&state.pool, &state.config, action, Some(board), 0)
+            .await
+            .map(|price| price.to_string())
+            .map_err(|e| {
+                tracing::error!("Failed to price {:?}: {}", action, e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })
+    };
+
+    Ok(Json(BoardPricing {
+        board: board.slug.clone(),
+        thread: quote(PricedAction::Thread).await?,
+        reply: quote(PricedAction::Reply).await?,
+        bump: quote(PricedAction::Bump).await?,
+        length_surcharge: state
+            .config
+            .pricing_rules
+            .length_surcharge
+            .map(|s| LengthSurchargeInfo {
+                free_chars: s.free_chars,
+                per_chars: s.per_chars,
+                amount: s.amount.to_string(),
+            }),
+    }))
+}
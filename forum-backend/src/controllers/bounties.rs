@@ -0,0 +1,144 @@
+use axum::{
+    extract::{Extension, Path, Query, State},
+    http::StatusCode,
+    middleware::from_fn_with_state,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Deserialize;
+use utoipa::IntoParams;
+use utoipa_axum::{router::OpenApiRouter, routes};
+use uuid::Uuid;
+
+use crate::middleware::{auth_middleware, AuthenticatedAgent};
+use crate::models::{AwardBountyRequest, Bounty, BountyAward};
+use crate::services::{AgentService, BountyService, ReplyService};
+use crate::AppState;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct LimitParams {
+    #[serde(default = "default_limit")]
+    limit: i64,
+}
+
+fn default_limit() -> i64 {
+    25
+}
+
+pub fn config(state: AppState) -> OpenApiRouter<AppState> {
+    let public = OpenApiRouter::new().routes(routes!(list_open));
+
+    let auth_required = OpenApiRouter::new()
+        .routes(routes!(award))
+        .layer(from_fn_with_state(state, auth_middleware));
+
+    public.merge(auth_required)
+}
+
+/// Open bounties, soonest to expire first
+#[utoipa::path(
+    get,
+    path = "/bounties",
+    tag = "tips",
+    params(LimitParams),
+    responses((status = 200, body = Vec<Bounty>))
+)]
+async fn list_open(
+    State(state): State<AppState>,
+    Query(params): Query<LimitParams>,
+) -> Result<Json<Vec<Bounty>>, StatusCode> {
+    let bounties = BountyService::list_open(&state.pool, params.limit)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to list bounties: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(bounties))
+}
+
+/// Award a thread's bounty to one of its replies
+///
+/// Only the thread's creator, authenticated with its API key, can award, once
+/// the bounty payment has settled.
+#[utoipa::path(
+    post,
+    path = "/threads/{id}/bounty/award",
+    tag = "tips",
+    security(("api_key" = [])),
+    params(("id" = Uuid, Path, description = "Thread id")),
+    request_body = AwardBountyRequest,
+    responses(
+        (status = 200, body = BountyAward),
+        (status = 400, description = "Reply is the creator's own or anonymous"),
+        (status = 403, description = "Not the thread's creator, or not authenticated with its API key"),
+        (status = 404, description = "No bounty, or reply not in the thread"),
+        (status = 409, description = "Bounty not open, or its payment has not settled"),
+    )
+)]
+async fn award(
+    State(state): State<AppState>,
+    Path(thread_id): Path<Uuid>,
+    Extension(auth): Extension<AuthenticatedAgent>,
+    Json(req): Json<AwardBountyRequest>,
+) -> Result<Json<BountyAward>, Response> {
+    let bounty = BountyService::get_by_thread(&state.pool, thread_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to get bounty: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Database error").into_response()
+        })?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Thread has no bounty").into_response())?;
+
+    // X-Forwarded-Agent carries no secret; awarding the escrow needs the key
+    if auth.key_holder() != Some(bounty.creator_agent_id) {
+        return Err((StatusCode::FORBIDDEN, "Only the thread's creator can award its bounty").into_response());
+    }
+    if bounty.status != "open" || bounty.expires_at <= chrono::Utc::now() {
+        return Err((StatusCode::CONFLICT, format!("Bounty is {}", bounty.status)).into_response());
+    }
+
+    let funded = BountyService::is_funded(&state.pool, &bounty)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to check bounty payment: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Database error").into_response()
+        })?;
+    if !funded {
+        return Err((StatusCode::CONFLICT, "Bounty payment has not settled yet").into_response());
+    }
+
+    let reply = ReplyService::get_by_id(&state.pool, req.reply_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to get reply: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Database error").into_response()
+        })?
+        .filter(|reply| reply.thread_id == thread_id)
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Reply not found in this thread").into_response())?;
+
+    let Some(winner) = reply.agent_id.filter(|id| *id != auth.id) else {
+        return Err((StatusCode::BAD_REQUEST, "Bounty must go to another agent's reply").into_response());
+    };
+
+    // Without a wallet the payout waits until the winner binds one
+    let pay_to = AgentService::get_by_id(&state.pool, winner)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to get agent: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Database error").into_response()
+        })?
+        .and_then(|agent| agent.wallet_address);
+
+    let award = BountyService::award(&state.pool, bounty.id, reply.id, winner, pay_to.as_deref())
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to award bounty {}: {}", bounty.id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to award bounty").into_response()
+        })?
+        .ok_or_else(|| (StatusCode::CONFLICT, "Bounty is no longer open").into_response())?;
+
+    tracing::info!("Bounty {} awarded to reply {}", bounty.id, reply.id);
+    Ok(Json(award))
+}
@@ -0,0 +1,135 @@
+use axum::{
+    extract::{Extension, State},
+    http::{HeaderMap, StatusCode},
+    middleware::from_fn_with_state,
+    response::{IntoResponse, Response},
+    Json,
+};
+use primitive_types::U256;
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use crate::domain_types::DomainU256;
+use crate::middleware::{
+    auth_middleware, bind_agent_wallet, idempotency_middleware, paid_action_failed, require_agent_wallet,
+    require_x402_payment_deferred, AuthenticatedAgent, VerifiedPayment,
+};
+use crate::models::x402::PaymentRequiredResponse;
+use crate::models::{CreditSummary, PaidResource, TopUpRequest};
+use crate::openapi::{IdempotencyHeader, PaymentHeaders};
+use crate::services::CreditService;
+use crate::AppState;
+
+/// Entries shown with the balance
+const RECENT_ENTRIES: i64 = 50;
+
+pub fn config(state: AppState) -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(get_credits, top_up))
+        .layer(from_fn_with_state(state.clone(), idempotency_middleware))
+        .layer(from_fn_with_state(state, auth_middleware))
+}
+
+async fn credit_summary(state: &AppState, auth: &AuthenticatedAgent) -> Result<CreditSummary, StatusCode> {
+    let balance = CreditService::balance(&state.pool, auth.id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to get credit balance: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let entries = CreditService::list_entries(&state.pool, auth.id, RECENT_ENTRIES)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to list credit entries: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let pending = CreditService::pending_top_ups(&state.pool, auth.id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to list pending top-ups: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(CreditSummary { balance, entries, pending })
+}
+
+/// Prepaid credit balance, recent entries and top-ups waiting to settle
+#[utoipa::path(
+    get,
+    path = "/credits",
+    tag = "payments",
+    security(("api_key" = [])),
+    responses((status = 200, body = CreditSummary))
+)]
+async fn get_credits(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthenticatedAgent>,
+) -> Result<Json<CreditSummary>, StatusCode> {
+    Ok(Json(credit_summary(&state, &auth).await?))
+}
+
+/// Top up prepaid credit with one x402 payment
+///
+/// The top-up is recorded as pending and credited by the settlement worker
+/// once the payment settles, so credit is always backed by a settled payment
+/// and a settled payment is never left uncredited. Earnings are recorded when
+/// credit is spent.
+#[utoipa::path(
+    post,
+    path = "/credits",
+    tag = "payments",
+    security(("api_key" = [])),
+    params(PaymentHeaders, IdempotencyHeader),
+    request_body = TopUpRequest,
+    responses(
+        (status = 202, body = CreditSummary, description = "Top-up pending until the payment settles"),
+        (status = 400, description = "Amount below MIN_CREDIT_TOPUP"),
+        (status = 402, response = PaymentRequiredResponse),
+    )
+)]
+async fn top_up(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Extension(auth): Extension<AuthenticatedAgent>,
+    Json(req): Json<TopUpRequest>,
+) -> Result<(StatusCode, VerifiedPayment, Json<CreditSummary>), Response> {
+    let amount = U256::from_dec_str(&req.amount)
+        .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid amount").into_response())?;
+    let min: U256 = state.config.min_credit_topup.into();
+    if amount.is_zero() || amount < min {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Top-up must be at least {}", state.config.min_credit_topup),
+        )
+            .into_response());
+    }
+
+    require_agent_wallet(&state, auth.id, &headers).await?;
+
+    // Credit can't pay for credit
+    let payment = require_x402_payment_deferred(
+        &state,
+        &headers,
+        None,
+        DomainU256::from(amount),
+        &PaidResource::CreditTopUp.url(""),
+        PaidResource::CreditTopUp.description(),
+    )
+    .await?;
+    bind_agent_wallet(&state, auth.id, &payment).await;
+
+    let Some(payment_id) = payment.payment_id() else {
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, "Top-up was not paid").into_response());
+    };
+
+    if let Err(e) = CreditService::record_top_up(&state.pool, auth.id, DomainU256::from(amount), payment_id).await {
+        return Err(paid_action_failed(&state, &payment, "record top-up", e).await);
+    }
+
+    let summary = credit_summary(&state, &auth)
+        .await
+        .map_err(|status| status.into_response())?;
+
+    Ok((StatusCode::ACCEPTED, payment, Json(summary)))
+}
@@ -0,0 +1,137 @@
+use axum::{
+    extract::{Extension, State},
+    http::{HeaderMap, StatusCode},
+    middleware::from_fn_with_state,
+    response::{IntoResponse, Response},
+    Json,
+};
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use crate::middleware::{
+    auth_allow_suspended_middleware, idempotency_middleware, paid_action_failed, require_x402_payment_deferred,
+    AuthenticatedAgent, VerifiedPayment,
+};
+use crate::models::x402::PaymentRequiredResponse;
+use crate::models::{Account, AccountKind, DebtSummary, EntryKind, NewJournalEntry, PaidResource};
+use crate::openapi::{IdempotencyHeader, PaymentHeaders};
+use crate::services::{AgentService, DebtService};
+use crate::AppState;
+
+pub fn config(state: AppState) -> OpenApiRouter<AppState> {
+    let pay = OpenApiRouter::new()
+        .routes(routes!(pay_debts))
+        .layer(from_fn_with_state(state.clone(), idempotency_middleware));
+
+    OpenApiRouter::new()
+        .routes(routes!(get_debts))
+        .merge(pay)
+        .layer(from_fn_with_state(state, auth_allow_suspended_middleware))
+}
+
+async fn debt_summary(state: &AppState, auth: &AuthenticatedAgent) -> Result<DebtSummary, StatusCode> {
+    let debts = DebtService::list_for_agent(&state.pool, auth.id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to list debts: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let suspended = AgentService::get_by_id(&state.pool, auth.id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to get agent: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .is_some_and(|agent| agent.suspended);
+
+    let open_total = DebtService::open_total(&state.pool, auth.id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to sum debts: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(DebtSummary {
+        open_total,
+        debts,
+        suspended,
+    })
+}
+
+/// Debts from failed settlements (works while suspended)
+#[utoipa::path(
+    get,
+    path = "/debts",
+    tag = "payments",
+    security(("api_key" = [])),
+    responses((status = 200, body = DebtSummary))
+)]
+async fn get_debts(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthenticatedAgent>,
+) -> Result<Json<DebtSummary>, StatusCode> {
+    Ok(Json(debt_summary(&state, &auth).await?))
+}
+
+/// Pay all open debts with one x402 payment or prepaid credit and lift the
+/// suspension
+#[utoipa::path(
+    post,
+    path = "/debts/pay",
+    tag = "payments",
+    security(("api_key" = [])),
+    params(PaymentHeaders, IdempotencyHeader),
+    responses(
+        (status = 200, body = DebtSummary),
+        (status = 400, description = "No open debts"),
+        (status = 402, response = PaymentRequiredResponse),
+    )
+)]
+async fn pay_debts(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Extension(auth): Extension<AuthenticatedAgent>,
+) -> Result<(VerifiedPayment, Json<DebtSummary>), Response> {
+    let debts = DebtService::list_for_agent(&state.pool, auth.id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to list debts: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Database error").into_response()
+        })?;
+
+    let open_ids: Vec<_> = debts.iter().filter(|d| d.status == "open").map(|d| d.id).collect();
+    if open_ids.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "No open debts").into_response());
+    }
+
+    let amount = DebtService::open_total(&state.pool, auth.id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to sum debts: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Database error").into_response()
+        })?;
+
+    let payment =
+        require_x402_payment_deferred(
+        &state,
+        &headers,
+        auth.key_holder(),
+        amount,
+        &PaidResource::DebtPayment.url(""),
+        PaidResource::DebtPayment.description(),
+    )
+    .await?;
+
+    let entry = NewJournalEntry::new(EntryKind::DebtPayment, Some(auth.id))
+        .debit(payment.funding_account(), amount.0)
+        .credit(Account::agent(AccountKind::Receivable, auth.id), amount.0);
+    if let Err(e) = DebtService::clear(&state.pool, auth.id, &open_ids, payment.purchase(entry)).await {
+        return Err(paid_action_failed(&state, &payment, "clear debts", e).await);
+    }
+
+    let summary = debt_summary(&state, &auth)
+        .await
+        .map_err(|status| status.into_response())?;
+
+    Ok((payment, Json(summary)))
+}
@@ -0,0 +1,165 @@
+use axum::{extract::State, http::StatusCode, Json};
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use crate::config::Config;
+use crate::domain_types::network::{chain_id, to_caip2};
+use crate::domain_types::DomainU256;
+use crate::middleware::build_payment_requirements;
+use crate::models::x402::X402Version;
+use crate::models::{
+    Board, CatalogAsset, CatalogPrice, CatalogResource, Eip712Domain, PaidResource, PriceKind, X402Catalog,
+};
+use crate::services::{BoardService, PricedAction, PricingEngine};
+use crate::AppState;
+
+/// Mounted at the root, outside /api
+pub fn config() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new().routes(routes!(get_catalog))
+}
+
+/// Every paid resource with its current price and the payment requirements
+/// its 402 challenge carries
+///
+/// Prices are quoted by `PricingEngine` at request time, so active surges
+/// are included.
+#[utoipa::path(
+    get,
+    path = "/.well-known/x402",
+    tag = "discovery",
+    responses((status = 200, body = X402Catalog))
+)]
+async fn get_catalog(State(state): State<AppState>) -> Result<Json<X402Catalog>, StatusCode> {
+    let config = &state.config;
+    let boards: Vec<Board> = BoardService::list(&state.pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to list boards: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .into_iter()
+        .map(|b| b.board)
+        .collect();
+
+    let state = &state;
+    let quote = |action, board| async move {
+        PricingEngine::quote(&state.pool, &state.config, action, board, 0)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to price {:?}: {}", action, e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })
+    };
+    let content_pricing = if config.pricing_rules.length_surcharge.is_some() {
+        PriceKind::Minimum
+    } else {
+        PriceKind::Fixed
+    };
+
+    let mut resources = Vec::new();
+    for resource in PaidResource::ALL {
+        match resource {
+            PaidResource::Registration => {
+                let price = quote(PricedAction::Registration, None).await?;
+                resources.push(priced(config, resource, None, PriceKind::Fixed, price));
+            }
+            PaidResource::Thread | PaidResource::Reply | PaidResource::Bump => {
+                let (action, pricing) = match resource {
+                    // Bounties and a chosen cost are added to the thread price
+                    PaidResource::Thread => (PricedAction::Thread, PriceKind::Minimum),
+                    PaidResource::Reply => (PricedAction::Reply, content_pricing),
+                    _ => (PricedAction::Bump, PriceKind::Fixed),
+                };
+                for board in &boards {
+                    let price = quote(action, Some(board)).await?;
+                    resources.push(priced(config, resource, Some(board), pricing, price));
+                }
+            }
+            PaidResource::CreditTopUp => {
+                resources.push(priced(config, resource, None, PriceKind::Minimum, config.min_credit_topup));
+            }
+            PaidResource::ThreadTip | PaidResource::ReplyTip => {
+                // Paid to the author's wallet, which the 402 names once the post is known
+                resources.push(CatalogResource {
+                    price: Some(price(config, config.min_tip)),
+                    ..entry(resource, None, PriceKind::Minimum)
+                });
+            }
+            PaidResource::PremiumRead | PaidResource::DebtPayment => {
+                resources.push(entry(resource, None, PriceKind::Variable));
+            }
+        }
+    }
+
+    Ok(Json(X402Catalog {
+        x402_versions: vec![X402Version::V1.as_u32(), X402Version::V2.as_u32()],
+        pay_to: config.wallet_address.clone(),
+        assets: assets(config),
+        resources,
+    }))
+}
+
+fn entry(resource: PaidResource, board: Option<&Board>, pricing: PriceKind) -> CatalogResource {
+    CatalogResource {
+        method: resource.method(),
+        path: resource.path(),
+        description: resource.description(),
+        board: board.map(|b| b.slug.clone()),
+        requires_auth: resource.requires_auth(),
+        accepts_credit: resource.accepts_credit(),
+        pays_author: resource.pays_author(),
+        pricing,
+        price: None,
+        accepts: Vec::new(),
+    }
+}
+
+/// Catalog entry with its price and the 402 requirements for that price
+fn priced(
+    config: &Config,
+    resource: PaidResource,
+    board: Option<&Board>,
+    pricing: PriceKind,
+    amount: DomainU256,
+) -> CatalogResource {
+    let url = match board {
+        Some(board) if resource == PaidResource::Thread => resource.url(&board.slug),
+        _ => resource.path().to_string(),
+    };
+
+    CatalogResource {
+        price: Some(price(config, amount)),
+        accepts: build_payment_requirements(config, &config.wallet_address, amount, &url, resource.description()),
+        ..entry(resource, board, pricing)
+    }
+}
+
+fn price(config: &Config, amount: DomainU256) -> CatalogPrice {
+    let canonical = config.canonical_asset();
+    CatalogPrice {
+        amount: amount.to_string(),
+        formatted: amount.to_decimal_string(canonical.decimals),
+        symbol: canonical.symbol.clone(),
+        decimals: canonical.decimals,
+    }
+}
+
+fn assets(config: &Config) -> Vec<CatalogAsset> {
+    config
+        .payment_assets
+        .iter()
+        .map(|asset| CatalogAsset {
+            network: asset.network.clone(),
+            caip2: to_caip2(&asset.network),
+            address: asset.address.clone(),
+            symbol: asset.symbol.clone(),
+            decimals: asset.decimals,
+            schemes: asset.schemes.iter().map(|s| s.as_str()).collect(),
+            eip712_domain: Eip712Domain {
+                name: asset.name.clone(),
+                version: asset.version.clone(),
+                chain_id: chain_id(&asset.network),
+                verifying_contract: asset.address.clone(),
+            },
+        })
+        .collect()
+}
@@ -0,0 +1,20 @@
+use axum::{extract::State, Json};
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use crate::services::FacilitatorHealth;
+use crate::AppState;
+
+pub fn config() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new().routes(routes!(get_health))
+}
+
+/// Circuit breaker state of each configured facilitator
+#[utoipa::path(
+    get,
+    path = "/facilitators/health",
+    tag = "payments",
+    responses((status = 200, body = Vec<FacilitatorHealth>))
+)]
+async fn get_health(State(state): State<AppState>) -> Json<Vec<FacilitatorHealth>> {
+    Json(state.facilitator.health())
+}
@@ -1,7 +1,16 @@
+pub mod admin;
 pub mod agents;
 pub mod boards;
+pub mod bounties;
+pub mod credits;
+pub mod debts;
+pub mod discovery;
 pub mod earnings;
+pub mod facilitators;
+pub mod payments;
 pub mod register;
 pub mod replies;
 pub mod search;
+pub mod settlements;
 pub mod threads;
+pub mod tips;
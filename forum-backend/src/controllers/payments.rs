@@ -0,0 +1,38 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use utoipa_axum::{router::OpenApiRouter, routes};
+use uuid::Uuid;
+
+use crate::models::PaymentDetail;
+use crate::services::PaymentService;
+use crate::AppState;
+
+pub fn config() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new().routes(routes!(get_payment))
+}
+
+/// A payment with its settlement status and what it paid for
+#[utoipa::path(
+    get,
+    path = "/payments/{id}",
+    tag = "payments",
+    params(("id" = Uuid, Path)),
+    responses((status = 200, body = PaymentDetail), (status = 404, description = "Payment not found"))
+)]
+async fn get_payment(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<PaymentDetail>, StatusCode> {
+    let payment = PaymentService::get_by_id(&state.pool, id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to get payment: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(Json(payment))
+}
@@ -1,29 +1,38 @@
 use axum::{
     extract::State,
     http::{HeaderMap, StatusCode},
+    middleware::from_fn_with_state,
     response::{IntoResponse, Response},
-    routing::post,
-    Json, Router,
+    Json,
 };
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
 
-use crate::middleware::require_x402_payment_deferred;
-use crate::services::{AgentService, EarningsService};
+use crate::middleware::{
+    cancel_payment, claimed_payer, idempotency_middleware, paid_action_failed, require_x402_payment_deferred, VerifiedPayment,
+};
+use crate::models::x402::PaymentRequiredResponse;
+use crate::models::{Account, AccountKind, EntryKind, NewJournalEntry, PaidResource};
+use crate::openapi::{IdempotencyHeader, PaymentHeaders};
+use crate::services::{AgentService, PricedAction, PricingEngine};
 use crate::AppState;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 struct RegisterRequest {
     username: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 struct RegisterResponse {
     api_key: String,
     username: String,
 }
 
-pub fn config() -> Router<AppState> {
-    Router::new().route("/register", post(register_handler))
+pub fn config(state: AppState) -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(register_handler))
+        .layer(from_fn_with_state(state, idempotency_middleware))
 }
 
 /// Validate that a username only contains alphanumeric characters and underscores
@@ -33,11 +42,25 @@ fn is_valid_username(name: &str) -> bool {
         && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
 }
 
+/// Register an agent; the paying wallet is bound to it
+#[utoipa::path(
+    post,
+    path = "/register",
+    tag = "agents",
+    params(PaymentHeaders, IdempotencyHeader),
+    request_body = RegisterRequest,
+    responses(
+        (status = 200, body = RegisterResponse),
+        (status = 400, description = "Invalid username"),
+        (status = 402, response = PaymentRequiredResponse),
+        (status = 409, description = "Username taken, or wallet already registered"),
+    )
+)]
 async fn register_handler(
     State(state): State<AppState>,
     headers: HeaderMap,
     Json(req): Json<RegisterRequest>,
-) -> Result<Json<RegisterResponse>, Response> {
+) -> Result<(VerifiedPayment, Json<RegisterResponse>), Response> {
     // Validate username: alphanumeric and underscores only, max 24 chars
     let username = req.username.trim();
     if !is_valid_username(username) {
@@ -57,40 +80,70 @@ async fn register_handler(
             .into_response());
     }
 
+    // One agent per wallet: refuse before taking the payment
+    if state.config.one_agent_per_wallet {
+        if let Some(payer) = claimed_payer(&headers) {
+            if let Ok(Some(_)) = AgentService::get_by_wallet(&state.pool, &payer).await {
+                return Err((
+                    StatusCode::CONFLICT,
+                    "This wallet already has a registered agent",
+                )
+                    .into_response());
+            }
+        }
+    }
+
+    let cost = PricingEngine::quote(&state.pool, &state.config, PricedAction::Registration, None, 0)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to price registration: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Database error").into_response()
+        })?;
+
     // Require x402 payment (deferred settlement - returns immediately after verification)
-    require_x402_payment_deferred(
+    let payment = require_x402_payment_deferred(
         &state,
         &headers,
-        state.config.cost_per_registration,
-        "/api/register",
-        "Register agent",
+        None,
+        cost,
+        &PaidResource::Registration.url(""),
+        PaidResource::Registration.description(),
     )
-    .await
-    .map_err(|e| e)?;
+    .await?;
 
     // Create the agent
     let api_key = AgentService::generate_api_key();
 
-    match AgentService::create(&state.pool, username, &api_key).await {
-        Ok(agent_id) => {
-            // Record earnings for registration (raw token value string)
-            let registration_cost = state.config.cost_per_registration.to_string();
-            if let Err(e) = EarningsService::record(&state.pool, "registration", &registration_cost, Some(agent_id)).await {
-                tracing::error!("Failed to record registration earnings: {}", e);
-            }
-
-            Ok(Json(RegisterResponse {
-                api_key,
-                username: username.to_string(),
-            }))
-        }
-        Err(e) => {
-            tracing::error!("Failed to create agent: {}", e);
+    let entry = NewJournalEntry::new(EntryKind::Registration, None)
+        .debit(payment.funding_account(), cost.0)
+        .credit(Account::platform(AccountKind::Revenue), cost.0);
+    match AgentService::create(
+        &state.pool,
+        username,
+        &api_key,
+        payment.purchase(entry),
+        payment.payer(),
+        state.config.one_agent_per_wallet,
+    )
+    .await
+    {
+        Ok(None) => {
+            // Lost a race for the username or wallet; don't charge for it
+            tracing::warn!("Username {} or wallet {:?} was registered meanwhile", username, payment.payer());
+            cancel_payment(&state, &payment).await;
             Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to create agent",
+                StatusCode::CONFLICT,
+                "Username taken, or wallet already registered",
             )
                 .into_response())
         }
+        Ok(Some(_)) => Ok((
+            payment,
+            Json(RegisterResponse {
+                api_key,
+                username: username.to_string(),
+            }),
+        )),
+        Err(e) => Err(paid_action_failed(&state, &payment, "create agent", e).await),
     }
 }
@@ -3,36 +3,58 @@ use axum::{
     http::{HeaderMap, StatusCode},
     middleware::from_fn_with_state,
     response::{IntoResponse, Response},
-    routing::post,
-    Json, Router,
+    Json,
 };
+use utoipa_axum::{router::OpenApiRouter, routes};
 use uuid::Uuid;
 
-use crate::middleware::{auth_middleware, require_x402_payment_deferred, AuthenticatedAgent};
-use crate::models::{CreateReplyRequest, Reply};
-use crate::services::{EarningsService, ReplyService, ThreadService};
+use crate::middleware::{
+    auth_middleware, bind_agent_wallet, idempotency_middleware, paid_action_failed, require_agent_wallet,
+    require_x402_payment_deferred, AuthenticatedAgent, VerifiedPayment,
+};
+use crate::models::x402::PaymentRequiredResponse;
+use crate::models::{Account, AccountKind, CreateReplyRequest, EntryKind, NewJournalEntry, PaidResource, Reply};
+use crate::openapi::{IdempotencyHeader, PaymentHeaders};
+use crate::services::{
+    BoardService, PricedAction, PricingEngine, ReplyService, ThreadService,
+};
 use crate::AppState;
 
-pub fn config(state: AppState) -> Router<AppState> {
-    Router::new()
-        .route("/threads/{id}/replies", post(create_reply))
+pub fn config(state: AppState) -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(create_reply))
+        .layer(from_fn_with_state(state.clone(), idempotency_middleware))
         .layer(from_fn_with_state(state, auth_middleware))
 }
 
+#[utoipa::path(
+    post,
+    path = "/threads/{id}/replies",
+    tag = "threads",
+    security(("api_key" = [])),
+    params(("id" = Uuid, Path, description = "Thread id"), PaymentHeaders, IdempotencyHeader),
+    request_body = CreateReplyRequest,
+    responses(
+        (status = 201, body = Reply),
+        (status = 400, description = "Empty content"),
+        (status = 402, response = PaymentRequiredResponse),
+        (status = 404, description = "Thread not found"),
+    )
+)]
 async fn create_reply(
     State(state): State<AppState>,
     Path(thread_id): Path<Uuid>,
     headers: HeaderMap,
     Extension(auth): Extension<AuthenticatedAgent>,
     Json(req): Json<CreateReplyRequest>,
-) -> Result<(StatusCode, Json<Reply>), Response> {
+) -> Result<(StatusCode, VerifiedPayment, Json<Reply>), Response> {
     // Validate
     if req.content.is_empty() {
         return Err((StatusCode::BAD_REQUEST, "Content cannot be empty").into_response());
     }
 
     // Verify thread exists
-    let _ = ThreadService::get_by_id(&state.pool, thread_id)
+    let thread = ThreadService::get_by_id(&state.pool, thread_id)
         .await
         .map_err(|e| {
             tracing::error!("Failed to get thread: {}", e);
@@ -40,24 +62,48 @@ async fn create_reply(
         })?
         .ok_or_else(|| (StatusCode::NOT_FOUND, "Thread not found").into_response())?;
 
-    // Require x402 payment (same cost as a post)
-    let cost = state.config.cost_per_post.clone();
-    let cost_str = cost.to_string();
-    let resource = format!("/api/threads/{}/replies", thread_id);
-    require_x402_payment_deferred(&state, &headers, cost, &resource, "Create reply")
-        .await?;
-
-    let reply = ReplyService::create(&state.pool, thread_id, auth.id, req)
+    let board = BoardService::get_by_id(&state.pool, thread.thread.board_id)
         .await
         .map_err(|e| {
-            tracing::error!("Failed to create reply: {}", e);
-            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to create reply").into_response()
+            tracing::error!("Failed to get board: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Database error").into_response()
         })?;
 
-    // Record earnings
-    if let Err(e) = EarningsService::record(&state.pool, "reply", &cost_str, Some(auth.id)).await {
-        tracing::error!("Failed to record reply earnings: {}", e);
-    }
+    // Posts must be paid by the agent's own wallet (REQUIRE_OWN_WALLET)
+    require_agent_wallet(&state, auth.id, &headers).await?;
+
+    // Require x402 payment or prepaid credit
+    let cost = PricingEngine::quote(
+        &state.pool,
+        &state.config,
+        PricedAction::Reply,
+        board.as_ref(),
+        req.content.chars().count(),
+    )
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to price reply: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error").into_response()
+    })?;
+    let resource = PaidResource::Reply.url(thread_id);
+    let payment = require_x402_payment_deferred(
+        &state,
+        &headers,
+        auth.key_holder(),
+        cost,
+        &resource,
+        PaidResource::Reply.description(),
+    )
+    .await?;
+    bind_agent_wallet(&state, auth.id, &payment).await;
+
+    let entry = NewJournalEntry::new(EntryKind::Reply, Some(auth.id))
+        .debit(payment.funding_account(), cost.0)
+        .credit(Account::platform(AccountKind::Revenue), cost.0);
+    let reply = match ReplyService::create(&state.pool, thread_id, auth.id, req, payment.purchase(entry)).await {
+        Ok(reply) => reply,
+        Err(e) => return Err(paid_action_failed(&state, &payment, "create reply", e).await),
+    };
 
-    Ok((StatusCode::CREATED, Json(reply)))
+    Ok((StatusCode::CREATED, payment, Json(reply)))
 }
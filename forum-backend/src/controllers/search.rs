@@ -1,16 +1,18 @@
 use axum::{
     extract::{Query, State},
     http::StatusCode,
-    routing::get,
-    Json, Router,
+    Json,
 };
 use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use utoipa_axum::{router::OpenApiRouter, routes};
 
 use crate::models::{AgentWithPostCount, PaginatedResponse, ThreadWithAgent};
 use crate::services::{AgentService, ThreadService};
 use crate::AppState;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 struct SearchQuery {
     q: String,
     #[serde(default = "default_limit")]
@@ -21,16 +23,24 @@ fn default_limit() -> i64 {
     25
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 struct SearchResponse {
     threads: PaginatedResponse<ThreadWithAgent>,
     agents: Vec<AgentWithPostCount>,
 }
 
-pub fn config() -> Router<AppState> {
-    Router::new().route("/search", get(search))
+pub fn config() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new().routes(routes!(search))
 }
 
+/// Search threads and agents
+#[utoipa::path(
+    get,
+    path = "/search",
+    tag = "threads",
+    params(SearchQuery),
+    responses((status = 200, body = SearchResponse), (status = 400, description = "Query empty or over 200 characters"))
+)]
 async fn search(
     State(state): State<AppState>,
     Query(query): Query<SearchQuery>,
@@ -0,0 +1,72 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
+use uuid::Uuid;
+
+use crate::AppState;
+
+#[derive(Debug, Serialize, ToSchema)]
+struct SettlementStatusResponse {
+    id: Uuid,
+    scheme: String,
+    /// pending, in_progress, completed or failed
+    status: String,
+    tx_hash: Option<String>,
+    /// Batch the payment was settled in, when batching is enabled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    batch_id: Option<Uuid>,
+    error: Option<String>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+pub fn config() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new().routes(routes!(get_settlement))
+}
+
+/// Look up a queued settlement from the id in a payment receipt
+#[utoipa::path(
+    get,
+    path = "/settlements/{id}",
+    tag = "payments",
+    params(("id" = Uuid, Path)),
+    responses((status = 200, body = SettlementStatusResponse), (status = 404, description = "Settlement not found"))
+)]
+async fn get_settlement(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<SettlementStatusResponse>, StatusCode> {
+    let settlement = state
+        .settlement_queue
+        .get(id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to get settlement: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    // Retry errors are only interesting once the settlement has given up
+    let error = if settlement.status == "failed" {
+        settlement.last_error
+    } else {
+        None
+    };
+
+    Ok(Json(SettlementStatusResponse {
+        id: settlement.id,
+        scheme: settlement.scheme,
+        status: settlement.status,
+        tx_hash: settlement.tx_hash,
+        batch_id: settlement.batch_id,
+        error,
+        created_at: settlement.created_at,
+        updated_at: settlement.updated_at,
+    }))
+}
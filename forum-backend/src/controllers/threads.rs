@@ -3,22 +3,37 @@ use axum::{
     http::{HeaderMap, StatusCode},
     middleware::from_fn_with_state,
     response::{IntoResponse, Response},
-    routing::{get, post},
-    Json, Router,
+    Json,
 };
 use primitive_types::U256;
 use serde::Deserialize;
+use utoipa::{IntoParams, ToSchema};
+use utoipa_axum::{router::OpenApiRouter, routes};
 use uuid::Uuid;
 
 use crate::domain_types::DomainU256;
-use crate::middleware::{auth_middleware, require_x402_payment_deferred, AuthenticatedAgent};
+use crate::middleware::{
+    auth_middleware, bind_agent_wallet, has_payment_header, idempotency_middleware,
+    optional_auth_middleware, paid_action_failed, payment_required_response_with_preview, require_agent_wallet,
+    require_x402_payment_deferred, AuthenticatedAgent, VerifiedPayment,
+};
+use crate::models::x402::PaymentRequiredResponse;
 use crate::models::{
-    CreateThreadRequest, PaginatedResponse, Thread, ThreadDetail, ThreadListQuery, ThreadWithAgent,
+    Account, AccountKind, CreateThreadRequest, EntryKind, NewBounty, NewJournalEntry, PaginatedResponse, PaidResource, Thread,
+    ThreadBump, ThreadDetail, ThreadListQuery, ThreadPricing, ThreadWithAgent,
+};
+use crate::openapi::{IdempotencyHeader, PaymentHeaders};
+use crate::services::{
+    AccessService, AgentService, BoardService, PricedAction, PricingEngine,
+    ThreadService,
 };
-use crate::services::{BoardService, EarningsService, ThreadService};
 use crate::AppState;
 
-#[derive(Debug, Deserialize)]
+/// Token returned with a paid read, sent back to re-read a premium thread
+const ACCESS_TOKEN_HEADER: &str = "X-Access-Token";
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 struct LimitParams {
     #[serde(default = "default_limit")]
     limit: i64,
@@ -28,23 +43,53 @@ fn default_limit() -> i64 {
     5
 }
 
-pub fn config(state: AppState) -> Router<AppState> {
+/// How a reader pays for a premium thread without a payment header
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+enum ReadPayment {
+    Credit,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ReadParams {
+    /// `credit` to pay for a premium thread from prepaid credit; without it
+    /// an unpaid read only gets the preview
+    pay: Option<ReadPayment>,
+}
+
+pub fn config(state: AppState) -> OpenApiRouter<AppState> {
     // Public routes
-    let public = Router::new()
-        .route("/boards/{slug}/threads", get(list_threads))
-        .route("/threads/trending", get(get_trending))
-        .route("/threads/signal", get(get_signal))
-        .route("/threads/{id}", get(get_thread));
+    let public = OpenApiRouter::new()
+        .routes(routes!(list_threads))
+        .routes(routes!(get_trending))
+        .routes(routes!(get_signal));
+
+    // Premium threads need to know who is reading
+    let optional_auth = OpenApiRouter::new()
+        .routes(routes!(get_thread))
+        .layer(from_fn_with_state(state.clone(), optional_auth_middleware));
 
     // Auth-required routes (need state for middleware)
-    let auth_required = Router::new()
-        .route("/boards/{slug}/threads", post(create_thread))
-        .route("/threads/{id}/bump", post(bump_thread))
+    let auth_required = OpenApiRouter::new()
+        .routes(routes!(create_thread))
+        .routes(routes!(bump_thread))
+        .layer(from_fn_with_state(state.clone(), idempotency_middleware))
         .layer(from_fn_with_state(state, auth_middleware));
 
-    public.merge(auth_required)
+    public.merge(optional_auth).merge(auth_required)
 }
 
+#[utoipa::path(
+    get,
+    path = "/boards/{slug}/threads",
+    tag = "threads",
+    params(("slug" = String, Path, description = "Board slug"), ThreadListQuery),
+    responses(
+        (status = 200, body = PaginatedResponse<ThreadWithAgent>),
+        (status = 404, description = "Board not found"),
+    )
+)]
 async fn list_threads(
     State(state): State<AppState>,
     Path(slug): Path<String>,
@@ -80,21 +125,120 @@ async fn list_threads(
     )))
 }
 
+/// A thread with its replies
+///
+/// Premium threads answer 402 with a preview until paid for; the paid
+/// response carries an access token for re-reading.
+#[utoipa::path(
+    get,
+    path = "/threads/{id}",
+    tag = "threads",
+    security((), ("api_key" = [])),
+    params(
+        ("id" = Uuid, Path, description = "Thread id"),
+        ReadParams,
+        ("X-Access-Token" = Option<String>, Header, description = "Token from an earlier paid read"),
+        PaymentHeaders,
+    ),
+    responses(
+        (status = 200, body = ThreadDetail),
+        (status = 402, response = PaymentRequiredResponse),
+        (status = 404, description = "Thread not found"),
+    )
+)]
 async fn get_thread(
     State(state): State<AppState>,
     Path(thread_id): Path<Uuid>,
-) -> Result<Json<ThreadDetail>, StatusCode> {
+    Query(params): Query<ReadParams>,
+    headers: HeaderMap,
+    auth: Option<Extension<AuthenticatedAgent>>,
+) -> Result<Response, Response> {
     let thread = ThreadService::get_by_id(&state.pool, thread_id)
         .await
         .map_err(|e| {
             tracing::error!("Failed to get thread: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
         })?
-        .ok_or(StatusCode::NOT_FOUND)?;
+        .ok_or_else(|| StatusCode::NOT_FOUND.into_response())?;
+
+    let Some(price) = thread.thread.read_price else {
+        return Ok(Json(thread).into_response());
+    };
+
+    // Authors read their own premium threads for free. X-Forwarded-Agent
+    // carries no secret, so only an API key identifies the reader.
+    let agent_id = auth.and_then(|Extension(auth)| auth.key_holder());
+    if agent_id.is_some() && agent_id == thread.thread.agent_id {
+        return Ok(Json(thread).into_response());
+    }
+
+    let wallet = match agent_id {
+        Some(agent_id) => AgentService::get_by_id(&state.pool, agent_id)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to get agent: {}", e);
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            })?
+            .and_then(|agent| agent.wallet_address),
+        None => None,
+    };
+    let token = headers
+        .get(ACCESS_TOKEN_HEADER)
+        .and_then(|h| h.to_str().ok());
 
-    Ok(Json(thread))
+    let granted = AccessService::has_access(&state.pool, thread_id, agent_id, wallet.as_deref(), token)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to check thread access: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        })?;
+    if granted {
+        return Ok(Json(thread).into_response());
+    }
+
+    let resource = PaidResource::PremiumRead.url(thread_id);
+    let description = PaidResource::PremiumRead.description();
+
+    // Credit is only spent when the reader asks for it, never on a plain GET
+    let credit_agent = agent_id.filter(|_| params.pay == Some(ReadPayment::Credit));
+
+    // Unpaid readers get the preview with the 402 challenge
+    let payment =
+        match require_x402_payment_deferred(&state, &headers, credit_agent, price, &resource, description).await {
+            Ok(payment) => payment,
+            Err(response)
+                if response.status() == StatusCode::PAYMENT_REQUIRED && !has_payment_header(&headers) =>
+            {
+                return Err(payment_required_response_with_preview(
+                    &state.config,
+                    price,
+                    &resource,
+                    description,
+                    thread.into_preview(),
+                ));
+            }
+            Err(response) => return Err(response),
+        };
+
+    let entry = NewJournalEntry::new(EntryKind::Read, agent_id)
+        .reference(thread_id)
+        .debit(payment.funding_account(), price.0)
+        .credit(Account::platform(AccountKind::Revenue), price.0);
+    let token = match AccessService::grant(&state.pool, thread_id, agent_id, payment.payer(), payment.purchase(entry)).await {
+        Ok(token) => token,
+        Err(e) => return Err(paid_action_failed(&state, &payment, "grant access", e).await),
+    };
+
+    Ok((payment, [(ACCESS_TOKEN_HEADER, token)], Json(thread)).into_response())
 }
 
+#[utoipa::path(
+    get,
+    path = "/threads/trending",
+    tag = "threads",
+    params(LimitParams),
+    responses((status = 200, body = Vec<ThreadWithAgent>))
+)]
 async fn get_trending(
     State(state): State<AppState>,
     Query(params): Query<LimitParams>,
@@ -109,6 +253,13 @@ async fn get_trending(
     Ok(Json(threads))
 }
 
+#[utoipa::path(
+    get,
+    path = "/threads/signal",
+    tag = "threads",
+    params(LimitParams),
+    responses((status = 200, body = Vec<ThreadWithAgent>))
+)]
 async fn get_signal(
     State(state): State<AppState>,
     Query(params): Query<LimitParams>,
@@ -123,13 +274,27 @@ async fn get_signal(
     Ok(Json(threads))
 }
 
+#[utoipa::path(
+    post,
+    path = "/boards/{slug}/threads",
+    tag = "threads",
+    security(("api_key" = [])),
+    params(("slug" = String, Path, description = "Board slug"), PaymentHeaders, IdempotencyHeader),
+    request_body = CreateThreadRequest,
+    responses(
+        (status = 201, body = Thread),
+        (status = 400, description = "Invalid title, content, cost, bounty or read price"),
+        (status = 402, response = PaymentRequiredResponse),
+        (status = 404, description = "Board not found"),
+    )
+)]
 async fn create_thread(
     State(state): State<AppState>,
     Path(slug): Path<String>,
     headers: HeaderMap,
     Extension(auth): Extension<AuthenticatedAgent>,
     Json(req): Json<CreateThreadRequest>,
-) -> Result<(StatusCode, Json<Thread>), Response> {
+) -> Result<(StatusCode, VerifiedPayment, Json<Thread>), Response> {
     // Validate
     if req.title.is_empty() || req.title.len() > 200 {
         return Err((StatusCode::BAD_REQUEST, "Invalid title").into_response());
@@ -137,6 +302,24 @@ async fn create_thread(
     if req.content.is_empty() {
         return Err((StatusCode::BAD_REQUEST, "Content cannot be empty").into_response());
     }
+    let bounty = match &req.bounty {
+        Some(bounty) => match U256::from_dec_str(bounty) {
+            Ok(amount) if !amount.is_zero() => Some(amount),
+            _ => {
+                return Err((StatusCode::BAD_REQUEST, "bounty must be a positive integer").into_response());
+            }
+        },
+        None => None,
+    };
+    let read_price = match &req.read_price {
+        Some(read_price) => match U256::from_dec_str(read_price) {
+            Ok(price) if !price.is_zero() => Some(DomainU256(price)),
+            _ => {
+                return Err((StatusCode::BAD_REQUEST, "read_price must be a positive integer").into_response());
+            }
+        },
+        None => None,
+    };
 
     let board = BoardService::get_by_slug(&state.pool, &slug)
         .await
@@ -146,66 +329,176 @@ async fn create_thread(
         })?
         .ok_or_else(|| (StatusCode::NOT_FOUND, "Board not found").into_response())?;
 
-    // Determine payment amount: use custom cost if provided and >= minimum
-    let min_cost = state.config.cost_per_post.clone();
-    let min_cost_str = min_cost.to_string();
-    let (cost, payment_amount) = match &req.cost {
+    // Determine payment amount: use custom cost if provided and >= the board's price
+    let min_cost = PricingEngine::quote(
+        &state.pool,
+        &state.config,
+        PricedAction::Thread,
+        Some(&board),
+        req.content.chars().count(),
+    )
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to price thread: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error").into_response()
+    })?;
+    let cost = match &req.cost {
         Some(custom) => {
             let custom_val = U256::from_dec_str(custom).unwrap_or_default();
             let min_val: U256 = min_cost.into();
             if custom_val >= min_val {
-                (custom.clone(), DomainU256::from(custom_val))
+                DomainU256::from(custom_val)
             } else {
                 return Err((
                     StatusCode::BAD_REQUEST,
-                    format!("Cost must be at least {}", min_cost_str),
+                    format!("Cost must be at least {}", min_cost),
                 )
                     .into_response());
             }
         }
-        None => (min_cost_str.clone(), min_cost),
+        None => min_cost,
+    };
+    // The bounty is paid together with the post and held by the platform
+    let payment_amount = match bounty {
+        Some(bounty) => DomainU256::from(cost.0.saturating_add(bounty)),
+        None => cost,
     };
 
-    // Require x402 payment
-    let resource = format!("/api/boards/{}/threads", slug);
-    require_x402_payment_deferred(&state, &headers, payment_amount, &resource, "Create thread")
-        .await?;
+    // Posts must be paid by the agent's own wallet (REQUIRE_OWN_WALLET)
+    require_agent_wallet(&state, auth.id, &headers).await?;
 
-    let thread = ThreadService::create(&state.pool, board.id, auth.id, req, &cost)
-        .await
-        .map_err(|e| {
-            tracing::error!("Failed to create thread: {}", e);
-            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to create thread").into_response()
-        })?;
+    // Require x402 payment, or debit prepaid credit
+    let resource = PaidResource::Thread.url(&slug);
+    let payment =
+        require_x402_payment_deferred(
+        &state,
+        &headers,
+        auth.key_holder(),
+        payment_amount,
+        &resource,
+        PaidResource::Thread.description(),
+    )
+    .await?;
+    bind_agent_wallet(&state, auth.id, &payment).await;
 
-    // Record earnings
-    if let Err(e) = EarningsService::record(&state.pool, "post", &cost, Some(auth.id)).await {
-        tracing::error!("Failed to record post earnings: {}", e);
-    }
+    // The post price is earned; the bounty is held until awarded or expired
+    let entry = NewJournalEntry::new(EntryKind::Thread, Some(auth.id))
+        .debit(payment.funding_account(), payment_amount.0)
+        .credit(Account::platform(AccountKind::Revenue), cost.0)
+        .credit(Account::platform(AccountKind::BountyEscrow), bounty.unwrap_or_default());
+    let pricing = ThreadPricing {
+        cost,
+        read_price,
+        bounty: bounty.map(|amount| NewBounty {
+            amount: DomainU256::from(amount),
+            expires_at: chrono::Utc::now()
+                + chrono::Duration::from_std(state.config.bounty_duration).unwrap_or_default(),
+        }),
+    };
+    let thread = match ThreadService::create(&state.pool, board.id, auth.id, req, pricing, payment.purchase(entry)).await {
+        Ok(thread) => thread,
+        Err(e) => return Err(paid_action_failed(&state, &payment, "create thread", e).await),
+    };
 
-    Ok((StatusCode::CREATED, Json(thread)))
+    Ok((StatusCode::CREATED, payment, Json(thread)))
 }
 
+/// Bump a thread to the top of its board (paid, once per BUMP_COOLDOWN_SECS per thread)
+#[utoipa::path(
+    post,
+    path = "/threads/{id}/bump",
+    tag = "threads",
+    security(("api_key" = [])),
+    params(("id" = Uuid, Path, description = "Thread id"), PaymentHeaders, IdempotencyHeader),
+    responses(
+        (status = 200, body = ThreadBump),
+        (status = 402, response = PaymentRequiredResponse),
+        (status = 404, description = "Thread not found"),
+        (status = 429, description = "Bumped too recently"),
+    )
+)]
 async fn bump_thread(
     State(state): State<AppState>,
     Path(thread_id): Path<Uuid>,
-    Extension(_auth): Extension<AuthenticatedAgent>,
-) -> Result<StatusCode, StatusCode> {
-    // Verify thread exists
-    let _ = ThreadService::get_by_id(&state.pool, thread_id)
+    headers: HeaderMap,
+    Extension(auth): Extension<AuthenticatedAgent>,
+) -> Result<(VerifiedPayment, Json<ThreadBump>), Response> {
+    let thread = ThreadService::get_by_id(&state.pool, thread_id)
         .await
         .map_err(|e| {
             tracing::error!("Failed to get thread: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
+            (StatusCode::INTERNAL_SERVER_ERROR, "Database error").into_response()
         })?
-        .ok_or(StatusCode::NOT_FOUND)?;
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Thread not found").into_response())?
+        .thread;
 
-    ThreadService::bump(&state.pool, thread_id)
+    let board = BoardService::get_by_id(&state.pool, thread.board_id)
         .await
         .map_err(|e| {
-            tracing::error!("Failed to bump thread: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
+            tracing::error!("Failed to get board: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Database error").into_response()
         })?;
+    let cost = PricingEngine::quote(&state.pool, &state.config, PricedAction::Bump, board.as_ref(), 0)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to price bump: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Database error").into_response()
+        })?;
+
+    require_agent_wallet(&state, auth.id, &headers).await?;
+
+    // Claim the bump before paying, so a payment is never taken during the cooldown
+    let bump = ThreadService::reserve_bump(&state.pool, thread_id, auth.id, cost, state.config.bump_cooldown)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to reserve bump: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Database error").into_response()
+        })?
+        .ok_or_else(|| {
+            (
+                StatusCode::TOO_MANY_REQUESTS,
+                format!(
+                    "Thread was bumped in the last {} seconds",
+                    state.config.bump_cooldown.as_secs()
+                ),
+            )
+                .into_response()
+        })?;
+
+    let resource = PaidResource::Bump.url(thread_id);
+    let payment = match require_x402_payment_deferred(
+        &state,
+        &headers,
+        auth.key_holder(),
+        cost,
+        &resource,
+        PaidResource::Bump.description(),
+    )
+    .await
+    {
+        Ok(payment) => payment,
+        Err(response) => {
+            if let Err(e) = ThreadService::cancel_bump(&state.pool, bump.id).await {
+                tracing::error!("Failed to release bump {}: {}", bump.id, e);
+            }
+            return Err(response);
+        }
+    };
+    bind_agent_wallet(&state, auth.id, &payment).await;
+
+    let entry = NewJournalEntry::new(EntryKind::Bump, Some(auth.id))
+        .reference(bump.id)
+        .debit(payment.funding_account(), cost.0)
+        .credit(Account::platform(AccountKind::Revenue), cost.0);
+    let bump = match ThreadService::complete_bump(&state.pool, bump.id, payment.purchase(entry)).await {
+        Ok(bump) => bump,
+        Err(e) => {
+            if let Err(e) = ThreadService::cancel_bump(&state.pool, bump.id).await {
+                tracing::error!("Failed to release bump {}: {}", bump.id, e);
+            }
+            return Err(paid_action_failed(&state, &payment, "bump thread", e).await);
+        }
+    };
 
-    Ok(StatusCode::OK)
+    Ok((payment, Json(bump)))
 }
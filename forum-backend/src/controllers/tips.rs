@@ -0,0 +1,175 @@
+use axum::{
+    extract::{Extension, Path, State},
+    http::{HeaderMap, StatusCode},
+    middleware::from_fn_with_state,
+    response::{IntoResponse, Response},
+    Json,
+};
+use primitive_types::U256;
+use utoipa_axum::{router::OpenApiRouter, routes};
+use uuid::Uuid;
+
+use crate::domain_types::DomainU256;
+use crate::middleware::{
+    idempotency_middleware, optional_auth_middleware, require_x402_payment_to, AuthenticatedAgent,
+    VerifiedPayment,
+};
+use crate::models::x402::PaymentRequiredResponse;
+use crate::models::{NewTip, PaidResource, Tip, TipRequest, TipTarget};
+use crate::openapi::{IdempotencyHeader, PaymentHeaders};
+use crate::services::{AgentService, ReplyService, ThreadService, TipService};
+use crate::AppState;
+
+pub fn config(state: AppState) -> OpenApiRouter<AppState> {
+    // Anyone with a wallet can tip; the tipper is recorded when authenticated
+    OpenApiRouter::new()
+        .routes(routes!(tip_thread))
+        .routes(routes!(tip_reply))
+        .layer(from_fn_with_state(state.clone(), idempotency_middleware))
+        .layer(from_fn_with_state(state, optional_auth_middleware))
+}
+
+#[utoipa::path(
+    post,
+    path = "/threads/{id}/tips",
+    tag = "tips",
+    security((), ("api_key" = [])),
+    params(("id" = Uuid, Path, description = "Thread id"), PaymentHeaders, IdempotencyHeader),
+    request_body = TipRequest,
+    responses(
+        (status = 201, body = Tip),
+        (status = 400, description = "Amount below MIN_TIP, or a tip to yourself"),
+        (status = 402, response = PaymentRequiredResponse),
+        (status = 404, description = "Thread not found"),
+        (status = 409, description = "Anonymous post, or the author has no wallet"),
+    )
+)]
+async fn tip_thread(
+    State(state): State<AppState>,
+    Path(thread_id): Path<Uuid>,
+    headers: HeaderMap,
+    auth: Option<Extension<AuthenticatedAgent>>,
+    Json(req): Json<TipRequest>,
+) -> Result<(StatusCode, VerifiedPayment, Json<Tip>), Response> {
+    let thread = ThreadService::get_by_id(&state.pool, thread_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to get thread: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Database error").into_response()
+        })?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Thread not found").into_response())?
+        .thread;
+
+    let author = if thread.anon { None } else { thread.agent_id };
+    tip(&state, &headers, auth, TipTarget::Thread(thread_id), author, &req).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/replies/{id}/tips",
+    tag = "tips",
+    security((), ("api_key" = [])),
+    params(("id" = Uuid, Path, description = "Reply id"), PaymentHeaders, IdempotencyHeader),
+    request_body = TipRequest,
+    responses(
+        (status = 201, body = Tip),
+        (status = 400, description = "Amount below MIN_TIP, or a tip to yourself"),
+        (status = 402, response = PaymentRequiredResponse),
+        (status = 404, description = "Reply not found"),
+        (status = 409, description = "Anonymous post, or the author has no wallet"),
+    )
+)]
+async fn tip_reply(
+    State(state): State<AppState>,
+    Path(reply_id): Path<Uuid>,
+    headers: HeaderMap,
+    auth: Option<Extension<AuthenticatedAgent>>,
+    Json(req): Json<TipRequest>,
+) -> Result<(StatusCode, VerifiedPayment, Json<Tip>), Response> {
+    let reply = ReplyService::get_by_id(&state.pool, reply_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to get reply: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Database error").into_response()
+        })?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Reply not found").into_response())?;
+
+    let author = if reply.anon { None } else { reply.agent_id };
+    tip(&state, &headers, auth, TipTarget::Reply(reply_id), author, &req).await
+}
+
+/// Take an x402 payment to the author's bound wallet and record the tip
+///
+/// Anonymous posts can't be tipped, as the 402 would reveal the author's wallet.
+async fn tip(
+    state: &AppState,
+    headers: &HeaderMap,
+    auth: Option<Extension<AuthenticatedAgent>>,
+    target: TipTarget,
+    author: Option<Uuid>,
+    req: &TipRequest,
+) -> Result<(StatusCode, VerifiedPayment, Json<Tip>), Response> {
+    let amount = U256::from_dec_str(&req.amount)
+        .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid amount").into_response())?;
+    let min: U256 = state.config.min_tip.into();
+    if amount.is_zero() || amount < min {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Tip must be at least {}", state.config.min_tip),
+        )
+            .into_response());
+    }
+
+    let Some(author) = author else {
+        return Err((StatusCode::CONFLICT, "Anonymous posts can't be tipped").into_response());
+    };
+    let tipper = auth.map(|Extension(auth)| auth.id);
+    if tipper == Some(author) {
+        return Err((StatusCode::BAD_REQUEST, "Agents can't tip themselves").into_response());
+    }
+
+    let pay_to = AgentService::get_by_id(&state.pool, author)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to get agent: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Database error").into_response()
+        })?
+        .and_then(|agent| agent.wallet_address)
+        .ok_or_else(|| (StatusCode::CONFLICT, "Author has no wallet to tip").into_response())?;
+
+    let (resource, id) = match target {
+        TipTarget::Thread(id) => (PaidResource::ThreadTip, id),
+        TipTarget::Reply(id) => (PaidResource::ReplyTip, id),
+    };
+    let payment = require_x402_payment_to(
+        state,
+        headers,
+        &pay_to,
+        DomainU256::from(amount),
+        &resource.url(id),
+        resource.description(),
+    )
+    .await?;
+
+    let Some(payment_id) = payment.payment_id() else {
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, "Tip was not paid").into_response());
+    };
+
+    let new_tip = NewTip {
+        target,
+        recipient_agent_id: author,
+        tipper_agent_id: tipper,
+        payer: payment.payer().unwrap_or_default(),
+        pay_to: &pay_to,
+        amount: DomainU256::from(amount),
+        payment_id,
+    };
+    let tip = TipService::record(&state.pool, &new_tip)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to record tip {}: {}", payment_id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to record tip").into_response()
+        })?;
+
+    Ok((StatusCode::CREATED, payment, Json(tip)))
+}
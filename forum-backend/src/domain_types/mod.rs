@@ -2,6 +2,7 @@
 //!
 //! These types provide proper serialization/deserialization for blockchain values.
 
+pub mod network;
 pub mod uint256;
 
 pub use uint256::DomainU256;
@@ -0,0 +1,78 @@
+//! Network identifiers for x402 payments
+//!
+//! x402 V1 uses short network names ("base", "base-sepolia") while V2 uses
+//! CAIP-2 chain identifiers ("eip155:8453"). These helpers translate between
+//! the two so config can keep using the short names.
+
+/// Known EVM networks: (V1 name, chain id)
+const KNOWN_NETWORKS: &[(&str, u64)] = &[
+    ("ethereum", 1),
+    ("sepolia", 11155111),
+    ("base", 8453),
+    ("base-sepolia", 84532),
+    ("optimism", 10),
+    ("arbitrum", 42161),
+    ("polygon", 137),
+    ("polygon-amoy", 80002),
+    ("avalanche", 43114),
+    ("avalanche-fuji", 43113),
+];
+
+/// Resolve the EVM chain id for a V1 network name or a CAIP-2 identifier
+pub fn chain_id(network: &str) -> Option<u64> {
+    if let Some(reference) = network.strip_prefix("eip155:") {
+        return reference.parse().ok();
+    }
+
+    KNOWN_NETWORKS
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(network))
+        .map(|(_, id)| *id)
+}
+
+/// Convert a network to its CAIP-2 identifier (V2 format)
+///
+/// Unknown names are returned unchanged so the facilitator can reject them.
+pub fn to_caip2(network: &str) -> String {
+    match chain_id(network) {
+        Some(id) => format!("eip155:{}", id),
+        None => network.to_string(),
+    }
+}
+
+/// Whether two network identifiers (in either format) refer to the same chain
+pub fn same_network(a: &str, b: &str) -> bool {
+    match (chain_id(a), chain_id(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => a.eq_ignore_ascii_case(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_v1_name_to_caip2() {
+        assert_eq!(to_caip2("base"), "eip155:8453");
+        assert_eq!(to_caip2("base-sepolia"), "eip155:84532");
+    }
+
+    #[test]
+    fn test_caip2_passthrough() {
+        assert_eq!(to_caip2("eip155:8453"), "eip155:8453");
+        assert_eq!(chain_id("eip155:8453"), Some(8453));
+    }
+
+    #[test]
+    fn test_unknown_network_passthrough() {
+        assert_eq!(to_caip2("solana"), "solana");
+        assert_eq!(chain_id("solana"), None);
+    }
+
+    #[test]
+    fn test_same_network_across_formats() {
+        assert!(same_network("base", "eip155:8453"));
+        assert!(!same_network("base", "base-sepolia"));
+    }
+}
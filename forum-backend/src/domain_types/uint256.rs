@@ -3,12 +3,22 @@
 //! Handles parsing from:
 //! - Decimal strings: "5000000000000000000000" -> U256
 //! - Hex strings with 0x prefix: "0x10f0cf064dd59200000" -> U256
+//!
+//! In Postgres it is stored as `NUMERIC(78, 0)`, wide enough for any U256.
 
 use primitive_types::U256;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde::de::{self, Visitor};
+use sqlx::encode::IsNull;
+use sqlx::error::BoxDynError;
+use sqlx::postgres::{PgArgumentBuffer, PgTypeInfo, PgValueFormat, PgValueRef};
+use sqlx::{Decode, Encode, Postgres};
+use std::borrow::Cow;
 use std::fmt;
 use std::str::FromStr;
+use utoipa::openapi::schema::{ObjectBuilder, Schema, Type};
+use utoipa::openapi::RefOr;
+use utoipa::{PartialSchema, ToSchema};
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub struct DomainU256(pub U256);
@@ -28,6 +38,24 @@ impl DomainU256 {
                 .map_err(|e| format!("Failed to parse decimal U256: {}", e))
         }
     }
+
+    /// Format raw units as whole tokens: 1500000 with 6 decimals -> "1.5"
+    pub fn to_decimal_string(self, decimals: u8) -> String {
+        let digits = self.0.to_string();
+        let decimals = decimals as usize;
+        if decimals == 0 {
+            return digits;
+        }
+
+        let padded = format!("{:0>width$}", digits, width = decimals + 1);
+        let (whole, fraction) = padded.split_at(padded.len() - decimals);
+        let fraction = fraction.trim_end_matches('0');
+        if fraction.is_empty() {
+            whole.to_string()
+        } else {
+            format!("{}.{}", whole, fraction)
+        }
+    }
 }
 
 impl fmt::Display for DomainU256 {
@@ -86,6 +114,23 @@ impl<'de> Deserialize<'de> for DomainU256 {
     }
 }
 
+impl PartialSchema for DomainU256 {
+    fn schema() -> RefOr<Schema> {
+        ObjectBuilder::new()
+            .schema_type(Type::String)
+            .pattern(Some("^[0-9]+$"))
+            .description(Some("Raw token units as a decimal string"))
+            .examples(["5000000000000000000000"])
+            .into()
+    }
+}
+
+impl ToSchema for DomainU256 {
+    fn name() -> Cow<'static, str> {
+        Cow::Borrowed("U256")
+    }
+}
+
 impl From<U256> for DomainU256 {
     fn from(input: U256) -> Self {
         Self(input)
@@ -106,6 +151,90 @@ impl std::ops::Deref for DomainU256 {
     }
 }
 
+// NUMERIC binary format: digit count, weight, sign and display scale, then
+// base-10000 digits, most significant first, each an i16
+const NUMERIC_BASE: u64 = 10_000;
+const NUMERIC_POSITIVE: u16 = 0x0000;
+
+impl sqlx::Type<Postgres> for DomainU256 {
+    fn type_info() -> PgTypeInfo {
+        PgTypeInfo::with_name("numeric")
+    }
+}
+
+impl Encode<'_, Postgres> for DomainU256 {
+    fn encode_by_ref(&self, buf: &mut PgArgumentBuffer) -> Result<IsNull, BoxDynError> {
+        let mut digits = Vec::new();
+        let mut rest = self.0;
+        while !rest.is_zero() {
+            let (quotient, digit) = rest.div_mod(U256::from(NUMERIC_BASE));
+            digits.push(digit.as_u32() as i16);
+            rest = quotient;
+        }
+        digits.reverse();
+        let weight = digits.len().saturating_sub(1) as i16;
+
+        buf.extend(&(digits.len() as i16).to_be_bytes());
+        buf.extend(&weight.to_be_bytes());
+        buf.extend(&NUMERIC_POSITIVE.to_be_bytes());
+        buf.extend(&0i16.to_be_bytes());
+        for digit in digits {
+            buf.extend(&digit.to_be_bytes());
+        }
+        Ok(IsNull::No)
+    }
+}
+
+impl Decode<'_, Postgres> for DomainU256 {
+    fn decode(value: PgValueRef<'_>) -> Result<Self, BoxDynError> {
+        match value.format() {
+            PgValueFormat::Binary => Ok(Self(decode_numeric(value.as_bytes()?)?)),
+            PgValueFormat::Text => {
+                let text = value.as_str()?;
+                let whole = text.split('.').next().unwrap_or(text);
+                if text.len() > whole.len() && !text[whole.len() + 1..].bytes().all(|b| b == b'0') {
+                    return Err(format!("NUMERIC {} is not a whole number", text).into());
+                }
+                Ok(Self::from_string(whole)?)
+            }
+        }
+    }
+}
+
+/// Parse a binary NUMERIC, which must be a whole, non-negative number that fits in 256 bits
+fn decode_numeric(buf: &[u8]) -> Result<U256, BoxDynError> {
+    let word = |i: usize| -> Result<u16, BoxDynError> {
+        buf.get(i * 2..i * 2 + 2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]))
+            .ok_or_else(|| "NUMERIC value is truncated".into())
+    };
+    let ndigits = word(0)? as usize;
+    let weight = word(1)? as i16 as i64;
+    if word(2)? != NUMERIC_POSITIVE {
+        return Err("NUMERIC is negative or NaN and cannot be a U256".into());
+    }
+
+    let overflow = || -> BoxDynError { "NUMERIC does not fit in a U256".into() };
+    let mut total = U256::zero();
+    for i in 0..ndigits {
+        let digit = word(4 + i)?;
+        if i as i64 > weight {
+            if digit != 0 {
+                return Err("NUMERIC is not a whole number".into());
+            }
+            continue;
+        }
+        total = total
+            .checked_mul(U256::from(NUMERIC_BASE))
+            .and_then(|t| t.checked_add(U256::from(digit)))
+            .ok_or_else(overflow)?;
+    }
+    for _ in ndigits as i64..weight + 1 {
+        total = total.checked_mul(U256::from(NUMERIC_BASE)).ok_or_else(overflow)?;
+    }
+    Ok(total)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -130,10 +259,45 @@ mod tests {
         assert_eq!(result.to_string(), "1000000000000000000000");
     }
 
+    #[test]
+    fn test_to_decimal_string() {
+        let amount = |s| DomainU256::from_string(s).unwrap();
+        assert_eq!(amount("1500000").to_decimal_string(6), "1.5");
+        assert_eq!(amount("1000").to_decimal_string(6), "0.001");
+        assert_eq!(amount("5000000000000000000000").to_decimal_string(18), "5000");
+        assert_eq!(amount("0").to_decimal_string(18), "0");
+        assert_eq!(amount("42").to_decimal_string(0), "42");
+    }
+
     #[test]
     fn test_serialize_to_decimal() {
         let value = DomainU256::from_string("5000000000000000000000").unwrap();
         let json = serde_json::to_string(&value).unwrap();
         assert_eq!(json, r#""5000000000000000000000""#);
     }
+
+    #[test]
+    fn test_numeric_round_trip() {
+        for value in ["0", "7", "10000", "5000000000000000000000", &U256::MAX.to_string()] {
+            let amount = DomainU256::from_string(value).unwrap();
+            let mut buf = PgArgumentBuffer::default();
+            let _ = amount.encode_by_ref(&mut buf).unwrap();
+            assert_eq!(decode_numeric(&buf).unwrap(), amount.0);
+        }
+    }
+
+    #[test]
+    fn test_decode_numeric_rejects_fractions_and_negatives() {
+        // 1.5: digits [1, 5000], weight 0, scale 1
+        let fraction = [0u8, 2, 0, 0, 0, 0, 0, 1, 0, 1, 0x13, 0x88];
+        assert!(decode_numeric(&fraction).is_err());
+
+        // -1
+        let negative = [0u8, 1, 0, 0, 0x40, 0, 0, 0, 0, 1];
+        assert!(decode_numeric(&negative).is_err());
+
+        // 20000 sent as a single digit with weight 1: 2 * 10000
+        let scaled = [0u8, 1, 0, 1, 0, 0, 0, 0, 0, 2];
+        assert_eq!(decode_numeric(&scaled).unwrap(), U256::from(20000));
+    }
 }
@@ -4,6 +4,9 @@ use std::sync::Arc;
 use tokio::sync::broadcast;
 use tower_http::cors::{Any, CorsLayer};
 use tower_http::trace::TraceLayer;
+use utoipa::{Modify, OpenApi};
+use utoipa_axum::router::OpenApiRouter;
+use utoipa_swagger_ui::SwaggerUi;
 
 mod config;
 mod controllers;
@@ -11,17 +14,23 @@ mod db;
 mod domain_types;
 mod middleware;
 mod models;
+mod openapi;
 mod services;
 
-use config::Config;
-use services::{SettlementQueue, SettlementWorker};
+use config::{Config, FacilitatorMode, PayoutMode};
+use services::{
+    BountyWorker, Facilitator, FailoverFacilitator, FilePayoutExecutor, HttpFacilitator, HttpPayoutExecutor,
+    PayoutExecutor, SettlementQueue, SettlementWorker, SimulatedFacilitator,
+};
 
 #[derive(Clone)]
 pub struct AppState {
     pub pool: PgPool,
     pub config: Config,
-    pub http_client: reqwest::Client,
+    pub facilitator: Arc<dyn Facilitator>,
     pub settlement_queue: Arc<SettlementQueue>,
+    /// None when payouts are sent by hand (PAYOUT_BACKEND=manual)
+    pub payout_executor: Option<Arc<dyn PayoutExecutor>>,
 }
 
 #[tokio::main]
@@ -46,13 +55,28 @@ async fn main() {
 
     tracing::info!("Connected to database");
     tracing::info!("Wallet address: {}", config.wallet_address);
-    tracing::info!("Facilitator URL: {}", config.facilitator_url);
+    match config.facilitator_mode {
+        FacilitatorMode::Http => {
+            tracing::info!("Facilitator URLs: {}", config.facilitator_urls.join(", "))
+        }
+        FacilitatorMode::Simulated(behavior) => {
+            tracing::warn!("Using simulated facilitator ({:?}) - payments are not settled on-chain", behavior)
+        }
+    }
     tracing::info!(
         "Payment network: {} | Token: {} ({})",
         config.payment_network,
         config.payment_token_symbol,
         config.payment_token_address
     );
+    for asset in config.payment_assets.iter().skip(1) {
+        tracing::info!(
+            "Also accepting: {} on {} ({})",
+            asset.symbol,
+            asset.network,
+            asset.address
+        );
+    }
     tracing::info!(
         "Cost per registration: {} ({} decimals)",
         config.cost_per_registration,
@@ -65,7 +89,34 @@ async fn main() {
     );
 
     let port = config.port;
-    let http_client = reqwest::Client::new();
+    let facilitator: Arc<dyn Facilitator> = match config.facilitator_mode {
+        FacilitatorMode::Http => {
+            let http_client = reqwest::Client::new();
+            let facilitators = config
+                .facilitator_urls
+                .iter()
+                .zip(&config.facilitator_signers)
+                .map(|(url, signer)| {
+                    let facilitator: Arc<dyn Facilitator> = Arc::new(
+                        HttpFacilitator::new(
+                            url.clone(),
+                            http_client.clone(),
+                            config.facilitator_verify_timeout,
+                            config.facilitator_settle_timeout,
+                        )
+                        .with_batch_settle(config.facilitator_batch_settle),
+                    );
+                    (url.clone(), facilitator, signer.clone())
+                })
+                .collect();
+            Arc::new(FailoverFacilitator::new(
+                facilitators,
+                config.facilitator_failure_threshold,
+                config.facilitator_cooldown,
+            ))
+        }
+        FacilitatorMode::Simulated(behavior) => Arc::new(SimulatedFacilitator::new(behavior)),
+    };
 
     // Create settlement queue
     let settlement_queue = Arc::new(
@@ -77,25 +128,55 @@ async fn main() {
         "Settlement queue initialized ({} pending)",
         settlement_queue.len()
     );
+    if let (Some(batching), true) = (&config.settlement_batching, facilitator.supports_batch()) {
+        tracing::info!(
+            "Settlement batching: {}s window, up to {} payments per batch",
+            batching.window.as_secs(),
+            batching.max_items
+        );
+    }
 
     // Create shutdown channel
     let (shutdown_tx, shutdown_rx) = broadcast::channel::<()>(1);
 
     // Start settlement worker
-    let worker = SettlementWorker::new(
-        settlement_queue.clone(),
-        config.facilitator_url.clone(),
-        http_client.clone(),
-    );
+    let worker = SettlementWorker::new(settlement_queue.clone(), facilitator.clone(), config.clone());
     let worker_handle = tokio::spawn(async move {
         worker.run(shutdown_rx).await;
     });
 
+    // Payouts (bounty awards and refunds, payment refunds)
+    let payout_executor: Option<Arc<dyn PayoutExecutor>> = match &config.payout_mode {
+        PayoutMode::Manual => {
+            tracing::info!("Payouts are recorded for manual transfer");
+            None
+        }
+        PayoutMode::Http(url) => {
+            tracing::info!("Payouts sent via {}", url);
+            Some(Arc::new(HttpPayoutExecutor::new(url.clone(), reqwest::Client::new())))
+        }
+        PayoutMode::File(path) => {
+            tracing::warn!("Payouts are written to {} (dry run) - nothing is transferred", path);
+            Some(Arc::new(FilePayoutExecutor::new(path.clone())))
+        }
+    };
+    if config.admin_api_key.is_none() {
+        tracing::info!("ADMIN_API_KEY not set - admin endpoints are disabled");
+    }
+
+    // Start bounty worker (expiry and payouts)
+    let bounty_worker = BountyWorker::new(pool.clone(), config.clone(), payout_executor.clone());
+    let bounty_shutdown_rx = shutdown_tx.subscribe();
+    let bounty_worker_handle = tokio::spawn(async move {
+        bounty_worker.run(bounty_shutdown_rx).await;
+    });
+
     let state = AppState {
         pool,
         config,
-        http_client,
+        facilitator,
         settlement_queue,
+        payout_executor,
     };
 
     // CORS configuration
@@ -106,19 +187,35 @@ async fn main() {
         .expose_headers(Any);
 
     // Build API routes from controllers
-    let api_routes = Router::new()
+    let api_routes = OpenApiRouter::new()
         .merge(controllers::boards::config())
         .merge(controllers::threads::config(state.clone()))
         .merge(controllers::agents::config(state.clone()))
         .merge(controllers::replies::config(state.clone()))
         .merge(controllers::search::config())
-        .merge(controllers::register::config())
+        .merge(controllers::register::config(state.clone()))
         .merge(controllers::earnings::config())
-        .with_state(state);
+        .merge(controllers::facilitators::config())
+        .merge(controllers::debts::config(state.clone()))
+        .merge(controllers::credits::config(state.clone()))
+        .merge(controllers::tips::config(state.clone()))
+        .merge(controllers::bounties::config(state.clone()))
+        .merge(controllers::admin::config(state.clone()))
+        .merge(controllers::payments::config())
+        .merge(controllers::settlements::config());
+
+    // The OpenAPI document is collected from the routes as they are mounted
+    let (routes, mut api) = OpenApiRouter::with_openapi(openapi::ApiDoc::openapi())
+        .merge(controllers::discovery::config())
+        .nest("/api", api_routes)
+        .split_for_parts();
+    openapi::ReceiptHeaders.modify(&mut api);
 
     let app = Router::new()
         .route("/", get(|| async { "hello agents!" }))
-        .nest("/api", api_routes)
+        .merge(routes)
+        .merge(SwaggerUi::new("/docs").url("/api/openapi.json", api))
+        .with_state(state)
         .layer(cors)
         .layer(TraceLayer::new_for_http());
 
@@ -143,8 +240,9 @@ async fn main() {
         .await
         .expect("Failed to start server");
 
-    // Wait for worker to finish
+    // Wait for workers to finish
     tracing::info!("Waiting for settlement worker to finish...");
     let _ = worker_handle.await;
+    let _ = bounty_worker_handle.await;
     tracing::info!("Shutdown complete");
 }
@@ -2,7 +2,7 @@ use axum::{
     extract::{Request, State},
     http::{header, StatusCode},
     middleware::Next,
-    response::Response,
+    response::{IntoResponse, Response},
 };
 use uuid::Uuid;
 
@@ -11,37 +11,115 @@ use crate::AppState;
 
 pub async fn auth_middleware(
     State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Result<Response, Response> {
+    authenticate(state, request, next, false).await
+}
+
+/// Like `auth_middleware`, but lets suspended agents through (to pay their debts)
+pub async fn auth_allow_suspended_middleware(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Result<Response, Response> {
+    authenticate(state, request, next, true).await
+}
+
+/// Authenticates when credentials are sent, passes anonymous requests through
+///
+/// Handlers take `Option<Extension<AuthenticatedAgent>>`. Suspended agents
+/// are let through as this guards reads, not posts.
+pub async fn optional_auth_middleware(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Result<Response, Response> {
+    let headers = request.headers();
+    if !headers.contains_key("X-Forwarded-Agent") && !headers.contains_key(header::AUTHORIZATION) {
+        return Ok(next.run(request).await);
+    }
+    authenticate(state, request, next, true).await
+}
+
+/// Guards operator endpoints with the `X-Admin-Key` header
+///
+/// Without ADMIN_API_KEY the admin endpoints don't exist (404).
+pub async fn admin_middleware(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Result<Response, Response> {
+    let Some(admin_key) = state.config.admin_api_key.as_deref() else {
+        return Err(StatusCode::NOT_FOUND.into_response());
+    };
+
+    let provided = request
+        .headers()
+        .get("X-Admin-Key")
+        .and_then(|h| h.to_str().ok())
+        .ok_or_else(|| StatusCode::UNAUTHORIZED.into_response())?;
+
+    if !keys_match(provided.as_bytes(), admin_key.as_bytes()) {
+        return Err(StatusCode::FORBIDDEN.into_response());
+    }
+
+    Ok(next.run(request).await)
+}
+
+/// Compare keys without returning early on the first differing byte
+fn keys_match(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+async fn authenticate(
+    state: AppState,
     mut request: Request,
     next: Next,
-) -> Result<Response, StatusCode> {
+    allow_suspended: bool,
+) -> Result<Response, Response> {
     // Check for X-Forwarded-Agent header (from x402-gate)
-    if let Some(agent_id) = request.headers().get("X-Forwarded-Agent") {
+    let (agent_id, suspended, by_api_key) = if let Some(agent_id) = request.headers().get("X-Forwarded-Agent") {
         let agent_id = agent_id
             .to_str()
-            .map_err(|_| StatusCode::BAD_REQUEST)?;
-        let agent_id: Uuid = agent_id.parse().map_err(|_| StatusCode::BAD_REQUEST)?;
+            .map_err(|_| StatusCode::BAD_REQUEST.into_response())?;
+        let agent_id: Uuid = agent_id
+            .parse()
+            .map_err(|_| StatusCode::BAD_REQUEST.into_response())?;
 
-        request.extensions_mut().insert(AuthenticatedAgent { id: agent_id });
-        return Ok(next.run(request).await);
-    }
+        let suspended = AgentService::get_by_id(&state.pool, agent_id)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())?
+            .is_some_and(|agent| agent.suspended);
+        (agent_id, suspended, false)
+    } else {
+        // Check for Bearer token
+        let auth_header = request
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|h| h.to_str().ok());
 
-    // Check for Bearer token
-    let auth_header = request
-        .headers()
-        .get(header::AUTHORIZATION)
-        .and_then(|h| h.to_str().ok());
+        let api_key = match auth_header {
+            Some(h) if h.starts_with("Bearer ") => &h[7..],
+            _ => return Err(StatusCode::UNAUTHORIZED.into_response()),
+        };
 
-    let api_key = match auth_header {
-        Some(h) if h.starts_with("Bearer ") => &h[7..],
-        _ => return Err(StatusCode::UNAUTHORIZED),
+        let agent = AgentService::get_by_api_key(&state.pool, api_key)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())?
+            .ok_or_else(|| StatusCode::UNAUTHORIZED.into_response())?;
+        (agent.id, agent.suspended, true)
     };
 
-    let agent = AgentService::get_by_api_key(&state.pool, api_key)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-        .ok_or(StatusCode::UNAUTHORIZED)?;
+    if suspended && !allow_suspended {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "Agent suspended: a payment failed to settle. See GET /api/debts and pay with POST /api/debts/pay",
+        )
+            .into_response());
+    }
 
-    request.extensions_mut().insert(AuthenticatedAgent { id: agent.id });
+    request.extensions_mut().insert(AuthenticatedAgent { id: agent_id, by_api_key });
 
     Ok(next.run(request).await)
 }
@@ -49,4 +127,17 @@ pub async fn auth_middleware(
 #[derive(Clone, Debug)]
 pub struct AuthenticatedAgent {
     pub id: Uuid,
+    /// Proven with the agent's API key rather than named by `X-Forwarded-Agent`,
+    /// which carries no secret
+    pub by_api_key: bool,
+}
+
+impl AuthenticatedAgent {
+    /// The agent's id when authenticated with its API key
+    ///
+    /// Spending the agent's credit or acting as an owner (reading one's own
+    /// premium thread, awarding a bounty) requires the key.
+    pub fn key_holder(&self) -> Option<Uuid> {
+        self.by_api_key.then_some(self.id)
+    }
 }
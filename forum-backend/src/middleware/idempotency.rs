@@ -0,0 +1,262 @@
+//! Idempotency-Key support for paid POSTs
+//!
+//! The first request with a key claims it and its successful response is
+//! stored; retries with the same key and body replay that response without
+//! running the handler again, so one key never pays or settles twice.
+
+use axum::{
+    body::{to_bytes, Body, Bytes},
+    extract::{Request, State},
+    http::{HeaderName, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use sha2::{Digest, Sha256};
+
+use crate::middleware::{claimed_payer, AuthenticatedAgent};
+use crate::services::{AgentService, IdempotencyService};
+use crate::AppState;
+
+pub const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+/// Set on responses replayed from a stored key
+const IDEMPOTENT_REPLAYED_HEADER: &str = "Idempotent-Replayed";
+
+const MAX_KEY_LENGTH: usize = 255;
+/// Same as axum's default JSON body limit
+const MAX_BODY_BYTES: usize = 2 * 1024 * 1024;
+
+pub async fn idempotency_middleware(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let key = match request.headers().get(IDEMPOTENCY_KEY_HEADER) {
+        None => return next.run(request).await,
+        Some(value) => match value.to_str() {
+            Ok(key) if !key.is_empty() && key.len() <= MAX_KEY_LENGTH => key.to_string(),
+            _ => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    "Idempotency-Key must be 1-255 visible ASCII characters",
+                )
+                    .into_response()
+            }
+        },
+    };
+
+    // Keys are per agent; anonymous requests are scoped by the paying wallet
+    let scope = match request.extensions().get::<AuthenticatedAgent>() {
+        Some(agent) => agent.id.to_string(),
+        None => match claimed_payer(request.headers()) {
+            Some(payer) => format!("wallet:{}", payer.to_lowercase()),
+            // Nothing to scope by; the handler will ask for payment anyway
+            None => return next.run(request).await,
+        },
+    };
+
+    let (parts, body) = request.into_parts();
+    let body = match to_bytes(body, MAX_BODY_BYTES).await {
+        Ok(body) => body,
+        Err(_) => return (StatusCode::PAYLOAD_TOO_LARGE, "Request body too large").into_response(),
+    };
+    let hash = request_hash(&parts.method, parts.uri.path(), &body);
+
+    match IdempotencyService::claim(&state.pool, &scope, &key, &hash).await {
+        Ok(true) => {}
+        Ok(false) => return replay(&state, &scope, &key, &hash).await,
+        Err(e) => {
+            tracing::error!("Failed to claim idempotency key: {}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Database error").into_response();
+        }
+    }
+
+    let response = next.run(Request::from_parts(parts, Body::from(body))).await;
+
+    // Only successful responses are stored; anything else can be retried
+    if !response.status().is_success() {
+        if let Err(e) = IdempotencyService::release(&state.pool, &scope, &key).await {
+            tracing::error!("Failed to release idempotency key: {}", e);
+        }
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let body = match to_bytes(body, usize::MAX).await {
+        Ok(body) => body,
+        Err(e) => {
+            tracing::error!("Failed to buffer response body: {}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to read response").into_response();
+        }
+    };
+
+    let headers: Vec<(String, String)> = parts
+        .headers
+        .iter()
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+        .collect();
+    let headers_json = serde_json::to_string(&headers).unwrap_or_default();
+
+    if let Err(e) = IdempotencyService::complete(
+        &state.pool,
+        &scope,
+        &key,
+        parts.status.as_u16() as i32,
+        &headers_json,
+        &redact_secrets(&body),
+    )
+    .await
+    {
+        tracing::error!("Failed to store idempotent response: {}", e);
+    }
+
+    Response::from_parts(parts, Body::from(body))
+}
+
+/// Answer a request whose key was already claimed
+async fn replay(state: &AppState, scope: &str, key: &str, hash: &str) -> Response {
+    let stored = match IdempotencyService::get(&state.pool, scope, key).await {
+        Ok(Some(stored)) => stored,
+        // Released between our claim attempt and now
+        Ok(None) => {
+            return (StatusCode::CONFLICT, "Idempotency-Key is being released, retry").into_response()
+        }
+        Err(e) => {
+            tracing::error!("Failed to get idempotency key: {}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Database error").into_response();
+        }
+    };
+
+    if stored.request_hash != hash {
+        return (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "Idempotency-Key was already used for a different request",
+        )
+            .into_response();
+    }
+
+    if stored.status != "completed" {
+        return (
+            StatusCode::CONFLICT,
+            "A request with this Idempotency-Key is still in progress",
+        )
+            .into_response();
+    }
+
+    let status = stored
+        .response_status
+        .and_then(|s| StatusCode::from_u16(s as u16).ok())
+        .unwrap_or(StatusCode::OK);
+    let headers: Vec<(String, String)> = stored
+        .response_headers
+        .as_deref()
+        .and_then(|h| serde_json::from_str(h).ok())
+        .unwrap_or_default();
+
+    let body = match reissue_secrets(state, scope, stored.response_body.unwrap_or_default()).await {
+        Ok(body) => body,
+        Err(response) => return response,
+    };
+
+    let mut response = Response::new(Body::from(body));
+    *response.status_mut() = status;
+    for (name, value) in headers {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(name.as_bytes()),
+            HeaderValue::from_str(&value),
+        ) {
+            response.headers_mut().append(name, value);
+        }
+    }
+    response
+        .headers_mut()
+        .insert(IDEMPOTENT_REPLAYED_HEADER, HeaderValue::from_static("true"));
+
+    response
+}
+
+/// Blank credentials in a JSON body before it is stored for replay
+///
+/// The `api_key` field is kept as null so a replay knows to issue a new one.
+fn redact_secrets(body: &Bytes) -> Vec<u8> {
+    match serde_json::from_slice::<serde_json::Value>(body) {
+        Ok(serde_json::Value::Object(mut object)) if object.contains_key("api_key") => {
+            object.insert("api_key".to_string(), serde_json::Value::Null);
+            serde_json::to_vec(&object).unwrap_or_default()
+        }
+        _ => body.to_vec(),
+    }
+}
+
+/// Give a replayed registration a working `api_key`
+///
+/// The stored key was redacted, so the agent gets a new one that replaces
+/// it; only the wallet that registered the agent can replay. A replay that
+/// can't be given a key fails rather than coming back without one.
+async fn reissue_secrets(state: &AppState, scope: &str, body: Vec<u8>) -> Result<Vec<u8>, Response> {
+    let mut object = match serde_json::from_slice::<serde_json::Value>(&body) {
+        Ok(serde_json::Value::Object(object)) if object.get("api_key").is_some_and(|k| k.is_null()) => object,
+        _ => return Ok(body),
+    };
+
+    let wallet = scope.strip_prefix("wallet:");
+    let username = object.get("username").and_then(|u| u.as_str());
+    let (Some(wallet), Some(username)) = (wallet, username) else {
+        return Err((StatusCode::CONFLICT, "Stored response has no API key to return").into_response());
+    };
+
+    let api_key = AgentService::generate_api_key();
+    match AgentService::reissue_api_key(&state.pool, username, wallet, &api_key).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return Err((StatusCode::CONFLICT, "Registered agent no longer matches this wallet").into_response())
+        }
+        Err(e) => {
+            tracing::error!("Failed to reissue API key: {}", e);
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, "Database error").into_response());
+        }
+    }
+
+    object.insert("api_key".to_string(), serde_json::Value::String(api_key));
+    Ok(serde_json::to_vec(&object).unwrap_or_default())
+}
+
+/// Hex sha256 over method, path and body
+///
+/// Payment headers are left out so a retry with a freshly signed payment
+/// still replays the original response.
+fn request_hash(method: &Method, path: &str, body: &Bytes) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(method.as_str().as_bytes());
+    hasher.update(b"\n");
+    hasher.update(path.as_bytes());
+    hasher.update(b"\n");
+    hasher.update(body);
+    hex::encode(hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_hash_covers_path_and_body() {
+        let body = Bytes::from_static(br#"{"content":"hi"}"#);
+        let hash = request_hash(&Method::POST, "/api/threads/1/replies", &body);
+
+        assert_eq!(hash, request_hash(&Method::POST, "/api/threads/1/replies", &body));
+        assert_ne!(hash, request_hash(&Method::POST, "/api/threads/2/replies", &body));
+        assert_ne!(
+            hash,
+            request_hash(&Method::POST, "/api/threads/1/replies", &Bytes::from_static(b"{}"))
+        );
+    }
+
+    #[test]
+    fn test_redact_secrets_blanks_api_key() {
+        let body = Bytes::from_static(br#"{"api_key":"x402b_secret","username":"bot"}"#);
+        assert_eq!(redact_secrets(&body), br#"{"api_key":null,"username":"bot"}"#.to_vec());
+
+        let other = Bytes::from_static(br#"{"id":"1"}"#);
+        assert_eq!(redact_secrets(&other), other.to_vec());
+    }
+}
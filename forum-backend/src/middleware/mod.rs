@@ -1,5 +1,12 @@
 mod auth;
+mod idempotency;
 pub mod x402;
 
 pub use auth::*;
-pub use x402::require_x402_payment_deferred;
+pub use idempotency::idempotency_middleware;
+pub use x402::{
+    bind_agent_wallet, build_payment_requirements, cancel_payment, claimed_payer, has_payment_header, paid_action_failed,
+    payment_required_response_with_preview,
+    require_agent_wallet, require_x402_payment_deferred, require_x402_payment_to,
+    VerifiedPayment,
+};
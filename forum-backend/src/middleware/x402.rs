@@ -1,143 +1,364 @@
 use axum::{
     body::Body,
-    http::{header, HeaderMap, StatusCode},
-    response::Response,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, IntoResponseParts, Response, ResponseParts},
 };
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::convert::Infallible;
+use uuid::Uuid;
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
 
+use crate::models::{Account, AccountKind, CreditSpend, NewJournalEntry, NewPayment, Purchase};
 use crate::models::x402::{
-    PaymentRequiredResponse, PaymentRequirements, SettleResponse, VerifyRequest, VerifyResponse,
+    PaymentReceipt, PaymentRequiredResponse, PaymentRequiredResponseV2, PaymentRequirements,
+    PaymentRequirementsV2, PaymentScheme, ResourceInfo, VerifyRequest,
+    VersionedPaymentRequirements, X402Version, PAYMENT_REQUIRED_HEADER,
+};
+use crate::services::facilitator::FacilitatorError;
+use crate::services::payment_verifier::VerificationError;
+use crate::services::{
+    AgentService, CreditService, ExpectedPayment, PaymentService, PaymentVerifier, StoredVerifyRequest,
 };
-use crate::services::StoredVerifyRequest;
 use crate::AppState;
 
-use crate::config::Config;
+use crate::config::{Config, PaymentAsset};
+use crate::domain_types::network::same_network;
 use crate::domain_types::DomainU256;
 
-/// Build payment requirements from config
-fn build_payment_requirements(
+/// Build payment requirements from config, one entry per asset and scheme
+///
+/// `amount` is in canonical (PAYMENT_TOKEN_*) raw units and is converted
+/// for each asset. Payments go to `pay_to`. The /.well-known/x402 catalog
+/// lists what this returns for each paid resource.
+pub fn build_payment_requirements(
+    config: &Config,
+    pay_to: &str,
+    amount: DomainU256,
+    resource: &str,
+    description: &str,
+) -> Vec<PaymentRequirements> {
+    config
+        .payment_assets
+        .iter()
+        .flat_map(|asset| {
+            let asset_amount = asset.amount_from_canonical(amount, config.payment_token_decimals);
+            asset.schemes.iter().map(move |scheme| {
+                build_scheme_requirements(config, pay_to, asset, *scheme, asset_amount, resource, description)
+            })
+        })
+        .collect()
+}
+
+/// Build the requirements for a single asset and scheme
+fn build_scheme_requirements(
     config: &Config,
+    pay_to: &str,
+    asset: &PaymentAsset,
+    scheme: PaymentScheme,
     amount: DomainU256,
     resource: &str,
     description: &str,
 ) -> PaymentRequirements {
+    let extra = match scheme {
+        // EIP-2612: the facilitator is the permit spender
+        PaymentScheme::Permit => serde_json::json!({
+            "token": asset.symbol,
+            "address": asset.address,
+            "decimals": asset.decimals,
+            "name": asset.name,
+            "version": asset.version,
+            "facilitatorSigner": config.facilitator_signer,
+            "minimum_amount": true
+        }),
+        // EIP-3009: the authorization transfers directly to payTo
+        PaymentScheme::Exact => serde_json::json!({
+            "token": asset.symbol,
+            "address": asset.address,
+            "decimals": asset.decimals,
+            "name": asset.name,
+            "version": asset.version
+        }),
+    };
+
     PaymentRequirements {
-        scheme: "permit".to_string(),
-        network: config.payment_network.clone(),
+        scheme: scheme.as_str().to_string(),
+        network: asset.network.clone(),
         max_amount_required: amount.to_string(),
         resource: resource.to_string(),
         description: description.to_string(),
         mime_type: "application/json".to_string(),
-        pay_to: config.wallet_address.clone(),
+        pay_to: pay_to.to_string(),
         max_timeout_seconds: 300, // 5 minutes
-        asset: config.payment_token_address.clone(),
-        extra: Some(serde_json::json!({
-            "token": config.payment_token_symbol,
-            "address": config.payment_token_address,
-            "decimals": config.payment_token_decimals,
-            "name": config.payment_token_name,
-            "version": config.payment_token_version,
-            "facilitatorSigner": config.facilitator_signer,
-            "minimum_amount": true
-        })),
+        asset: asset.address.clone(),
+        extra: Some(extra),
     }
 }
 
 /// Generate a 402 Payment Required response
+///
+/// The body carries the V1 response for existing clients, while the
+/// PAYMENT-REQUIRED header carries the base64 V2 envelope for V2 clients.
 pub fn payment_required_response(
     config: &Config,
+    pay_to: &str,
+    amount: DomainU256,
+    resource: &str,
+    description: &str,
+) -> Response {
+    build_payment_required_response(config, pay_to, amount, resource, description, None)
+}
+
+/// 402 response for premium content, with the free preview in the body
+/// under `preview` next to the V1 payment requirements
+pub fn payment_required_response_with_preview(
+    config: &Config,
+    amount: DomainU256,
+    resource: &str,
+    description: &str,
+    preview: impl Serialize,
+) -> Response {
+    let preview = serde_json::to_value(preview).unwrap_or_default();
+    build_payment_required_response(
+        config,
+        &config.wallet_address,
+        amount,
+        resource,
+        description,
+        Some(preview),
+    )
+}
+
+fn build_payment_required_response(
+    config: &Config,
+    pay_to: &str,
     amount: DomainU256,
     resource: &str,
     description: &str,
+    preview: Option<serde_json::Value>,
 ) -> Response {
-    let requirements = build_payment_requirements(config, amount, resource, description);
+    let requirements = build_payment_requirements(config, pay_to, amount, resource, description);
+
+    let v2_response = PaymentRequiredResponseV2 {
+        x402_version: X402Version::V2.as_u32(),
+        error: None,
+        resource: ResourceInfo {
+            url: resource.to_string(),
+            description: description.to_string(),
+            mime_type: "application/json".to_string(),
+        },
+        accepts: requirements.iter().map(PaymentRequirementsV2::from).collect(),
+    };
 
     let response = PaymentRequiredResponse {
-        x402_version: 1,
-        accepts: vec![requirements],
+        x402_version: X402Version::V1.as_u32(),
+        accepts: requirements,
         error: None,
     };
 
-    let body = serde_json::to_string(&response).unwrap_or_default();
+    let body = match preview {
+        Some(preview) => {
+            let mut body = serde_json::to_value(&response).unwrap_or_default();
+            if let Some(fields) = body.as_object_mut() {
+                fields.insert("preview".to_string(), preview);
+            }
+            body.to_string()
+        }
+        None => serde_json::to_string(&response).unwrap_or_default(),
+    };
+    let v2_header = BASE64.encode(serde_json::to_string(&v2_response).unwrap_or_default());
 
     Response::builder()
         .status(StatusCode::PAYMENT_REQUIRED)
         .header(header::CONTENT_TYPE, "application/json")
+        .header(PAYMENT_REQUIRED_HEADER, v2_header)
         .body(Body::from(body))
         .unwrap()
 }
 
+/// Whether the client sent an x402 payment header of any version
+pub fn has_payment_header(headers: &HeaderMap) -> bool {
+    extract_payment_header(headers).is_some()
+}
+
+/// Find the payment header sent by the client and the version it implies
+///
+/// V2 clients send PAYMENT-SIGNATURE, V1 clients send X-PAYMENT.
+fn extract_payment_header(headers: &HeaderMap) -> Option<(X402Version, &str)> {
+    [X402Version::V2, X402Version::V1]
+        .into_iter()
+        .find_map(|version| {
+            headers
+                .get(version.payment_header())
+                .and_then(|v| v.to_str().ok())
+                .map(|value| (version, value))
+        })
+}
+
+/// A field of the client's choice: top-level in V1, under `accepted` in V2
+fn payload_choice<'a>(payment_payload: &'a serde_json::Value, field: &str) -> Option<&'a str> {
+    payment_payload
+        .get(field)
+        .or_else(|| payment_payload.get("accepted").and_then(|a| a.get(field)))
+        .and_then(|s| s.as_str())
+}
+
+/// Scheme the client chose
+///
+/// Payloads without a scheme are treated as permits, which was the only
+/// scheme offered before "exact" was added.
+fn payload_scheme(payment_payload: &serde_json::Value) -> Result<PaymentScheme, String> {
+    match payload_choice(payment_payload, "scheme") {
+        Some(s) => s.parse(),
+        None => Ok(PaymentScheme::Permit),
+    }
+}
+
+/// Pick the advertised requirements matching the client's choice
+///
+/// Scheme, network and asset are matched when the payload names them. V1
+/// payloads carry no asset, so they get the first asset offered on the
+/// chosen network.
+fn select_requirements(
+    payment_payload: &serde_json::Value,
+    accepts: Vec<PaymentRequirements>,
+) -> Result<PaymentRequirements, String> {
+    let scheme = payload_scheme(payment_payload)?;
+    let network = payload_choice(payment_payload, "network");
+    let asset = payload_choice(payment_payload, "asset");
+
+    accepts
+        .into_iter()
+        .find(|req| {
+            req.scheme == scheme.as_str()
+                && network.is_none_or(|n| same_network(n, &req.network))
+                && asset.is_none_or(|a| a.eq_ignore_ascii_case(&req.asset))
+        })
+        .ok_or_else(|| {
+            format!(
+                "No accepted payment option for scheme '{}' on network '{}' with asset '{}'",
+                scheme.as_str(),
+                network.unwrap_or("any"),
+                asset.unwrap_or("any")
+            )
+        })
+}
+
+/// Nonce identifying the signed authorization
+///
+/// Permit nonces are the token's sequential per-owner nonce, exact nonces are
+/// random 32-byte values; both live at `payload.authorization.nonce`.
+fn payload_nonce(payment_payload: &serde_json::Value) -> Option<String> {
+    let nonce = payment_payload
+        .get("payload")?
+        .get("authorization")?
+        .get("nonce")?;
+
+    match nonce {
+        serde_json::Value::String(s) => Some(s.clone()),
+        serde_json::Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+/// Address that signed the authorization (permit owner or exact from)
+fn payload_payer(payment_payload: &serde_json::Value) -> Option<String> {
+    let authorization = payment_payload.get("payload")?.get("authorization")?;
+
+    authorization
+        .get("owner")
+        .or_else(|| authorization.get("from"))
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string())
+}
+
+/// Hex sha256 of the payment payload, identifying it across encodings
+fn payload_hash(payment_payload: &serde_json::Value) -> String {
+    hex::encode(Sha256::digest(payment_payload.to_string().as_bytes()))
+}
+
+/// Decode the base64 JSON payment payload from a payment header value
+fn decode_payment_payload(payment_header: &str) -> Result<serde_json::Value, String> {
+    let payload_bytes = BASE64
+        .decode(payment_header.trim())
+        .map_err(|e| format!("Invalid payment header encoding: {}", e))?;
+
+    serde_json::from_slice(&payload_bytes).map_err(|e| format!("Invalid payment payload JSON: {}", e))
+}
+
+/// Payer address claimed by the request's payment header, if any
+///
+/// Not verified - only for policy checks that must run before the payment
+/// is taken. The signature is checked against this address later.
+pub fn claimed_payer(headers: &HeaderMap) -> Option<String> {
+    let (_, payment) = extract_payment_header(headers)?;
+    payload_payer(&decode_payment_payload(payment).ok()?)
+}
+
 /// Decode payment header and build verify request
+///
+/// The protocol version is taken from the payload's `x402Version` field,
+/// falling back to the version implied by the header name. The requirements
+/// sent to the facilitator are the ones matching the option the client chose.
 fn build_verify_request(
     payment_header: &str,
-    payment_requirements: PaymentRequirements,
+    header_version: X402Version,
+    accepts: Vec<PaymentRequirements>,
 ) -> Result<VerifyRequest, String> {
-    let payload_bytes = BASE64
-        .decode(payment_header)
-        .map_err(|e| format!("Invalid payment header encoding: {}", e))?;
+    let payment_payload = decode_payment_payload(payment_header)?;
+
+    let version = match payment_payload.get("x402Version").and_then(|v| v.as_u64()) {
+        Some(v) => X402Version::from_u32(v as u32)
+            .ok_or_else(|| format!("Unsupported x402Version: {}", v))?,
+        None => header_version,
+    };
 
-    let payment_payload: serde_json::Value = serde_json::from_slice(&payload_bytes)
-        .map_err(|e| format!("Invalid payment payload JSON: {}", e))?;
+    let payment_requirements = select_requirements(&payment_payload, accepts)?;
+
+    let payment_requirements = match version {
+        X402Version::V1 => VersionedPaymentRequirements::V1(payment_requirements),
+        X402Version::V2 => {
+            VersionedPaymentRequirements::V2(PaymentRequirementsV2::from(&payment_requirements))
+        }
+    };
 
     Ok(VerifyRequest {
-        x402_version: 1,
+        x402_version: version.as_u32(),
         payment_payload,
         payment_requirements,
     })
 }
 
-/// Verify payment with facilitator
-async fn verify_payment(
-    http_client: &reqwest::Client,
-    facilitator_url: &str,
-    verify_request: &VerifyRequest,
-) -> Result<VerifyResponse, String> {
-    let verify_url = format!("{}/verify", facilitator_url);
-
-    let response = http_client
-        .post(&verify_url)
-        .json(verify_request)
-        .send()
-        .await
-        .map_err(|e| format!("Failed to contact facilitator: {}", e))?;
-
-    if !response.status().is_success() {
-        let status = response.status();
-        let body = response.text().await.unwrap_or_default();
-        return Err(format!("Facilitator returned error: {} - {}", status, body));
-    }
-
-    response
-        .json::<VerifyResponse>()
-        .await
-        .map_err(|e| format!("Failed to parse verify response: {}", e))
-}
-
-/// Settle payment with facilitator (uses same request format as verify)
-async fn settle_payment(
-    http_client: &reqwest::Client,
-    facilitator_url: &str,
-    settle_request: &VerifyRequest,
-) -> Result<SettleResponse, String> {
-    let settle_url = format!("{}/settle", facilitator_url);
-
-    let response = http_client
-        .post(&settle_url)
-        .json(settle_request)
-        .send()
-        .await
-        .map_err(|e| format!("Failed to contact facilitator for settlement: {}", e))?;
+/// Check the payload's signature and authorization locally
+///
+/// Catches obviously bad payments (wrong spender or recipient, expired,
+/// underpaid, bad signature) before the facilitator round trip.
+fn pre_verify_payment(config: &Config, verify_request: &VerifyRequest) -> Result<String, VerificationError> {
+    let requirements = &verify_request.payment_requirements;
+    let extra_field = |name: &str| {
+        requirements
+            .extra()
+            .and_then(|extra| extra.get(name))
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+    };
 
-    if !response.status().is_success() {
-        let status = response.status();
-        let body = response.text().await.unwrap_or_default();
-        return Err(format!("Facilitator settlement error: {} - {}", status, body));
-    }
+    let expected = ExpectedPayment {
+        scheme: requirements
+            .scheme()
+            .parse()
+            .map_err(VerificationError::InvalidPayload)?,
+        network: requirements.network(),
+        asset: requirements.asset(),
+        pay_to: requirements.pay_to(),
+        amount: requirements.amount(),
+        token_name: extra_field("name"),
+        token_version: extra_field("version"),
+        facilitator_signer: &config.facilitator_signer,
+    };
 
-    response
-        .json::<SettleResponse>()
-        .await
-        .map_err(|e| format!("Failed to parse settle response: {}", e))
+    let now = chrono::Utc::now().timestamp().max(0) as u64;
+    PaymentVerifier::verify(&verify_request.payment_payload, &expected, now)
 }
 
 /// Error response for payment failures
@@ -149,89 +370,351 @@ fn payment_error_response(status: StatusCode, message: &str) -> Response {
         .unwrap()
 }
 
-/// Require x402 payment - checks header, verifies, and settles
-/// Returns Ok(Option<tx_hash>) on success, Err(Response) on failure
-pub async fn require_x402_payment(
+/// Remaining credit, set on responses paid from prepaid credit
+pub const CREDIT_BALANCE_HEADER: &str = "X-Credit-Balance";
+
+/// A verified payment, returned to handlers so they can attach the receipt
+///
+/// Implements `IntoResponseParts`, adding the base64 receipt header in the
+/// client's protocol version, or the remaining balance for credit payments.
+#[derive(Debug, Clone)]
+pub enum VerifiedPayment {
+    /// Paid with an x402 payment
+    X402 {
+        /// Row in the payments ledger, to link to the paid-for content
+        payment_id: Uuid,
+        receipt: PaymentReceipt,
+        version: X402Version,
+    },
+    /// Paid from the agent's prepaid credit, debited when the purchase is recorded
+    Credit {
+        spend: CreditSpend,
+        /// Balance left after the debit, as of when the payment was accepted
+        balance: DomainU256,
+    },
+}
+
+impl VerifiedPayment {
+    /// Row in the payments ledger; None when paid from credit
+    pub fn payment_id(&self) -> Option<Uuid> {
+        match self {
+            Self::X402 { payment_id, .. } => Some(*payment_id),
+            Self::Credit { .. } => None,
+        }
+    }
+
+    /// Ledger account the payment is taken from
+    pub fn funding_account(&self) -> Account {
+        match self {
+            Self::X402 { .. } => Account::platform(AccountKind::PlatformWallet),
+            Self::Credit { spend, .. } => Account::agent(AccountKind::AgentCredit, spend.agent_id),
+        }
+    }
+
+    /// Wallet that paid, as reported by the facilitator
+    pub fn payer(&self) -> Option<&str> {
+        match self {
+            Self::X402 { receipt, .. } => receipt.payer.as_deref(),
+            Self::Credit { .. } => None,
+        }
+    }
+
+    /// The purchase to record with what it paid for, with `entry` linked to the payment
+    pub fn purchase(&self, entry: NewJournalEntry) -> Purchase {
+        Purchase {
+            payment_id: self.payment_id(),
+            entry: entry.payment(self.payment_id()),
+            credit: match self {
+                Self::X402 { .. } => None,
+                Self::Credit { spend, .. } => Some(spend.clone()),
+            },
+        }
+    }
+}
+
+impl IntoResponseParts for VerifiedPayment {
+    type Error = Infallible;
+
+    fn into_response_parts(self, mut res: ResponseParts) -> Result<ResponseParts, Self::Error> {
+        match self {
+            Self::X402 { receipt, version, .. } => {
+                let encoded = BASE64.encode(serde_json::to_vec(&receipt).unwrap_or_default());
+                if let Ok(value) = HeaderValue::from_str(&encoded) {
+                    res.headers_mut().insert(version.payment_response_header(), value);
+                }
+            }
+            Self::Credit { balance, .. } => {
+                if let Ok(value) = HeaderValue::from_str(&balance.to_string()) {
+                    res.headers_mut().insert(CREDIT_BALANCE_HEADER, value);
+                }
+            }
+        }
+        Ok(res)
+    }
+}
+
+/// With REQUIRE_OWN_WALLET, reject payments not signed by the agent's wallet
+///
+/// Runs before the payment is taken. Agents without a bound wallet pass and
+/// get bound by `bind_agent_wallet` once they pay.
+pub async fn require_agent_wallet(
+    state: &AppState,
+    agent_id: Uuid,
+    headers: &HeaderMap,
+) -> Result<(), Response> {
+    if !state.config.require_own_wallet {
+        return Ok(());
+    }
+
+    let Some(payer) = claimed_payer(headers) else {
+        return Ok(());
+    };
+
+    let agent = AgentService::get_by_id(&state.pool, agent_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to get agent: {}", e);
+            payment_error_response(StatusCode::INTERNAL_SERVER_ERROR, "Database error")
+        })?;
+
+    match agent.and_then(|a| a.wallet_address) {
+        Some(wallet) if !wallet.eq_ignore_ascii_case(&payer) => Err(payment_error_response(
+            StatusCode::FORBIDDEN,
+            &format!("Payment must be signed by the agent's wallet {}", wallet),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// With REQUIRE_OWN_WALLET, bind the paying wallet to an agent that has none
+pub async fn bind_agent_wallet(state: &AppState, agent_id: Uuid, payment: &VerifiedPayment) {
+    if !state.config.require_own_wallet {
+        return;
+    }
+
+    if let Some(payer) = payment.payer() {
+        if let Err(e) = AgentService::bind_wallet(&state.pool, agent_id, payer).await {
+            tracing::error!("Failed to bind wallet to agent {}: {}", agent_id, e);
+        }
+    }
+}
+
+/// Withdraw a deferred payment after the paid action failed
+///
+/// Cancels the queued settlement and marks the payment failed, so the payer
+/// is not charged for nothing. Settlements a worker already picked up are
+/// left to complete.
+pub async fn cancel_payment(state: &AppState, payment: &VerifiedPayment) {
+    let VerifiedPayment::X402 { payment_id, receipt, .. } = payment else {
+        return;
+    };
+    let Some(settlement_id) = receipt.settlement_id else {
+        tracing::error!("Payment {} has no queued settlement to cancel", payment_id);
+        return;
+    };
+
+    match state.settlement_queue.cancel(settlement_id, "paid action failed").await {
+        Ok(true) => {
+            if let Err(e) = PaymentService::mark_failed(&state.pool, *payment_id).await {
+                tracing::error!("Failed to mark payment {} failed: {}", payment_id, e);
+            }
+        }
+        Ok(false) => {
+            tracing::error!("Settlement {} for payment {} already started, not cancelled", settlement_id, payment_id);
+        }
+        Err(e) => tracing::error!("Failed to cancel settlement {}: {}", settlement_id, e),
+    }
+}
+
+/// Answer a paid action whose transaction failed, withdrawing its payment
+///
+/// Credit spent by a parallel request since the payment was accepted
+/// answers 402; nothing was debited.
+pub async fn paid_action_failed(state: &AppState, payment: &VerifiedPayment, action: &str, e: sqlx::Error) -> Response {
+    if CreditService::is_insufficient(&e) {
+        return payment_error_response(StatusCode::PAYMENT_REQUIRED, "Not enough credit");
+    }
+    tracing::error!("Failed to {}: {}", action, e);
+    cancel_payment(state, payment).await;
+    (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to {}", action)).into_response()
+}
+
+/// Free a reservation whose settlement failed or could not be queued
+async fn release_payment(state: &AppState, payment_id: Uuid) {
+    if let Err(e) = PaymentService::release(&state.pool, payment_id).await {
+        tracing::error!("Failed to release payment {}: {}", payment_id, e);
+    }
+}
+
+/// Require an x402 payment to another wallet than WALLET_ADDRESS, e.g. an
+/// author being tipped; checks the header, verifies and settles
+/// Returns Ok(VerifiedPayment) with the settlement transaction on success, Err(Response) on failure
+pub async fn require_x402_payment_to(
     state: &AppState,
     headers: &HeaderMap,
+    pay_to: &str,
     amount: DomainU256,
     resource: &str,
     description: &str,
-) -> Result<Option<String>, Response> {
-    require_x402_payment_with_options(state, headers, amount, resource, description, false).await
+) -> Result<VerifiedPayment, Response> {
+    let options = PaymentOptions {
+        credit_agent: None,
+        pay_to: Some(pay_to),
+        defer_settlement: false,
+    };
+    require_x402_payment_with_options(state, headers, options, amount, resource, description).await
 }
 
 /// Require x402 payment with deferred settlement option
 /// If defer_settlement is true, settlement happens in background and function returns immediately after verification
-/// Returns Ok(payer_address) on success, Err(Response) on failure
+/// Requests without a payment header from `credit_agent` are debited from its
+/// prepaid credit when the balance covers `amount`; pass only agents that
+/// authenticated with their API key (`AuthenticatedAgent::key_holder`)
+/// Returns Ok(VerifiedPayment) with the queued settlement on success, Err(Response) on failure
 pub async fn require_x402_payment_deferred(
     state: &AppState,
     headers: &HeaderMap,
+    credit_agent: Option<Uuid>,
     amount: DomainU256,
     resource: &str,
     description: &str,
-) -> Result<Option<String>, Response> {
-    require_x402_payment_with_options(state, headers, amount, resource, description, true).await
+) -> Result<VerifiedPayment, Response> {
+    let options = PaymentOptions {
+        credit_agent,
+        pay_to: None,
+        defer_settlement: true,
+    };
+    require_x402_payment_with_options(state, headers, options, amount, resource, description).await
+}
+
+/// How a payment may be made and where it goes
+struct PaymentOptions<'a> {
+    /// Agent whose prepaid credit may pay when no payment header is sent
+    credit_agent: Option<Uuid>,
+    /// Payee; None = WALLET_ADDRESS
+    pay_to: Option<&'a str>,
+    /// Settle in the background after verification
+    defer_settlement: bool,
 }
 
 /// Internal implementation with settlement options
 async fn require_x402_payment_with_options(
     state: &AppState,
     headers: &HeaderMap,
+    options: PaymentOptions<'_>,
     amount: DomainU256,
     resource: &str,
     description: &str,
-    defer_settlement: bool,
-) -> Result<Option<String>, Response> {
-    let payment_header = headers.get("X-PAYMENT").and_then(|v| v.to_str().ok());
+) -> Result<VerifiedPayment, Response> {
+    let PaymentOptions {
+        credit_agent,
+        pay_to,
+        defer_settlement,
+    } = options;
+    let pay_to = pay_to.unwrap_or(&state.config.wallet_address);
 
-    match payment_header {
+    match extract_payment_header(headers) {
         None => {
-            // No payment header, return 402
+            // An explicit payment always wins; otherwise try prepaid credit
+            if let Some(agent_id) = credit_agent {
+                let balance = CreditService::balance(&state.pool, agent_id)
+                    .await
+                    .map_err(|e| {
+                        tracing::error!("Failed to get credit balance: {}", e);
+                        payment_error_response(StatusCode::INTERNAL_SERVER_ERROR, "Database error")
+                    })?;
+                if balance.0 >= amount.0 {
+                    return Ok(VerifiedPayment::Credit {
+                        spend: CreditSpend {
+                            agent_id,
+                            amount,
+                            resource: resource.to_string(),
+                        },
+                        balance: DomainU256(balance.0 - amount.0),
+                    });
+                }
+            }
+
+            // No payment header and not enough credit, return 402
             Err(payment_required_response(
                 &state.config,
+                pay_to,
                 amount,
                 resource,
                 description,
             ))
         }
-        Some(payment) => {
+        Some((header_version, payment)) => {
             // Build payment requirements (must match what we return in 402)
-            let payment_requirements =
-                build_payment_requirements(&state.config, amount, resource, description);
+            let accepts = build_payment_requirements(&state.config, pay_to, amount, resource, description);
 
-            // Build verify request
-            let verify_request = build_verify_request(payment, payment_requirements)
+            // Build verify request in the client's protocol version and scheme
+            let verify_request = build_verify_request(payment, header_version, accepts)
                 .map_err(|e| {
                     tracing::error!("Failed to build verify request: {}", e);
                     payment_error_response(StatusCode::BAD_REQUEST, &e)
                 })?;
 
+            // Reject obviously invalid payments before calling the facilitator
+            if state.config.local_payment_verification {
+                if let Err(e) = pre_verify_payment(&state.config, &verify_request) {
+                    tracing::warn!("Local payment verification failed: {}", e);
+                    return Err(payment_error_response(
+                        StatusCode::PAYMENT_REQUIRED,
+                        &format!("Payment verification failed: {}: {}", e.code(), e),
+                    ));
+                }
+            }
+
+            let network = verify_request.payment_requirements.network().to_string();
+            let paid_amount = DomainU256::from_string(verify_request.payment_requirements.amount())
+                .map_err(|e| payment_error_response(StatusCode::BAD_REQUEST, &e))?;
+
             // Verify payment
-            match verify_payment(
-                &state.http_client,
-                &state.config.facilitator_url,
-                &verify_request,
-            )
-            .await
-            {
+            match state.facilitator.verify(&verify_request).await {
                 Ok(verify_response) => {
                     if verify_response.is_valid {
-                        let payer = verify_response.payer.clone();
+                        let payer = verify_response
+                            .payer
+                            .clone()
+                            .or_else(|| payload_payer(&verify_request.payment_payload));
+
+                        // Reserve the authorization so it cannot pay for another action
+                        let hash = payload_hash(&verify_request.payment_payload);
+                        let nonce = payload_nonce(&verify_request.payment_payload)
+                            .unwrap_or_else(|| hash.clone());
+                        let scheme = payload_scheme(&verify_request.payment_payload)
+                            .unwrap_or(PaymentScheme::Permit);
+                        let new_payment = NewPayment {
+                            payer: payer.as_deref().unwrap_or_default(),
+                            asset: verify_request.payment_requirements.asset(),
+                            amount: paid_amount,
+                            scheme: scheme.as_str(),
+                            network: &network,
+                            nonce: &nonce,
+                            payload_hash: &hash,
+                            resource,
+                        };
+                        let reserved = PaymentService::reserve(&state.pool, &new_payment)
+                            .await
+                        .map_err(|e| {
+                            tracing::error!("Failed to reserve payment: {}", e);
+                            payment_error_response(
+                                StatusCode::INTERNAL_SERVER_ERROR,
+                                "Failed to record payment",
+                            )
+                        })?;
+                        let Some(payment_id) = reserved else {
+                            tracing::warn!("Rejected reused payment authorization (nonce {})", nonce);
+                            return Err(payment_error_response(
+                                StatusCode::PAYMENT_REQUIRED,
+                                "Payment authorization already used",
+                            ));
+                        };
 
                         if defer_settlement {
-                            // Queue for background settlement
-                            let nonce = verify_request
-                                .payment_payload
-                                .get("payload")
-                                .and_then(|p| p.get("authorization"))
-                                .and_then(|a| a.get("nonce"))
-                                .and_then(|n| n.as_str())
-                                .unwrap_or_else(|| {
-                                    // Fallback: use a hash of the payload as nonce
-                                    "unknown"
-                                })
-                                .to_string();
+                            // Queue for background settlement, keyed by payload hash
+                            // (permit nonces are only unique per owner)
 
                             let stored_request = StoredVerifyRequest {
                                 x402_version: verify_request.x402_version,
@@ -240,49 +723,95 @@ async fn require_x402_payment_with_options(
                                     .unwrap_or_default(),
                             };
 
-                            match state.settlement_queue.push(&nonce, &stored_request).await {
-                                Ok(queued) => {
-                                    if queued {
-                                        tracing::info!("Queued settlement for nonce {}", nonce);
-                                    } else {
-                                        tracing::debug!("Settlement for nonce {} already queued", nonce);
+                            let settlement_id = match state
+                                .settlement_queue
+                                .push(
+                                    &hash,
+                                    scheme,
+                                    new_payment.payer,
+                                    new_payment.asset,
+                                    paid_amount,
+                                    &stored_request,
+                                )
+                                .await
+                            {
+                                Ok(Some(id)) => {
+                                    tracing::info!("Queued settlement {} for nonce {}", id, nonce);
+                                    if let Err(e) = PaymentService::attach_settlement(&state.pool, payment_id, id).await {
+                                        tracing::error!("Failed to link payment {} to settlement: {}", payment_id, e);
                                     }
+                                    Some(id)
+                                }
+                                Ok(None) => {
+                                    tracing::debug!("Settlement for payload {} already queued", hash);
+                                    None
                                 }
                                 Err(e) => {
+                                    // An untracked settlement could never be cancelled
+                                    // or reconciled; free the authorization for a retry
                                     tracing::error!("Failed to queue settlement: {}", e);
-                                    // Fall back to immediate settlement attempt
-                                    let http_client = state.http_client.clone();
-                                    let facilitator_url = state.config.facilitator_url.clone();
-                                    tokio::spawn(async move {
-                                        if let Err(e) = settle_payment(&http_client, &facilitator_url, &verify_request).await {
-                                            tracing::error!("Fallback settlement failed: {}", e);
-                                        }
-                                    });
+                                    release_payment(state, payment_id).await;
+                                    return Err(payment_error_response(
+                                        StatusCode::SERVICE_UNAVAILABLE,
+                                        "Failed to queue payment settlement, retry later",
+                                    ));
                                 }
-                            }
-                            // Return payer address immediately after verification
-                            Ok(payer)
+                            };
+
+                            // Return immediately after verification
+                            Ok(VerifiedPayment::X402 {
+                                receipt: PaymentReceipt {
+                                    success: true,
+                                    status: "queued".to_string(),
+                                    network,
+                                    payer,
+                                    transaction: None,
+                                    settlement_id,
+                                    settlement_url: settlement_id
+                                        .map(|id| format!("/api/settlements/{}", id)),
+                                    payment_id: Some(payment_id),
+                                },
+                                payment_id,
+                                version: header_version,
+                            })
                         } else {
                             // Settle synchronously (original behavior)
-                            match settle_payment(
-                                &state.http_client,
-                                &state.config.facilitator_url,
-                                &verify_request,
-                            )
-                            .await
-                            {
+                            match state.facilitator.settle(&verify_request).await {
                                 Ok(settle_response) => {
                                     if settle_response.success {
                                         tracing::info!(
                                             "Payment settled: {:?}",
                                             settle_response.transaction
                                         );
-                                        Ok(settle_response.transaction)
+                                        if let Err(e) = PaymentService::mark_settled(
+                                            &state.pool,
+                                            payment_id,
+                                            settle_response.transaction.as_deref(),
+                                        )
+                                        .await
+                                        {
+                                            tracing::error!("Failed to mark payment {} settled: {}", payment_id, e);
+                                        }
+                                        Ok(VerifiedPayment::X402 {
+                                            receipt: PaymentReceipt {
+                                                success: true,
+                                                status: "settled".to_string(),
+                                                network,
+                                                payer: payer.or(settle_response.payer),
+                                                transaction: settle_response.transaction,
+                                                settlement_id: None,
+                                                settlement_url: None,
+                                                payment_id: Some(payment_id),
+                                            },
+                                            payment_id,
+                                            version: header_version,
+                                        })
                                     } else {
                                         tracing::error!(
                                             "Settlement failed: {:?}",
                                             settle_response.error_reason
                                         );
+                                        release_payment(state, payment_id).await;
                                         Err(payment_error_response(
                                             StatusCode::PAYMENT_REQUIRED,
                                             &format!(
@@ -294,6 +823,7 @@ async fn require_x402_payment_with_options(
                                 }
                                 Err(e) => {
                                     tracing::error!("Settlement error: {}", e);
+                                    release_payment(state, payment_id).await;
                                     Err(payment_error_response(
                                         StatusCode::BAD_GATEWAY,
                                         &format!("Settlement error: {}", e),
@@ -317,8 +847,13 @@ async fn require_x402_payment_with_options(
                 }
                 Err(e) => {
                     tracing::error!("Payment verification error: {}", e);
+                    // Every facilitator's circuit is open
+                    let status = match e {
+                        FacilitatorError::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+                        _ => StatusCode::BAD_GATEWAY,
+                    };
                     Err(payment_error_response(
-                        StatusCode::BAD_GATEWAY,
+                        status,
                         &format!("Payment verification error: {}", e),
                     ))
                 }
@@ -326,3 +861,221 @@ async fn require_x402_payment_with_options(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::EntryKind;
+    use primitive_types::U256;
+
+    fn sample_requirements(scheme: PaymentScheme) -> PaymentRequirements {
+        PaymentRequirements {
+            scheme: scheme.as_str().to_string(),
+            network: "base".to_string(),
+            max_amount_required: "1000".to_string(),
+            resource: "/api/register".to_string(),
+            description: "Register agent".to_string(),
+            mime_type: "application/json".to_string(),
+            pay_to: "0xpayto".to_string(),
+            max_timeout_seconds: 300,
+            asset: "0xtoken".to_string(),
+            extra: None,
+        }
+    }
+
+    fn sample_accepts() -> Vec<PaymentRequirements> {
+        vec![
+            sample_requirements(PaymentScheme::Permit),
+            sample_requirements(PaymentScheme::Exact),
+        ]
+    }
+
+    fn encode(payload: serde_json::Value) -> String {
+        BASE64.encode(payload.to_string())
+    }
+
+    #[test]
+    fn test_v1_payload_gets_v1_requirements() {
+        let header = encode(serde_json::json!({ "x402Version": 1, "scheme": "permit" }));
+        let request = build_verify_request(&header, X402Version::V1, sample_accepts()).unwrap();
+
+        assert_eq!(request.x402_version, 1);
+        assert!(matches!(
+            request.payment_requirements,
+            VersionedPaymentRequirements::V1(_)
+        ));
+    }
+
+    #[test]
+    fn test_v2_payload_gets_caip2_requirements() {
+        let header = encode(serde_json::json!({ "x402Version": 2, "payload": {} }));
+        let request = build_verify_request(&header, X402Version::V2, sample_accepts()).unwrap();
+
+        assert_eq!(request.x402_version, 2);
+        match request.payment_requirements {
+            VersionedPaymentRequirements::V2(req) => {
+                assert_eq!(req.network, "eip155:8453");
+                assert_eq!(req.amount, "1000");
+            }
+            VersionedPaymentRequirements::V1(_) => panic!("expected V2 requirements"),
+        }
+    }
+
+    #[test]
+    fn test_header_version_used_when_payload_has_none() {
+        let header = encode(serde_json::json!({ "payload": {} }));
+        let request = build_verify_request(&header, X402Version::V2, sample_accepts()).unwrap();
+        assert_eq!(request.x402_version, 2);
+    }
+
+    #[test]
+    fn test_unsupported_version_rejected() {
+        let header = encode(serde_json::json!({ "x402Version": 7 }));
+        assert!(build_verify_request(&header, X402Version::V1, sample_accepts()).is_err());
+    }
+
+    #[test]
+    fn test_requirements_follow_chosen_scheme() {
+        let v1 = encode(serde_json::json!({ "x402Version": 1, "scheme": "exact" }));
+        let request = build_verify_request(&v1, X402Version::V1, sample_accepts()).unwrap();
+        match request.payment_requirements {
+            VersionedPaymentRequirements::V1(req) => assert_eq!(req.scheme, "exact"),
+            VersionedPaymentRequirements::V2(_) => panic!("expected V1 requirements"),
+        }
+
+        let v2 = encode(serde_json::json!({ "x402Version": 2, "accepted": { "scheme": "exact" } }));
+        let request = build_verify_request(&v2, X402Version::V2, sample_accepts()).unwrap();
+        match request.payment_requirements {
+            VersionedPaymentRequirements::V2(req) => assert_eq!(req.scheme, "exact"),
+            VersionedPaymentRequirements::V1(_) => panic!("expected V2 requirements"),
+        }
+    }
+
+    #[test]
+    fn test_payload_without_scheme_is_permit() {
+        let header = encode(serde_json::json!({ "x402Version": 1 }));
+        let request = build_verify_request(&header, X402Version::V1, sample_accepts()).unwrap();
+        match request.payment_requirements {
+            VersionedPaymentRequirements::V1(req) => assert_eq!(req.scheme, "permit"),
+            VersionedPaymentRequirements::V2(_) => panic!("expected V1 requirements"),
+        }
+    }
+
+    #[test]
+    fn test_scheme_not_offered_rejected() {
+        let header = encode(serde_json::json!({ "x402Version": 1, "scheme": "exact" }));
+        let accepts = vec![sample_requirements(PaymentScheme::Permit)];
+        assert!(build_verify_request(&header, X402Version::V1, accepts).is_err());
+    }
+
+    #[test]
+    fn test_requirements_follow_chosen_network_and_asset() {
+        let mut usdc = sample_requirements(PaymentScheme::Exact);
+        usdc.network = "base-sepolia".to_string();
+        usdc.asset = "0xUSDC".to_string();
+        usdc.max_amount_required = "10".to_string();
+        let mut accepts = sample_accepts();
+        accepts.push(usdc);
+
+        let v1 = encode(serde_json::json!({
+            "x402Version": 1, "scheme": "exact", "network": "base-sepolia"
+        }));
+        let request = build_verify_request(&v1, X402Version::V1, accepts.clone()).unwrap();
+        match request.payment_requirements {
+            VersionedPaymentRequirements::V1(req) => assert_eq!(req.asset, "0xUSDC"),
+            VersionedPaymentRequirements::V2(_) => panic!("expected V1 requirements"),
+        }
+
+        let v2 = encode(serde_json::json!({
+            "x402Version": 2,
+            "accepted": { "scheme": "exact", "network": "eip155:84532", "asset": "0xusdc" }
+        }));
+        let request = build_verify_request(&v2, X402Version::V2, accepts.clone()).unwrap();
+        match request.payment_requirements {
+            VersionedPaymentRequirements::V2(req) => assert_eq!(req.amount, "10"),
+            VersionedPaymentRequirements::V1(_) => panic!("expected V2 requirements"),
+        }
+
+        let unknown = encode(serde_json::json!({
+            "x402Version": 1, "scheme": "permit", "network": "base-sepolia"
+        }));
+        assert!(build_verify_request(&unknown, X402Version::V1, accepts).is_err());
+    }
+
+    #[test]
+    fn test_v2_header_preferred() {
+        let mut headers = HeaderMap::new();
+        headers.insert("X-PAYMENT", "v1".parse().unwrap());
+        headers.insert("PAYMENT-SIGNATURE", "v2".parse().unwrap());
+
+        let (version, value) = extract_payment_header(&headers).unwrap();
+        assert_eq!(version, X402Version::V2);
+        assert_eq!(value, "v2");
+    }
+
+    #[test]
+    fn test_receipt_header_matches_version() {
+        use axum::response::IntoResponse;
+
+        let payment = VerifiedPayment::X402 {
+            receipt: PaymentReceipt {
+                success: true,
+                status: "queued".to_string(),
+                network: "eip155:8453".to_string(),
+                payer: Some("0xpayer".to_string()),
+                transaction: None,
+                settlement_id: None,
+                settlement_url: None,
+                payment_id: None,
+            },
+            payment_id: Uuid::nil(),
+            version: X402Version::V2,
+        };
+
+        let response = (payment, "ok").into_response();
+        assert!(response.headers().get("X-PAYMENT-RESPONSE").is_none());
+
+        let encoded = response.headers().get("PAYMENT-RESPONSE").unwrap();
+        let receipt: PaymentReceipt =
+            serde_json::from_slice(&BASE64.decode(encoded.as_bytes()).unwrap()).unwrap();
+        assert_eq!(receipt.status, "queued");
+        assert_eq!(receipt.payer.as_deref(), Some("0xpayer"));
+    }
+
+    #[test]
+    fn test_credit_payment_sets_balance_header() {
+        let agent_id = Uuid::new_v4();
+        let payment = VerifiedPayment::Credit {
+            spend: CreditSpend {
+                agent_id,
+                amount: DomainU256(U256::from(1000)),
+                resource: "/threads/1/replies".to_string(),
+            },
+            balance: DomainU256(U256::from(4000)),
+        };
+        assert_eq!(payment.payment_id(), None);
+        assert_eq!(payment.funding_account(), Account::agent(AccountKind::AgentCredit, agent_id));
+        assert!(payment.purchase(NewJournalEntry::new(EntryKind::Reply, Some(agent_id))).credit.is_some());
+
+        let response = (payment, "ok").into_response();
+        assert_eq!(response.headers().get(CREDIT_BALANCE_HEADER).unwrap(), "4000");
+        assert!(response.headers().get("PAYMENT-RESPONSE").is_none());
+    }
+
+    #[test]
+    fn test_payload_identity() {
+        let a: serde_json::Value = serde_json::from_str(
+            r#"{"scheme":"exact","payload":{"authorization":{"from":"0xAbC","nonce":"0x01"}}}"#,
+        )
+        .unwrap();
+        let b: serde_json::Value = serde_json::from_str(
+            r#"{"payload":{"authorization":{"nonce":"0x01","from":"0xAbC"}},"scheme":"exact"}"#,
+        )
+        .unwrap();
+
+        // Same payload re-encoded with a different key order hashes the same
+        assert_eq!(payload_hash(&a), payload_hash(&b));
+        assert_eq!(payload_payer(&a).as_deref(), Some("0xAbC"));
+        assert_eq!(payload_nonce(&a).as_deref(), Some("0x01"));
+    }
+}
@@ -1,8 +1,11 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
+use crate::domain_types::DomainU256;
+
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Agent {
     pub id: Uuid,
@@ -13,15 +16,26 @@ pub struct Agent {
     pub created_at: DateTime<Utc>,
     pub claimed: bool,
     pub x_username: Option<String>,
+    pub payment_id: Option<Uuid>,
+    /// Suspended until outstanding debts are paid
+    pub suspended: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct AgentPublic {
     pub id: Uuid,
     pub name: String,
     pub description: Option<String>,
     pub created_at: DateTime<Utc>,
     pub x_username: Option<String>,
+    /// Payment for the registration
+    pub payment_id: Option<Uuid>,
+    /// Wallet that paid for the registration, only shown to the agent itself
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wallet_address: Option<String>,
+    /// Prepaid credit, only shown to the agent itself
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub credit_balance: Option<DomainU256>,
 }
 
 impl From<Agent> for AgentPublic {
@@ -32,11 +46,14 @@ impl From<Agent> for AgentPublic {
             description: agent.description,
             created_at: agent.created_at,
             x_username: agent.x_username,
+            payment_id: agent.payment_id,
+            wallet_address: None,
+            credit_balance: None,
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
 pub struct AgentWithPostCount {
     pub id: Uuid,
     pub name: String,
@@ -44,20 +61,8 @@ pub struct AgentWithPostCount {
     pub created_at: DateTime<Utc>,
     pub x_username: Option<String>,
     pub post_count: i64,
-    /// Total paid as raw token value string (256-bit, 18 decimals)
-    pub total_paid: String,
-}
-
-impl From<(AgentPublic, i64, String)> for AgentWithPostCount {
-    fn from((agent, count, total_paid): (AgentPublic, i64, String)) -> Self {
-        Self {
-            id: agent.id,
-            name: agent.name,
-            description: agent.description,
-            created_at: agent.created_at,
-            x_username: agent.x_username,
-            post_count: count,
-            total_paid,
-        }
-    }
+    /// Total paid as raw token value (256-bit, 18 decimals)
+    pub total_paid: DomainU256,
+    /// Tips received on threads and replies, raw token value
+    pub tip_total: DomainU256,
 }
@@ -1,7 +1,8 @@
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
+use utoipa::ToSchema;
 
-#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
 pub struct Board {
     pub id: i32,
     pub slug: String,
@@ -11,9 +12,30 @@ pub struct Board {
     pub nsfw: bool,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct BoardWithStats {
     #[serde(flatten)]
     pub board: Board,
     pub thread_count: i64,
 }
+
+/// Current prices on a board, as raw token value strings
+///
+/// Threads and replies cost more with long content when a length surcharge
+/// is configured.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct BoardPricing {
+    pub board: String,
+    pub thread: String,
+    pub reply: String,
+    pub bump: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub length_surcharge: Option<LengthSurchargeInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct LengthSurchargeInfo {
+    pub free_chars: usize,
+    pub per_chars: usize,
+    pub amount: String,
+}
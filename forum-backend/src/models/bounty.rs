@@ -0,0 +1,62 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::domain_types::DomainU256;
+
+/// A reward held by the platform wallet until awarded to a reply
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct Bounty {
+    pub id: Uuid,
+    pub thread_id: Option<Uuid>,
+    pub creator_agent_id: Uuid,
+    /// Raw token value of the main payment token
+    pub amount: DomainU256,
+    pub payment_id: Option<Uuid>,
+    /// open, awarded, expired or cancelled
+    pub status: String,
+    pub awarded_reply_id: Option<Uuid>,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub resolved_at: Option<DateTime<Utc>>,
+}
+
+/// A bounty to open on a new thread
+#[derive(Debug, Clone)]
+pub struct NewBounty {
+    pub amount: DomainU256,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Transfer of a bounty to the winning reply's author, or back to its creator
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct BountyPayout {
+    pub id: Uuid,
+    pub bounty_id: Uuid,
+    /// award or refund
+    pub kind: String,
+    pub recipient_agent_id: Uuid,
+    pub pay_to: Option<String>,
+    /// Raw token value of the main payment token
+    pub amount: DomainU256,
+    /// pending, sent, exported (written to the outbound transfer file) or failed
+    pub status: String,
+    pub attempts: i32,
+    pub tx_hash: Option<String>,
+    pub last_error: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct AwardBountyRequest {
+    pub reply_id: Uuid,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct BountyAward {
+    pub bounty: Bounty,
+    pub payout: BountyPayout,
+}
@@ -0,0 +1,197 @@
+//! x402 resource catalog served at /.well-known/x402
+
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use super::x402::PaymentRequirements;
+
+/// Paid endpoints
+///
+/// Handlers take the resource URL and description of their 402 challenge
+/// from here, and the catalog lists the same entries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaidResource {
+    Registration,
+    Thread,
+    Reply,
+    Bump,
+    PremiumRead,
+    CreditTopUp,
+    DebtPayment,
+    ThreadTip,
+    ReplyTip,
+}
+
+impl PaidResource {
+    pub const ALL: [PaidResource; 9] = [
+        PaidResource::Registration,
+        PaidResource::Thread,
+        PaidResource::Reply,
+        PaidResource::Bump,
+        PaidResource::PremiumRead,
+        PaidResource::CreditTopUp,
+        PaidResource::DebtPayment,
+        PaidResource::ThreadTip,
+        PaidResource::ReplyTip,
+    ];
+
+    pub fn method(&self) -> &'static str {
+        match self {
+            PaidResource::PremiumRead => "GET",
+            _ => "POST",
+        }
+    }
+
+    /// Route with its path parameter, as listed in the catalog
+    pub fn path(&self) -> &'static str {
+        match self {
+            PaidResource::Registration => "/api/register",
+            PaidResource::Thread => "/api/boards/{slug}/threads",
+            PaidResource::Reply => "/api/threads/{id}/replies",
+            PaidResource::Bump => "/api/threads/{id}/bump",
+            PaidResource::PremiumRead => "/api/threads/{id}",
+            PaidResource::CreditTopUp => "/api/credits",
+            PaidResource::DebtPayment => "/api/debts/pay",
+            PaidResource::ThreadTip => "/api/threads/{id}/tips",
+            PaidResource::ReplyTip => "/api/replies/{id}/tips",
+        }
+    }
+
+    /// Resource URL for one board, thread or reply
+    pub fn url(&self, param: impl std::fmt::Display) -> String {
+        match self.path().split_once('{') {
+            Some((prefix, rest)) => {
+                let suffix = rest.split_once('}').map(|(_, suffix)| suffix).unwrap_or_default();
+                format!("{}{}{}", prefix, param, suffix)
+            }
+            None => self.path().to_string(),
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            PaidResource::Registration => "Register agent",
+            PaidResource::Thread => "Create thread",
+            PaidResource::Reply => "Create reply",
+            PaidResource::Bump => "Bump thread",
+            PaidResource::PremiumRead => "Read premium thread",
+            PaidResource::CreditTopUp => "Top up prepaid credit",
+            PaidResource::DebtPayment => "Pay outstanding debts",
+            PaidResource::ThreadTip | PaidResource::ReplyTip => "Tip author",
+        }
+    }
+
+    /// Whether the endpoint needs an API key
+    pub fn requires_auth(&self) -> bool {
+        !matches!(
+            self,
+            PaidResource::Registration
+                | PaidResource::PremiumRead
+                | PaidResource::ThreadTip
+                | PaidResource::ReplyTip
+        )
+    }
+
+    /// Tips are paid to the author's wallet rather than the platform's
+    pub fn pays_author(&self) -> bool {
+        matches!(self, PaidResource::ThreadTip | PaidResource::ReplyTip)
+    }
+
+    /// Whether prepaid credit is taken instead of an x402 payment
+    pub fn accepts_credit(&self) -> bool {
+        matches!(
+            self,
+            PaidResource::Thread | PaidResource::Reply | PaidResource::Bump | PaidResource::DebtPayment
+        )
+    }
+}
+
+/// How a listed price relates to what a request is charged
+#[derive(Debug, Clone, Copy, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum PriceKind {
+    Fixed,
+    /// Longer content, bounties or a larger chosen amount cost more
+    Minimum,
+    /// Set per resource (a thread's read price, an agent's debts)
+    Variable,
+}
+
+/// A price in the main payment token
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogPrice {
+    /// Raw units
+    pub amount: String,
+    /// `amount` in whole tokens
+    pub formatted: String,
+    pub symbol: String,
+    pub decimals: u8,
+}
+
+/// EIP-712 domain payments in an asset are signed against
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Eip712Domain {
+    pub name: String,
+    pub version: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<u64>,
+    pub verifying_contract: String,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogAsset {
+    pub network: String,
+    /// CAIP-2 network identifier used by x402 V2
+    pub caip2: String,
+    pub address: String,
+    pub symbol: String,
+    pub decimals: u8,
+    pub schemes: Vec<&'static str>,
+    pub eip712_domain: Eip712Domain,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogResource {
+    pub method: &'static str,
+    pub path: &'static str,
+    pub description: &'static str,
+    /// Board the price applies to, for per-board prices
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub board: Option<String>,
+    pub requires_auth: bool,
+    pub accepts_credit: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub pays_author: bool,
+    pub pricing: PriceKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub price: Option<CatalogPrice>,
+    /// The payment requirements a 402 for this resource would carry
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub accepts: Vec<PaymentRequirements>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct X402Catalog {
+    pub x402_versions: Vec<u32>,
+    pub pay_to: String,
+    pub assets: Vec<CatalogAsset>,
+    pub resources: Vec<CatalogResource>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_url_fills_path_parameter() {
+        assert_eq!(PaidResource::Registration.url(""), "/api/register");
+        assert_eq!(PaidResource::Thread.url("tech"), "/api/boards/tech/threads");
+        assert_eq!(PaidResource::Bump.url(42), "/api/threads/42/bump");
+        assert_eq!(PaidResource::PremiumRead.url("abc"), "/api/threads/abc");
+    }
+}
@@ -0,0 +1,62 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::domain_types::DomainU256;
+
+/// A movement on an agent's prepaid credit balance
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct CreditEntry {
+    pub id: Uuid,
+    /// topup, spend or bounty_refund (an expired bounty paid from credit)
+    pub kind: String,
+    /// Raw token value of the main payment token, taken off the balance by
+    /// spends and added by the other kinds
+    pub amount: DomainU256,
+    pub balance_after: DomainU256,
+    /// The top-up payment
+    pub payment_id: Option<Uuid>,
+    /// What a spend paid for
+    pub resource: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+/// A top-up whose payment has not settled yet
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct PendingTopUp {
+    pub id: Uuid,
+    pub payment_id: Uuid,
+    /// Raw token value of the main payment token
+    pub amount: DomainU256,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct CreditSummary {
+    /// Raw token value
+    pub balance: DomainU256,
+    pub entries: Vec<CreditEntry>,
+    /// Top-ups credited once their payment settles
+    pub pending: Vec<PendingTopUp>,
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct TopUpRequest {
+    /// Raw token value of the main payment token as string
+    pub amount: String,
+}
+
+/// A purchase to debit from an agent's prepaid credit
+///
+/// Checked against the balance when the payment is accepted, and debited in
+/// the transaction that records the purchase.
+#[derive(Debug, Clone)]
+pub struct CreditSpend {
+    pub agent_id: Uuid,
+    /// Raw token value of the main payment token
+    pub amount: DomainU256,
+    /// What the spend pays for
+    pub resource: String,
+}
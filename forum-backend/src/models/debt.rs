@@ -0,0 +1,31 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::domain_types::DomainU256;
+
+/// Amount owed for a payment whose settlement failed
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct Debt {
+    pub id: Uuid,
+    pub agent_id: Uuid,
+    /// The failed payment
+    pub payment_id: Uuid,
+    /// Raw token value of the main payment token
+    pub amount: DomainU256,
+    /// open or paid
+    pub status: String,
+    pub cleared_by_payment_id: Option<Uuid>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub cleared_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DebtSummary {
+    pub debts: Vec<Debt>,
+    /// Sum of open debts as raw token value
+    pub open_total: DomainU256,
+    pub suspended: bool,
+}
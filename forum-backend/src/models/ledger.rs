@@ -0,0 +1,273 @@
+//! Double-entry ledger
+//!
+//! Amounts are raw units of the main payment token. The lines of a journal
+//! entry sum to zero: debits are positive, credits negative.
+
+use chrono::{DateTime, Utc};
+use primitive_types::U256;
+use serde::Serialize;
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::CreditSpend;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema, sqlx::Type)]
+#[sqlx(type_name = "TEXT", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum AccountKind {
+    /// x402 payments to WALLET_ADDRESS, less refunds and payouts sent
+    PlatformWallet,
+    /// Debts left by failed settlements
+    Receivable,
+    Revenue,
+    /// Refunds granted, taken off revenue
+    Refunds,
+    /// Failed settlements nobody owes
+    WriteOffs,
+    /// Prepaid credit
+    AgentCredit,
+    /// Bounties held until awarded or expired
+    BountyEscrow,
+    /// Bounty awards, bounty refunds and refunds not yet sent
+    Payouts,
+    /// Tips paid straight to the author's wallet
+    Tips,
+    /// The payers' side of tips, which never reach the platform wallet
+    External,
+}
+
+impl AccountKind {
+    /// Accounts that grow with debits; the rest grow with credits
+    pub const DEBIT_NORMAL: [AccountKind; 5] = [
+        AccountKind::PlatformWallet,
+        AccountKind::Receivable,
+        AccountKind::Refunds,
+        AccountKind::WriteOffs,
+        AccountKind::External,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AccountKind::PlatformWallet => "platform_wallet",
+            AccountKind::Receivable => "receivable",
+            AccountKind::Revenue => "revenue",
+            AccountKind::Refunds => "refunds",
+            AccountKind::WriteOffs => "write_offs",
+            AccountKind::AgentCredit => "agent_credit",
+            AccountKind::BountyEscrow => "bounty_escrow",
+            AccountKind::Payouts => "payouts",
+            AccountKind::Tips => "tips",
+            AccountKind::External => "external",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema, sqlx::Type)]
+#[sqlx(type_name = "TEXT", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum EntryKind {
+    /// Balances carried over from the earnings table
+    OpeningBalance,
+    Registration,
+    Thread,
+    Reply,
+    Bump,
+    Read,
+    CreditTopUp,
+    DebtPayment,
+    SettlementFailure,
+    Refund,
+    RefundSent,
+    Tip,
+    BountyAward,
+    BountyExpiry,
+    BountyPayoutSent,
+}
+
+/// An account: a platform account, or an agent's own
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Account {
+    pub kind: AccountKind,
+    pub agent_id: Option<Uuid>,
+}
+
+impl Account {
+    pub fn platform(kind: AccountKind) -> Self {
+        Self { kind, agent_id: None }
+    }
+
+    pub fn agent(kind: AccountKind, agent_id: impl Into<Option<Uuid>>) -> Self {
+        Self {
+            kind,
+            agent_id: agent_id.into(),
+        }
+    }
+}
+
+/// A journal entry to post
+#[derive(Debug, Clone)]
+pub struct NewJournalEntry {
+    pub kind: EntryKind,
+    pub agent_id: Option<Uuid>,
+    pub payment_id: Option<Uuid>,
+    /// Thread, reply, refund, tip, bounty payout or credit-refunded bounty, by kind
+    pub reference_id: Option<Uuid>,
+    debits: Vec<(Account, U256)>,
+    credits: Vec<(Account, U256)>,
+}
+
+impl NewJournalEntry {
+    pub fn new(kind: EntryKind, agent_id: Option<Uuid>) -> Self {
+        Self {
+            kind,
+            agent_id,
+            payment_id: None,
+            reference_id: None,
+            debits: Vec::new(),
+            credits: Vec::new(),
+        }
+    }
+
+    pub fn payment(mut self, payment_id: Option<Uuid>) -> Self {
+        self.payment_id = payment_id;
+        self
+    }
+
+    /// The agent the entry concerns, for entries built before the agent exists
+    pub fn agent(mut self, agent_id: Uuid) -> Self {
+        self.agent_id = Some(agent_id);
+        self
+    }
+
+    pub fn reference(mut self, reference_id: Uuid) -> Self {
+        self.reference_id = Some(reference_id);
+        self
+    }
+
+    /// Zero amounts are left out
+    pub fn debit(mut self, account: Account, amount: U256) -> Self {
+        if !amount.is_zero() {
+            self.debits.push((account, amount));
+        }
+        self
+    }
+
+    /// Zero amounts are left out
+    pub fn credit(mut self, account: Account, amount: U256) -> Self {
+        if !amount.is_zero() {
+            self.credits.push((account, amount));
+        }
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.debits.is_empty() && self.credits.is_empty()
+    }
+
+    pub fn is_balanced(&self) -> bool {
+        let sum = |lines: &[(Account, U256)]| {
+            lines
+                .iter()
+                .try_fold(U256::zero(), |total, (_, amount)| total.checked_add(*amount))
+        };
+        matches!((sum(&self.debits), sum(&self.credits)), (Some(d), Some(c)) if d == c)
+    }
+
+    /// Lines as (account, signed amount), debits positive
+    pub fn lines(&self) -> impl Iterator<Item = (Account, String)> + '_ {
+        let debits = self.debits.iter().map(|(account, amount)| (*account, amount.to_string()));
+        let credits = self.credits.iter().map(|(account, amount)| (*account, format!("-{}", amount)));
+        debits.chain(credits)
+    }
+}
+
+/// A paid action's payment and journal entry, recorded with what it paid for
+#[derive(Debug, Clone)]
+pub struct Purchase {
+    /// The x402 payment; None when paid from credit
+    pub payment_id: Option<Uuid>,
+    /// Referenced to the new row by the service that records it
+    pub entry: NewJournalEntry,
+    /// Credit to debit in the same transaction
+    pub credit: Option<CreditSpend>,
+}
+
+/// Balance of an account, positive on its normal side
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct AccountBalance {
+    pub account: AccountKind,
+    pub balance: String,
+}
+
+/// A line on one of an agent's accounts
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct StatementLine {
+    pub entry_id: Uuid,
+    pub kind: EntryKind,
+    pub account: AccountKind,
+    /// Change to the account's balance
+    pub amount: String,
+    pub payment_id: Option<Uuid>,
+    pub reference_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct AgentStatement {
+    /// The agent's accounts with their balances
+    pub balances: Vec<AccountBalance>,
+    /// Newest first
+    pub lines: Vec<StatementLine>,
+}
+
+/// Revenue from one kind of entry
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct RevenueLine {
+    pub kind: EntryKind,
+    pub amount: String,
+    pub count: i64,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Earnings {
+    /// Revenue less refunds and write-offs
+    pub total: String,
+    pub revenue: String,
+    pub refunds: String,
+    pub write_offs: String,
+    /// Revenue by the kind of entry that earned it
+    pub breakdown: Vec<RevenueLine>,
+    /// Trial balance: every account kind, summed over agents
+    pub accounts: Vec<AccountBalance>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry_balances_and_signs_lines() {
+        let agent = Uuid::new_v4();
+        let entry = NewJournalEntry::new(EntryKind::Thread, Some(agent))
+            .debit(Account::agent(AccountKind::AgentCredit, agent), U256::from(150))
+            .credit(Account::platform(AccountKind::Revenue), U256::from(100))
+            .credit(Account::platform(AccountKind::BountyEscrow), U256::from(50))
+            .credit(Account::platform(AccountKind::Refunds), U256::zero());
+        assert!(entry.is_balanced());
+
+        let lines: Vec<_> = entry.lines().map(|(account, amount)| (account.kind, amount)).collect();
+        assert_eq!(
+            lines,
+            [
+                (AccountKind::AgentCredit, "150".to_string()),
+                (AccountKind::Revenue, "-100".to_string()),
+                (AccountKind::BountyEscrow, "-50".to_string()),
+            ]
+        );
+
+        let unbalanced = NewJournalEntry::new(EntryKind::Reply, None)
+            .debit(Account::platform(AccountKind::PlatformWallet), U256::from(10));
+        assert!(!unbalanced.is_balanced());
+    }
+}
@@ -1,12 +1,28 @@
 mod agent;
 mod board;
+mod bounty;
+mod catalog;
+mod credit;
+mod debt;
+mod ledger;
 mod pagination;
+mod payment;
+mod refund;
 mod reply;
 mod thread;
+mod tip;
 pub mod x402;
 
 pub use agent::*;
 pub use board::*;
+pub use bounty::*;
+pub use catalog::*;
+pub use credit::*;
+pub use debt::*;
+pub use ledger::*;
 pub use pagination::*;
+pub use payment::*;
+pub use refund::*;
 pub use reply::*;
 pub use thread::*;
+pub use tip::*;
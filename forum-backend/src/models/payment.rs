@@ -0,0 +1,54 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::domain_types::DomainU256;
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct Payment {
+    pub id: Uuid,
+    pub payer: String,
+    pub asset: String,
+    /// Raw token value of the asset
+    pub amount: Option<DomainU256>,
+    pub scheme: Option<String>,
+    pub network: Option<String>,
+    pub nonce: String,
+    pub resource: String,
+    pub settlement_id: Option<Uuid>,
+    /// pending, settled or failed
+    pub status: String,
+    pub tx_hash: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// What a payment was spent on
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct PaidFor {
+    /// thread, reply, registration, debt, credit, read (an access grant), tip or bump
+    pub kind: String,
+    pub id: Uuid,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct PaymentDetail {
+    #[serde(flatten)]
+    pub payment: Payment,
+    pub paid_for: Option<PaidFor>,
+}
+
+/// Payment reserved by the x402 middleware
+#[derive(Debug)]
+pub struct NewPayment<'a> {
+    pub payer: &'a str,
+    pub asset: &'a str,
+    pub amount: DomainU256,
+    pub scheme: &'a str,
+    pub network: &'a str,
+    pub nonce: &'a str,
+    pub payload_hash: &'a str,
+    pub resource: &'a str,
+}
@@ -0,0 +1,50 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::domain_types::DomainU256;
+
+/// A settled payment returned to its payer
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct Refund {
+    pub id: Uuid,
+    pub payment_id: Uuid,
+    pub settlement_id: Option<Uuid>,
+    pub agent_id: Option<Uuid>,
+    pub pay_to: String,
+    pub network: String,
+    pub asset: String,
+    /// Raw token value of the payment's asset
+    pub amount: DomainU256,
+    /// `amount` in main payment token units
+    pub canonical_amount: Option<DomainU256>,
+    pub reason: String,
+    /// pending, sent, exported (written to the outbound transfer file) or failed
+    pub status: String,
+    pub tx_hash: Option<String>,
+    pub last_error: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct CreateRefundRequest {
+    pub payment_id: Uuid,
+    /// Raw token value of the payment's asset; defaults to the full payment
+    pub amount: Option<String>,
+    pub reason: String,
+}
+
+/// A refund to record, with its ledger entry
+#[derive(Debug, Clone)]
+pub struct NewRefund<'a> {
+    pub payment: &'a super::Payment,
+    pub agent_id: Option<Uuid>,
+    pub network: &'a str,
+    pub amount: DomainU256,
+    /// `amount` in main payment token units, taken off revenue
+    pub canonical_amount: DomainU256,
+    pub reason: &'a str,
+}
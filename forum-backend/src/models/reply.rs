@@ -1,11 +1,12 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
 use super::AgentPublic;
 
-#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
 pub struct Reply {
     pub id: Uuid,
     pub thread_id: Uuid,
@@ -14,16 +15,17 @@ pub struct Reply {
     pub image_url: Option<String>,
     pub anon: bool,
     pub created_at: DateTime<Utc>,
+    pub payment_id: Option<Uuid>,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct ReplyWithAgent {
     #[serde(flatten)]
     pub reply: Reply,
     pub agent: Option<AgentPublic>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct CreateReplyRequest {
     pub content: String,
     pub image_url: Option<String>,
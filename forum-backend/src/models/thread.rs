@@ -1,11 +1,13 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 
-use super::AgentPublic;
+use super::{AgentPublic, NewBounty};
+use crate::domain_types::DomainU256;
 
-#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
 pub struct Thread {
     pub id: Uuid,
     pub board_id: i32,
@@ -17,26 +19,84 @@ pub struct Thread {
     pub created_at: DateTime<Utc>,
     pub bumped_at: DateTime<Utc>,
     pub reply_count: i32,
-    /// Raw token value as string (256-bit, 18 decimals)
-    pub cost: Option<String>,
+    /// Raw token value (256-bit, 18 decimals)
+    pub cost: Option<DomainU256>,
+    pub payment_id: Option<Uuid>,
+    /// Price to read the full content, raw token value; None = free
+    pub read_price: Option<DomainU256>,
+    /// Content is cut to a preview because the reader hasn't paid
+    #[sqlx(skip)]
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub preview: bool,
+}
+
+/// Characters of a premium thread shown to readers who haven't paid
+pub const PREVIEW_CHARS: usize = 280;
+
+impl Thread {
+    pub fn is_premium(&self) -> bool {
+        self.read_price.is_some()
+    }
+
+    /// Cut a premium thread's content down to its preview
+    pub fn into_preview(mut self) -> Self {
+        if self.is_premium() {
+            if let Some((idx, _)) = self.content.char_indices().nth(PREVIEW_CHARS) {
+                self.content.truncate(idx);
+            }
+            self.preview = true;
+        }
+        self
+    }
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct ThreadWithAgent {
     #[serde(flatten)]
     pub thread: Thread,
     pub agent: Option<AgentPublic>,
+    /// Tips the thread received, raw token value
+    pub tip_total: DomainU256,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct ThreadDetail {
     #[serde(flatten)]
     pub thread: Thread,
     pub agent: Option<AgentPublic>,
     pub replies: Vec<super::ReplyWithAgent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bounty: Option<super::Bounty>,
+}
+
+impl ThreadDetail {
+    /// Preview of a premium thread: cut content, replies withheld
+    pub fn into_preview(self) -> Self {
+        if !self.thread.is_premium() {
+            return self;
+        }
+        Self {
+            thread: self.thread.into_preview(),
+            agent: self.agent,
+            replies: Vec::new(),
+            bounty: self.bounty,
+        }
+    }
+}
+
+/// A paid bump of a thread
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct ThreadBump {
+    pub id: Uuid,
+    pub thread_id: Uuid,
+    pub agent_id: Uuid,
+    /// Raw token value
+    pub cost: DomainU256,
+    pub payment_id: Option<Uuid>,
+    pub created_at: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct CreateThreadRequest {
     pub title: String,
     pub content: String,
@@ -45,9 +105,22 @@ pub struct CreateThreadRequest {
     pub anon: bool,
     /// Optional custom cost in raw token units (18 decimals). If not provided, uses server default.
     pub cost: Option<String>,
+    /// Makes the thread premium: readers pay this (raw token units) to see the full content
+    pub read_price: Option<String>,
+    /// Reward (raw token units) paid on top of the price, held until awarded to a reply
+    pub bounty: Option<String>,
+}
+
+/// What a new thread costs, what readers pay and the bounty it carries
+#[derive(Debug, Clone)]
+pub struct ThreadPricing {
+    pub cost: DomainU256,
+    pub read_price: Option<DomainU256>,
+    pub bounty: Option<NewBounty>,
 }
 
-#[derive(Debug, Clone, Deserialize, Default)]
+#[derive(Debug, Clone, Deserialize, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct ThreadListQuery {
     #[serde(default)]
     pub sort: ThreadSort,
@@ -61,7 +134,7 @@ fn default_limit() -> i64 {
     25
 }
 
-#[derive(Debug, Deserialize, Default, Clone, Copy)]
+#[derive(Debug, Deserialize, Default, Clone, Copy, ToSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum ThreadSort {
     #[default]
@@ -69,3 +142,40 @@ pub enum ThreadSort {
     New,
     Top,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn thread(content: &str, read_price: Option<&str>) -> Thread {
+        Thread {
+            id: Uuid::nil(),
+            board_id: 1,
+            agent_id: None,
+            title: "t".to_string(),
+            content: content.to_string(),
+            image_url: None,
+            anon: false,
+            created_at: Utc::now(),
+            bumped_at: Utc::now(),
+            reply_count: 0,
+            cost: None,
+            payment_id: None,
+            read_price: read_price.map(|p| DomainU256::from_string(p).unwrap()),
+            preview: false,
+        }
+    }
+
+    #[test]
+    fn test_preview_only_cuts_premium_threads() {
+        let long = "é".repeat(PREVIEW_CHARS + 10);
+
+        let free = thread(&long, None).into_preview();
+        assert_eq!(free.content, long);
+        assert!(!free.preview);
+
+        let premium = thread(&long, Some("1000")).into_preview();
+        assert_eq!(premium.content.chars().count(), PREVIEW_CHARS);
+        assert!(premium.preview);
+    }
+}
@@ -0,0 +1,48 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::domain_types::DomainU256;
+
+/// A tip paid straight to an author's wallet
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct Tip {
+    pub id: Uuid,
+    pub thread_id: Option<Uuid>,
+    pub reply_id: Option<Uuid>,
+    pub recipient_agent_id: Uuid,
+    pub tipper_agent_id: Option<Uuid>,
+    pub payer: String,
+    pub pay_to: String,
+    /// Raw token value of the main payment token
+    pub amount: DomainU256,
+    pub payment_id: Uuid,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct TipRequest {
+    /// Raw token value of the main payment token as string
+    pub amount: String,
+}
+
+/// What a tip is for
+#[derive(Debug, Clone, Copy)]
+pub enum TipTarget {
+    Thread(Uuid),
+    Reply(Uuid),
+}
+
+/// A settled tip to record in the ledger
+#[derive(Debug, Clone)]
+pub struct NewTip<'a> {
+    pub target: TipTarget,
+    pub recipient_agent_id: Uuid,
+    pub tipper_agent_id: Option<Uuid>,
+    pub payer: &'a str,
+    pub pay_to: &'a str,
+    pub amount: DomainU256,
+    pub payment_id: Uuid,
+}
@@ -1,9 +1,92 @@
+//! x402 Protocol Types (V1 and V2)
+
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
 
-/// x402 Protocol V1 Types
+/// V1: client -> server payment payload
+pub const X_PAYMENT_HEADER: &str = "X-PAYMENT";
+/// V2: client -> server payment payload
+pub const PAYMENT_SIGNATURE_HEADER: &str = "PAYMENT-SIGNATURE";
+/// V2: server -> client payment requirements (base64 JSON)
+pub const PAYMENT_REQUIRED_HEADER: &str = "PAYMENT-REQUIRED";
+/// V1: server -> client payment receipt (base64 JSON)
+pub const X_PAYMENT_RESPONSE_HEADER: &str = "X-PAYMENT-RESPONSE";
+/// V2: server -> client payment receipt (base64 JSON)
+pub const PAYMENT_RESPONSE_HEADER: &str = "PAYMENT-RESPONSE";
 
-/// 402 Payment Required response body
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Supported x402 protocol versions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum X402Version {
+    V1,
+    V2,
+}
+
+impl X402Version {
+    pub fn from_u32(version: u32) -> Option<Self> {
+        match version {
+            1 => Some(X402Version::V1),
+            2 => Some(X402Version::V2),
+            _ => None,
+        }
+    }
+
+    pub fn as_u32(&self) -> u32 {
+        match self {
+            X402Version::V1 => 1,
+            X402Version::V2 => 2,
+        }
+    }
+
+    /// Header the client uses to send the payment payload
+    pub fn payment_header(&self) -> &'static str {
+        match self {
+            X402Version::V1 => X_PAYMENT_HEADER,
+            X402Version::V2 => PAYMENT_SIGNATURE_HEADER,
+        }
+    }
+
+    /// Header the server uses to return the payment receipt
+    pub fn payment_response_header(&self) -> &'static str {
+        match self {
+            X402Version::V1 => X_PAYMENT_RESPONSE_HEADER,
+            X402Version::V2 => PAYMENT_RESPONSE_HEADER,
+        }
+    }
+}
+
+/// Payment schemes the server can advertise
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaymentScheme {
+    /// EIP-2612 permit - the facilitator calls permit() then transferFrom()
+    Permit,
+    /// EIP-3009 transferWithAuthorization (USDC and similar tokens)
+    Exact,
+}
+
+impl PaymentScheme {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PaymentScheme::Permit => "permit",
+            PaymentScheme::Exact => "exact",
+        }
+    }
+}
+
+impl std::str::FromStr for PaymentScheme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "permit" => Ok(PaymentScheme::Permit),
+            "exact" => Ok(PaymentScheme::Exact),
+            other => Err(format!("Unknown payment scheme: {}", other)),
+        }
+    }
+}
+
+/// V1 402 Payment Required response body
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct PaymentRequiredResponse {
     pub x402_version: u32,
@@ -11,8 +94,8 @@ pub struct PaymentRequiredResponse {
     pub error: Option<String>,
 }
 
-/// Payment requirements (what the seller accepts)
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// V1 Payment requirements (what the seller accepts)
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct PaymentRequirements {
     pub scheme: String,
@@ -28,19 +111,118 @@ pub struct PaymentRequirements {
     pub extra: Option<serde_json::Value>,
 }
 
-/// V1 Verify/Settle request - contains both payload and requirements
+/// V2 description of the resource being paid for
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceInfo {
+    pub url: String,
+    pub description: String,
+    pub mime_type: String,
+}
+
+/// V2 Payment requirements - resource info moves to the envelope,
+/// networks are CAIP-2 identifiers and the amount is exact
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymentRequirementsV2 {
+    pub scheme: String,
+    pub network: String,
+    pub amount: String,
+    pub asset: String,
+    pub pay_to: String,
+    pub max_timeout_seconds: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extra: Option<serde_json::Value>,
+}
+
+impl From<&PaymentRequirements> for PaymentRequirementsV2 {
+    fn from(req: &PaymentRequirements) -> Self {
+        Self {
+            scheme: req.scheme.clone(),
+            network: crate::domain_types::network::to_caip2(&req.network),
+            amount: req.max_amount_required.clone(),
+            asset: req.asset.clone(),
+            pay_to: req.pay_to.clone(),
+            max_timeout_seconds: req.max_timeout_seconds,
+            extra: req.extra.clone(),
+        }
+    }
+}
+
+/// V2 402 Payment Required envelope (sent base64-encoded in PAYMENT-REQUIRED)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymentRequiredResponseV2 {
+    pub x402_version: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub resource: ResourceInfo,
+    pub accepts: Vec<PaymentRequirementsV2>,
+}
+
+/// Requirements in the shape matching the negotiated protocol version
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum VersionedPaymentRequirements {
+    V1(PaymentRequirements),
+    V2(PaymentRequirementsV2),
+}
+
+impl VersionedPaymentRequirements {
+    pub fn scheme(&self) -> &str {
+        match self {
+            VersionedPaymentRequirements::V1(req) => &req.scheme,
+            VersionedPaymentRequirements::V2(req) => &req.scheme,
+        }
+    }
+
+    pub fn network(&self) -> &str {
+        match self {
+            VersionedPaymentRequirements::V1(req) => &req.network,
+            VersionedPaymentRequirements::V2(req) => &req.network,
+        }
+    }
+
+    pub fn asset(&self) -> &str {
+        match self {
+            VersionedPaymentRequirements::V1(req) => &req.asset,
+            VersionedPaymentRequirements::V2(req) => &req.asset,
+        }
+    }
+
+    pub fn pay_to(&self) -> &str {
+        match self {
+            VersionedPaymentRequirements::V1(req) => &req.pay_to,
+            VersionedPaymentRequirements::V2(req) => &req.pay_to,
+        }
+    }
+
+    /// Required amount in the asset's raw units
+    pub fn amount(&self) -> &str {
+        match self {
+            VersionedPaymentRequirements::V1(req) => &req.max_amount_required,
+            VersionedPaymentRequirements::V2(req) => &req.amount,
+        }
+    }
+
+    pub fn extra(&self) -> Option<&serde_json::Value> {
+        match self {
+            VersionedPaymentRequirements::V1(req) => req.extra.as_ref(),
+            VersionedPaymentRequirements::V2(req) => req.extra.as_ref(),
+        }
+    }
+}
+
+/// Verify/Settle request - contains both payload and requirements
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct VerifyRequest {
     pub x402_version: u32,
     pub payment_payload: serde_json::Value,
-    pub payment_requirements: PaymentRequirements,
+    pub payment_requirements: VersionedPaymentRequirements,
 }
 
-/// Alias for SettleRequest - same structure as VerifyRequest in V1
-pub type SettleRequest = VerifyRequest;
-
-/// V1 Verify response
+/// Verify response (same shape in V1 and V2)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct VerifyResponse {
@@ -51,11 +233,12 @@ pub struct VerifyResponse {
     pub payer: Option<String>,
 }
 
-/// V1 Settle response
+/// Settle response (same shape in V1 and V2)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SettleResponse {
     pub success: bool,
+    #[serde(default)]
     pub network: String,
     #[serde(default)]
     pub transaction: Option<String>,
@@ -64,3 +247,25 @@ pub struct SettleResponse {
     #[serde(default)]
     pub payer: Option<String>,
 }
+
+/// Receipt returned to the payer on paid endpoints (base64 JSON in
+/// X-PAYMENT-RESPONSE / PAYMENT-RESPONSE)
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymentReceipt {
+    pub success: bool,
+    /// "settled" when settled during the request, "queued" when deferred
+    pub status: String,
+    pub network: String,
+    pub payer: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transaction: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub settlement_id: Option<Uuid>,
+    /// Where to poll a queued settlement
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub settlement_url: Option<String>,
+    /// Payment record, see GET /api/payments/{id}
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payment_id: Option<Uuid>,
+}
@@ -0,0 +1,81 @@
+use rand::Rng;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::Purchase;
+use crate::services::LedgerService;
+
+/// Access grants to premium threads
+pub struct AccessService;
+
+impl AccessService {
+    fn hash_token(token: &str) -> String {
+        hex::encode(Sha256::digest(token.as_bytes()))
+    }
+
+    /// Whether a reader already paid for a thread
+    ///
+    /// A grant matches the authenticated agent, the agent's bound wallet, or
+    /// an access token handed out with an earlier grant.
+    pub async fn has_access(
+        pool: &PgPool,
+        thread_id: Uuid,
+        agent_id: Option<Uuid>,
+        wallet: Option<&str>,
+        token: Option<&str>,
+    ) -> Result<bool, sqlx::Error> {
+        if agent_id.is_none() && wallet.is_none() && token.is_none() {
+            return Ok(false);
+        }
+
+        let (exists,): (bool,) = sqlx::query_as(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM access_grants
+                WHERE thread_id = $1
+                  AND (agent_id = $2 OR payer = $3 OR token_hash = $4)
+            )
+            "#,
+        )
+        .bind(thread_id)
+        .bind(agent_id)
+        .bind(wallet.map(|w| w.to_lowercase()))
+        .bind(token.map(Self::hash_token))
+        .fetch_one(pool)
+        .await?;
+        Ok(exists)
+    }
+
+    /// Record a paid read and return the access token for re-reading
+    pub async fn grant(
+        pool: &PgPool,
+        thread_id: Uuid,
+        agent_id: Option<Uuid>,
+        payer: Option<&str>,
+        purchase: Purchase,
+    ) -> Result<String, sqlx::Error> {
+        let random_bytes: [u8; 32] = rand::thread_rng().gen();
+        let token = format!("x402r_{}", hex::encode(random_bytes));
+        let mut tx = pool.begin().await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO access_grants (thread_id, agent_id, payer, token_hash, payment_id)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+        )
+        .bind(thread_id)
+        .bind(agent_id)
+        .bind(payer.map(|p| p.to_lowercase()))
+        .bind(Self::hash_token(&token))
+        .bind(purchase.payment_id)
+        .execute(&mut *tx)
+        .await?;
+
+        LedgerService::post_purchase(&mut tx, &purchase).await?;
+
+        tx.commit().await?;
+        Ok(token)
+    }
+}
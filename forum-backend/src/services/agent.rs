@@ -1,10 +1,31 @@
-use primitive_types::U256;
 use rand::Rng;
 use sha2::{Digest, Sha256};
 use sqlx::PgPool;
 use uuid::Uuid;
 
-use crate::models::{Agent, AgentWithPostCount};
+use crate::models::{Agent, AgentWithPostCount, Purchase};
+use crate::services::LedgerService;
+
+/// Agents with their public post count, what those posts cost and the tips
+/// they received, each summed in one grouped pass
+const AGENT_WITH_TOTALS: &str = r#"
+    SELECT a.id, a.name, a.description, a.created_at, a.x_username,
+           COALESCE(t.post_count, 0) AS post_count,
+           COALESCE(t.total_paid, 0) AS total_paid,
+           COALESCE(tp.tip_total, 0) AS tip_total
+    FROM agents a
+    LEFT JOIN (
+        SELECT agent_id, COUNT(*) AS post_count, SUM(cost) AS total_paid
+        FROM threads
+        WHERE anon = false AND hidden = false
+        GROUP BY agent_id
+    ) t ON t.agent_id = a.id
+    LEFT JOIN (
+        SELECT recipient_agent_id, SUM(amount) AS tip_total
+        FROM tips
+        GROUP BY recipient_agent_id
+    ) tp ON tp.recipient_agent_id = a.id
+"#;
 
 pub struct AgentService;
 
@@ -12,29 +33,103 @@ impl AgentService {
     pub fn generate_api_key() -> String {
         let random_bytes: [u8; 32] = rand::thread_rng().gen();
         let mut hasher = Sha256::new();
-        hasher.update(&random_bytes);
+        hasher.update(random_bytes);
         hasher.update(Uuid::new_v4().as_bytes());
         let result = hasher.finalize();
         format!("x402b_{}", hex::encode(&result[..24]))
     }
 
-    /// Create a new agent with just username and api_key
-    pub async fn create(pool: &PgPool, username: &str, api_key: &str) -> Result<Uuid, sqlx::Error> {
+    /// Create an agent bound to the wallet that paid for it
+    ///
+    /// Returns None instead if the username is taken or, with
+    /// `unique_wallet`, the wallet already has an agent. Both are locked
+    /// first, so concurrent registrations can't both pass the check. The
+    /// purchase is posted for the new agent in the same transaction.
+    pub async fn create(
+        pool: &PgPool,
+        username: &str,
+        api_key: &str,
+        purchase: Purchase,
+        wallet_address: Option<&str>,
+        unique_wallet: bool,
+    ) -> Result<Option<Uuid>, sqlx::Error> {
         let id = Uuid::new_v4();
+        let wallet_address = wallet_address.map(|w| w.to_lowercase());
+
+        let mut tx = pool.begin().await?;
 
-        sqlx::query(
+        sqlx::query("SELECT pg_advisory_xact_lock(hashtext('agent_name:' || $1))")
+            .bind(username)
+            .execute(&mut *tx)
+            .await?;
+        if let (true, Some(wallet)) = (unique_wallet, &wallet_address) {
+            sqlx::query("SELECT pg_advisory_xact_lock(hashtext('agent_wallet:' || $1))")
+                .bind(wallet)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        let inserted = sqlx::query(
             r#"
-            INSERT INTO agents (id, api_key, name)
-            VALUES ($1, $2, $3)
+            INSERT INTO agents (id, api_key, name, payment_id, wallet_address)
+            SELECT $1, $2, $3, $4, $5
+            WHERE NOT EXISTS (SELECT 1 FROM agents WHERE name = $3)
+              AND (NOT $6 OR $5 IS NULL OR NOT EXISTS (SELECT 1 FROM agents WHERE wallet_address = $5))
             "#,
         )
         .bind(id)
         .bind(api_key)
         .bind(username)
-        .execute(pool)
-        .await?;
+        .bind(purchase.payment_id)
+        .bind(&wallet_address)
+        .bind(unique_wallet)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        if inserted == 0 {
+            return Ok(None);
+        }
+
+        let purchase = Purchase { entry: purchase.entry.agent(id), ..purchase };
+        LedgerService::post_purchase(&mut tx, &purchase).await?;
+
+        tx.commit().await?;
+        Ok(Some(id))
+    }
+
+    pub async fn get_by_wallet(pool: &PgPool, wallet_address: &str) -> Result<Option<Agent>, sqlx::Error> {
+        sqlx::query_as::<_, Agent>("SELECT * FROM agents WHERE wallet_address = $1 LIMIT 1")
+            .bind(wallet_address.to_lowercase())
+            .fetch_optional(pool)
+            .await
+    }
+
+    /// Bind a wallet to an agent registered before wallets were recorded
+    pub async fn bind_wallet(pool: &PgPool, agent_id: Uuid, wallet_address: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE agents SET wallet_address = $1 WHERE id = $2 AND wallet_address IS NULL")
+            .bind(wallet_address.to_lowercase())
+            .bind(agent_id)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
 
-        Ok(id)
+    /// Replace the API key of an agent bound to `wallet_address`
+    ///
+    /// Returns false if no such agent exists.
+    pub async fn reissue_api_key(
+        pool: &PgPool,
+        username: &str,
+        wallet_address: &str,
+        api_key: &str,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("UPDATE agents SET api_key = $3 WHERE name = $1 AND wallet_address = $2")
+            .bind(username)
+            .bind(wallet_address.to_lowercase())
+            .bind(api_key)
+            .execute(pool)
+            .await?;
+        Ok(result.rows_affected() == 1)
     }
 
     pub async fn get_by_api_key(pool: &PgPool, api_key: &str) -> Result<Option<Agent>, sqlx::Error> {
@@ -58,15 +153,6 @@ impl AgentService {
             .await
     }
 
-    pub async fn claim(pool: &PgPool, agent_id: Uuid, x_username: &str) -> Result<(), sqlx::Error> {
-        sqlx::query("UPDATE agents SET claimed = true, x_username = $1 WHERE id = $2")
-            .bind(x_username)
-            .bind(agent_id)
-            .execute(pool)
-            .await?;
-        Ok(())
-    }
-
     /// Count all agents
     pub async fn count(pool: &PgPool) -> Result<i64, sqlx::Error> {
         let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM agents")
@@ -75,67 +161,23 @@ impl AgentService {
         Ok(count)
     }
 
-    /// Sum cost strings using U256 arithmetic
-    fn sum_costs(costs: &[Option<String>]) -> String {
-        let mut total = U256::zero();
-        for cost in costs {
-            if let Some(c) = cost {
-                if let Ok(val) = U256::from_dec_str(c) {
-                    total = total.saturating_add(val);
-                }
-            }
-        }
-        total.to_string()
-    }
-
     /// List all agents with their post counts
     pub async fn list_with_post_count(
         pool: &PgPool,
         limit: i64,
         offset: i64,
     ) -> Result<Vec<AgentWithPostCount>, sqlx::Error> {
-        // Get agents with post count
-        let rows: Vec<(Uuid, String, Option<String>, chrono::DateTime<chrono::Utc>, Option<String>, i64)> =
-            sqlx::query_as(
-                r#"
-                SELECT a.id, a.name, a.description, a.created_at, a.x_username,
-                       COALESCE(COUNT(t.id) FILTER (WHERE t.anon = false), 0) as post_count
-                FROM agents a
-                LEFT JOIN threads t ON t.agent_id = a.id
-                GROUP BY a.id
-                ORDER BY post_count DESC, a.created_at DESC
-                LIMIT $1 OFFSET $2
-                "#,
-            )
-            .bind(limit.min(100))
-            .bind(offset)
-            .fetch_all(pool)
-            .await?;
-
-        let mut results = Vec::with_capacity(rows.len());
-        for (id, name, description, created_at, x_username, post_count) in rows {
-            // Get costs for this agent's non-anon threads
-            let costs: Vec<(Option<String>,)> = sqlx::query_as(
-                "SELECT cost FROM threads WHERE agent_id = $1 AND anon = false"
-            )
-            .bind(id)
-            .fetch_all(pool)
-            .await?;
-
-            let total_paid = Self::sum_costs(&costs.into_iter().map(|(c,)| c).collect::<Vec<_>>());
-
-            results.push(AgentWithPostCount {
-                id,
-                name,
-                description,
-                created_at,
-                x_username,
-                post_count,
-                total_paid,
-            });
-        }
-
-        Ok(results)
+        sqlx::query_as(&format!(
+            r#"
+            {AGENT_WITH_TOTALS}
+            ORDER BY post_count DESC, a.created_at DESC
+            LIMIT $1 OFFSET $2
+            "#
+        ))
+        .bind(limit.min(100))
+        .bind(offset)
+        .fetch_all(pool)
+        .await
     }
 
     /// Get trending agents (top by post count, excludes agents with 0 posts)
@@ -143,47 +185,17 @@ impl AgentService {
         pool: &PgPool,
         limit: i64,
     ) -> Result<Vec<AgentWithPostCount>, sqlx::Error> {
-        let rows: Vec<(Uuid, String, Option<String>, chrono::DateTime<chrono::Utc>, Option<String>, i64)> =
-            sqlx::query_as(
-                r#"
-                SELECT a.id, a.name, a.description, a.created_at, a.x_username,
-                       COALESCE(COUNT(t.id) FILTER (WHERE t.anon = false), 0) as post_count
-                FROM agents a
-                LEFT JOIN threads t ON t.agent_id = a.id
-                GROUP BY a.id
-                HAVING COUNT(t.id) FILTER (WHERE t.anon = false) > 0
-                ORDER BY post_count DESC, a.created_at DESC
-                LIMIT $1
-                "#,
-            )
-            .bind(limit.min(100))
-            .fetch_all(pool)
-            .await?;
-
-        let mut results = Vec::with_capacity(rows.len());
-        for (id, name, description, created_at, x_username, post_count) in rows {
-            // Get costs for this agent's non-anon threads
-            let costs: Vec<(Option<String>,)> = sqlx::query_as(
-                "SELECT cost FROM threads WHERE agent_id = $1 AND anon = false"
-            )
-            .bind(id)
-            .fetch_all(pool)
-            .await?;
-
-            let total_paid = Self::sum_costs(&costs.into_iter().map(|(c,)| c).collect::<Vec<_>>());
-
-            results.push(AgentWithPostCount {
-                id,
-                name,
-                description,
-                created_at,
-                x_username,
-                post_count,
-                total_paid,
-            });
-        }
-
-        Ok(results)
+        sqlx::query_as(&format!(
+            r#"
+            {AGENT_WITH_TOTALS}
+            WHERE t.post_count > 0
+            ORDER BY post_count DESC, a.created_at DESC
+            LIMIT $1
+            "#
+        ))
+        .bind(limit.min(100))
+        .fetch_all(pool)
+        .await
     }
 
     /// Get agent by ID with post count
@@ -191,45 +203,10 @@ impl AgentService {
         pool: &PgPool,
         id: Uuid,
     ) -> Result<Option<AgentWithPostCount>, sqlx::Error> {
-        let row: Option<(Uuid, String, Option<String>, chrono::DateTime<chrono::Utc>, Option<String>, i64)> =
-            sqlx::query_as(
-                r#"
-                SELECT a.id, a.name, a.description, a.created_at, a.x_username,
-                       COALESCE(COUNT(t.id) FILTER (WHERE t.anon = false), 0) as post_count
-                FROM agents a
-                LEFT JOIN threads t ON t.agent_id = a.id
-                WHERE a.id = $1
-                GROUP BY a.id
-                "#,
-            )
+        sqlx::query_as(&format!("{AGENT_WITH_TOTALS} WHERE a.id = $1"))
             .bind(id)
             .fetch_optional(pool)
-            .await?;
-
-        match row {
-            Some((id, name, description, created_at, x_username, post_count)) => {
-                // Get costs for this agent's non-anon threads
-                let costs: Vec<(Option<String>,)> = sqlx::query_as(
-                    "SELECT cost FROM threads WHERE agent_id = $1 AND anon = false"
-                )
-                .bind(id)
-                .fetch_all(pool)
-                .await?;
-
-                let total_paid = Self::sum_costs(&costs.into_iter().map(|(c,)| c).collect::<Vec<_>>());
-
-                Ok(Some(AgentWithPostCount {
-                    id,
-                    name,
-                    description,
-                    created_at,
-                    x_username,
-                    post_count,
-                    total_paid,
-                }))
-            }
-            None => Ok(None),
-        }
+            .await
     }
 
     /// Search agents by name or description
@@ -240,47 +217,17 @@ impl AgentService {
     ) -> Result<Vec<AgentWithPostCount>, sqlx::Error> {
         let search_pattern = format!("%{}%", query);
 
-        let rows: Vec<(Uuid, String, Option<String>, chrono::DateTime<chrono::Utc>, Option<String>, i64)> =
-            sqlx::query_as(
-                r#"
-                SELECT a.id, a.name, a.description, a.created_at, a.x_username,
-                       COALESCE(COUNT(t.id) FILTER (WHERE t.anon = false), 0) as post_count
-                FROM agents a
-                LEFT JOIN threads t ON t.agent_id = a.id
-                WHERE a.name ILIKE $1 OR a.description ILIKE $1
-                GROUP BY a.id
-                ORDER BY post_count DESC, a.created_at DESC
-                LIMIT $2
-                "#,
-            )
-            .bind(&search_pattern)
-            .bind(limit.min(20))
-            .fetch_all(pool)
-            .await?;
-
-        let mut results = Vec::with_capacity(rows.len());
-        for (id, name, description, created_at, x_username, post_count) in rows {
-            // Get costs for this agent's non-anon threads
-            let costs: Vec<(Option<String>,)> = sqlx::query_as(
-                "SELECT cost FROM threads WHERE agent_id = $1 AND anon = false"
-            )
-            .bind(id)
-            .fetch_all(pool)
-            .await?;
-
-            let total_paid = Self::sum_costs(&costs.into_iter().map(|(c,)| c).collect::<Vec<_>>());
-
-            results.push(AgentWithPostCount {
-                id,
-                name,
-                description,
-                created_at,
-                x_username,
-                post_count,
-                total_paid,
-            });
-        }
-
-        Ok(results)
+        sqlx::query_as(&format!(
+            r#"
+            {AGENT_WITH_TOTALS}
+            WHERE a.name ILIKE $1 OR a.description ILIKE $1
+            ORDER BY post_count DESC, a.created_at DESC
+            LIMIT $2
+            "#
+        ))
+        .bind(&search_pattern)
+        .bind(limit.min(20))
+        .fetch_all(pool)
+        .await
     }
 }
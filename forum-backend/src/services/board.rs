@@ -15,7 +15,7 @@ impl BoardService {
         let mut result = Vec::with_capacity(boards.len());
         for board in boards {
             let thread_count: (i64,) = sqlx::query_as(
-                "SELECT COUNT(*) FROM threads WHERE board_id = $1"
+                "SELECT COUNT(*) FROM threads WHERE board_id = $1 AND hidden = false"
             )
             .bind(board.id)
             .fetch_one(pool)
@@ -42,6 +42,15 @@ impl BoardService {
         .await
     }
 
+    pub async fn get_by_id(pool: &PgPool, id: i32) -> Result<Option<Board>, sqlx::Error> {
+        sqlx::query_as::<_, Board>(
+            "SELECT * FROM boards WHERE id = $1"
+        )
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+    }
+
     pub async fn get_by_slug_with_stats(
         pool: &PgPool,
         slug: &str,
@@ -50,7 +59,7 @@ impl BoardService {
         match board {
             Some(board) => {
                 let thread_count: (i64,) = sqlx::query_as(
-                    "SELECT COUNT(*) FROM threads WHERE board_id = $1"
+                    "SELECT COUNT(*) FROM threads WHERE board_id = $1 AND hidden = false"
                 )
                 .bind(board.id)
                 .fetch_one(pool)
@@ -0,0 +1,301 @@
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::models::{Account, AccountKind, Bounty, BountyAward, BountyPayout, EntryKind, NewBounty, NewJournalEntry};
+use crate::services::payout::PayoutOutcome;
+use crate::services::{CreditService, LedgerService};
+
+const BOUNTY_COLUMNS: &str = "id, thread_id, creator_agent_id, amount, payment_id, status, \
+     awarded_reply_id, expires_at, created_at, resolved_at";
+
+const PAYOUT_COLUMNS: &str = "id, bounty_id, kind, recipient_agent_id, pay_to, amount, status, \
+     attempts, tx_hash, last_error, created_at, updated_at";
+
+pub struct BountyService;
+
+impl BountyService {
+    /// Open a bounty inside the caller's transaction, which posts its escrow
+    pub async fn create(
+        conn: &mut PgConnection,
+        thread_id: Uuid,
+        creator_agent_id: Uuid,
+        bounty: &NewBounty,
+        payment_id: Option<Uuid>,
+    ) -> Result<Bounty, sqlx::Error> {
+        sqlx::query_as::<_, Bounty>(&format!(
+            r#"
+            INSERT INTO bounties (thread_id, creator_agent_id, amount, payment_id, expires_at)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING {}
+            "#,
+            BOUNTY_COLUMNS
+        ))
+        .bind(thread_id)
+        .bind(creator_agent_id)
+        .bind(bounty.amount)
+        .bind(payment_id)
+        .bind(bounty.expires_at)
+        .fetch_one(conn)
+        .await
+    }
+
+    pub async fn get_by_thread(pool: &PgPool, thread_id: Uuid) -> Result<Option<Bounty>, sqlx::Error> {
+        sqlx::query_as::<_, Bounty>(&format!(
+            "SELECT {} FROM bounties WHERE thread_id = $1",
+            BOUNTY_COLUMNS
+        ))
+        .bind(thread_id)
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// The bounty a payment funded, if any
+    pub async fn get_by_payment(pool: &PgPool, payment_id: Uuid) -> Result<Option<Bounty>, sqlx::Error> {
+        sqlx::query_as::<_, Bounty>(&format!(
+            "SELECT {} FROM bounties WHERE payment_id = $1",
+            BOUNTY_COLUMNS
+        ))
+        .bind(payment_id)
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Open bounties on visible threads, soonest to expire first
+    pub async fn list_open(pool: &PgPool, limit: i64) -> Result<Vec<Bounty>, sqlx::Error> {
+        sqlx::query_as::<_, Bounty>(&format!(
+            r#"
+            SELECT {} FROM bounties
+            WHERE status = 'open' AND expires_at > NOW()
+              AND thread_id IN (SELECT id FROM threads WHERE hidden = false)
+            ORDER BY expires_at
+            LIMIT $1
+            "#,
+            BOUNTY_COLUMNS
+        ))
+        .bind(limit.min(100))
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Whether the money for a bounty has reached the platform wallet
+    ///
+    /// Bounties paid from credit are backed by settled top-ups.
+    pub async fn is_funded(pool: &PgPool, bounty: &Bounty) -> Result<bool, sqlx::Error> {
+        let Some(payment_id) = bounty.payment_id else {
+            return Ok(true);
+        };
+        let status: Option<(String,)> = sqlx::query_as("SELECT status FROM payments WHERE id = $1")
+            .bind(payment_id)
+            .fetch_optional(pool)
+            .await?;
+        Ok(status.is_some_and(|(status,)| status == "settled"))
+    }
+
+    /// Award an open bounty to a reply and record the payout to its author
+    ///
+    /// Returns None if the bounty was no longer open.
+    pub async fn award(
+        pool: &PgPool,
+        bounty_id: Uuid,
+        reply_id: Uuid,
+        recipient_agent_id: Uuid,
+        pay_to: Option<&str>,
+    ) -> Result<Option<BountyAward>, sqlx::Error> {
+        let mut tx = pool.begin().await?;
+
+        let bounty = sqlx::query_as::<_, Bounty>(&format!(
+            r#"
+            UPDATE bounties
+            SET status = 'awarded', awarded_reply_id = $2, resolved_at = NOW()
+            WHERE id = $1 AND status = 'open' AND expires_at > NOW()
+            RETURNING {}
+            "#,
+            BOUNTY_COLUMNS
+        ))
+        .bind(bounty_id)
+        .bind(reply_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(bounty) = bounty else {
+            return Ok(None);
+        };
+
+        let payout = sqlx::query_as::<_, BountyPayout>(&format!(
+            r#"
+            INSERT INTO bounty_payouts (bounty_id, kind, recipient_agent_id, pay_to, amount)
+            VALUES ($1, 'award', $2, $3, $4)
+            RETURNING {}
+            "#,
+            PAYOUT_COLUMNS
+        ))
+        .bind(bounty.id)
+        .bind(recipient_agent_id)
+        .bind(pay_to.map(|w| w.to_lowercase()))
+        .bind(bounty.amount)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        LedgerService::post(&mut tx, &escrow_release(EntryKind::BountyAward, &payout)).await?;
+
+        tx.commit().await?;
+        Ok(Some(BountyAward { bounty, payout }))
+    }
+
+    /// Expire paid bounties past their deadline and record refunds to their creators
+    ///
+    /// Bounties whose payment is still settling wait; a failed payment
+    /// cancels the bounty instead.
+    pub async fn expire_due(pool: &PgPool) -> Result<Vec<BountyPayout>, sqlx::Error> {
+        let mut tx = pool.begin().await?;
+
+        let payouts = sqlx::query_as::<_, BountyPayout>(&format!(
+            r#"
+            WITH expired AS (
+                UPDATE bounties
+                SET status = 'expired', resolved_at = NOW()
+                WHERE status = 'open' AND expires_at <= NOW()
+                  AND payment_id IN (SELECT id FROM payments WHERE status = 'settled')
+                RETURNING id, creator_agent_id, amount
+            )
+            INSERT INTO bounty_payouts (bounty_id, kind, recipient_agent_id, pay_to, amount)
+            SELECT e.id, 'refund', e.creator_agent_id, a.wallet_address, e.amount
+            FROM expired e JOIN agents a ON a.id = e.creator_agent_id
+            RETURNING {}
+            "#,
+            PAYOUT_COLUMNS
+        ))
+        .fetch_all(&mut *tx)
+        .await?;
+
+        for payout in &payouts {
+            LedgerService::post(&mut tx, &escrow_release(EntryKind::BountyExpiry, payout)).await?;
+        }
+
+        tx.commit().await?;
+        Ok(payouts)
+    }
+
+    /// Expire bounties paid from credit past their deadline, returning them
+    /// to their creators' credit balance
+    pub async fn expire_credit_funded(pool: &PgPool) -> Result<Vec<Bounty>, sqlx::Error> {
+        let mut tx = pool.begin().await?;
+
+        let bounties = sqlx::query_as::<_, Bounty>(&format!(
+            r#"
+            UPDATE bounties
+            SET status = 'expired', resolved_at = NOW()
+            WHERE status = 'open' AND expires_at <= NOW() AND payment_id IS NULL
+            RETURNING {}
+            "#,
+            BOUNTY_COLUMNS
+        ))
+        .fetch_all(&mut *tx)
+        .await?;
+
+        for bounty in &bounties {
+            CreditService::add(&mut tx, bounty.creator_agent_id, "bounty_refund", bounty.amount, None).await?;
+
+            let entry = NewJournalEntry::new(EntryKind::BountyExpiry, Some(bounty.creator_agent_id))
+                .reference(bounty.id)
+                .debit(Account::platform(AccountKind::BountyEscrow), bounty.amount.0)
+                .credit(Account::agent(AccountKind::AgentCredit, bounty.creator_agent_id), bounty.amount.0);
+            LedgerService::post(&mut tx, &entry).await?;
+        }
+
+        tx.commit().await?;
+        Ok(bounties)
+    }
+
+    /// Pending payouts with a wallet to send to, oldest first
+    ///
+    /// Payouts recorded before the recipient had a wallet pick up the wallet
+    /// it has bound since.
+    pub async fn pending_payouts(pool: &PgPool, limit: i64) -> Result<Vec<BountyPayout>, sqlx::Error> {
+        sqlx::query(
+            r#"
+            UPDATE bounty_payouts p SET pay_to = a.wallet_address, updated_at = NOW()
+            FROM agents a
+            WHERE p.status = 'pending' AND p.pay_to IS NULL
+              AND a.id = p.recipient_agent_id AND a.wallet_address IS NOT NULL
+            "#,
+        )
+        .execute(pool)
+        .await?;
+
+        sqlx::query_as::<_, BountyPayout>(&format!(
+            r#"
+            SELECT {} FROM bounty_payouts
+            WHERE status = 'pending' AND pay_to IS NOT NULL
+            ORDER BY created_at
+            LIMIT $1
+            "#,
+            PAYOUT_COLUMNS
+        ))
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Record the payout executor's outcome; a sent payout leaves the platform wallet
+    pub async fn mark_payout_done(pool: &PgPool, id: Uuid, outcome: &PayoutOutcome) -> Result<(), sqlx::Error> {
+        let mut tx = pool.begin().await?;
+
+        let payout = sqlx::query_as::<_, BountyPayout>(&format!(
+            r#"
+            UPDATE bounty_payouts SET status = $1, tx_hash = $2, attempts = attempts + 1, updated_at = NOW()
+            WHERE id = $3
+            RETURNING {}
+            "#,
+            PAYOUT_COLUMNS
+        ))
+        .bind(outcome.status())
+        .bind(outcome.transaction())
+        .bind(id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        if let PayoutOutcome::Sent(_) = outcome {
+            let entry = NewJournalEntry::new(EntryKind::BountyPayoutSent, Some(payout.recipient_agent_id))
+                .reference(payout.id)
+                .debit(Account::agent(AccountKind::Payouts, payout.recipient_agent_id), payout.amount.0)
+                .credit(Account::platform(AccountKind::PlatformWallet), payout.amount.0);
+            LedgerService::post(&mut tx, &entry).await?;
+        }
+
+        tx.commit().await
+    }
+
+    /// Record a failed transfer; the payout is marked failed after `max_attempts`
+    pub async fn record_payout_failure(
+        pool: &PgPool,
+        id: Uuid,
+        error: &str,
+        max_attempts: i32,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            UPDATE bounty_payouts
+            SET attempts = attempts + 1,
+                last_error = $1,
+                status = CASE WHEN attempts + 1 >= $3 THEN 'failed' ELSE status END,
+                updated_at = NOW()
+            WHERE id = $2
+            "#,
+        )
+        .bind(error)
+        .bind(id)
+        .bind(max_attempts)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+}
+
+/// Move an awarded or expired bounty from escrow to the recipient's payouts
+fn escrow_release(kind: EntryKind, payout: &BountyPayout) -> NewJournalEntry {
+    NewJournalEntry::new(kind, Some(payout.recipient_agent_id))
+        .reference(payout.id)
+        .debit(Account::platform(AccountKind::BountyEscrow), payout.amount.0)
+        .credit(Account::agent(AccountKind::Payouts, payout.recipient_agent_id), payout.amount.0)
+}
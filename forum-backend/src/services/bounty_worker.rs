@@ -0,0 +1,115 @@
+//! Background worker that expires bounties and sends their payouts
+
+use super::bounty::BountyService;
+use super::payout::{PayoutExecutor, PayoutRequest};
+use crate::config::Config;
+use sqlx::PgPool;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tracing::{error, info, warn};
+
+/// How often bounties are checked for expiry and payouts are sent
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Transfer attempts before a payout is marked failed
+const MAX_PAYOUT_ATTEMPTS: i32 = 5;
+
+/// Payouts sent per poll
+const PAYOUT_BATCH: i64 = 20;
+
+pub struct BountyWorker {
+    pool: PgPool,
+    config: Config,
+    /// None leaves payouts pending for an operator
+    executor: Option<Arc<dyn PayoutExecutor>>,
+}
+
+impl BountyWorker {
+    pub fn new(pool: PgPool, config: Config, executor: Option<Arc<dyn PayoutExecutor>>) -> Self {
+        Self {
+            pool,
+            config,
+            executor,
+        }
+    }
+
+    /// Run the worker until shutdown signal
+    pub async fn run(&self, mut shutdown: broadcast::Receiver<()>) {
+        info!("Bounty worker started");
+
+        loop {
+            self.tick().await;
+
+            tokio::select! {
+                biased;
+
+                _ = shutdown.recv() => {
+                    info!("Bounty worker shutting down");
+                    break;
+                }
+
+                _ = tokio::time::sleep(POLL_INTERVAL) => {}
+            }
+        }
+    }
+
+    async fn tick(&self) {
+        match BountyService::expire_due(&self.pool).await {
+            Ok(refunds) => {
+                for refund in refunds {
+                    info!("Bounty {} expired, refund {} recorded", refund.bounty_id, refund.id);
+                }
+            }
+            Err(e) => error!("Failed to expire bounties: {}", e),
+        }
+
+        match BountyService::expire_credit_funded(&self.pool).await {
+            Ok(bounties) => {
+                for bounty in bounties {
+                    info!("Bounty {} expired, returned to the creator's credit", bounty.id);
+                }
+            }
+            Err(e) => error!("Failed to expire credit-funded bounties: {}", e),
+        }
+
+        let Some(executor) = &self.executor else {
+            return;
+        };
+
+        let payouts = match BountyService::pending_payouts(&self.pool, PAYOUT_BATCH).await {
+            Ok(payouts) => payouts,
+            Err(e) => {
+                error!("Failed to load pending payouts: {}", e);
+                return;
+            }
+        };
+
+        // Bounties are held in the main payment token
+        let asset = self.config.canonical_asset();
+        for payout in payouts {
+            let request = PayoutRequest {
+                reference: payout.id,
+                network: asset.network.clone(),
+                asset: asset.address.clone(),
+                pay_to: payout.pay_to.clone().unwrap_or_default(),
+                amount: payout.amount.to_string(),
+            };
+
+            let result = match executor.send(&request).await {
+                Ok(outcome) => {
+                    info!("{} payout {}: {:?}", payout.kind, payout.id, outcome);
+                    BountyService::mark_payout_done(&self.pool, payout.id, &outcome).await
+                }
+                Err(e) => {
+                    warn!("Payout {} failed (attempt {}): {}", payout.id, payout.attempts + 1, e);
+                    BountyService::record_payout_failure(&self.pool, payout.id, &e.to_string(), MAX_PAYOUT_ATTEMPTS)
+                        .await
+                }
+            };
+            if let Err(e) = result {
+                error!("Failed to update payout {}: {}", payout.id, e);
+            }
+        }
+    }
+}
@@ -0,0 +1,361 @@
+use primitive_types::U256;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::domain_types::DomainU256;
+use crate::models::{Account, AccountKind, CreditEntry, CreditSpend, EntryKind, NewJournalEntry, PendingTopUp};
+use crate::services::LedgerService;
+
+const INSUFFICIENT_CREDIT: &str = "insufficient credit";
+
+pub struct CreditService;
+
+impl CreditService {
+    /// Current balance, zero for agents that never topped up
+    pub async fn balance(pool: &PgPool, agent_id: Uuid) -> Result<DomainU256, sqlx::Error> {
+        let balance: Option<(DomainU256,)> =
+            sqlx::query_as("SELECT balance FROM credit_balances WHERE agent_id = $1")
+                .bind(agent_id)
+                .fetch_optional(pool)
+                .await?;
+
+        Ok(balance.map(|(b,)| b).unwrap_or(DomainU256(U256::zero())))
+    }
+
+    /// Record a top-up paid with a queued payment, credited once it settles
+    pub async fn record_top_up(
+        pool: &PgPool,
+        agent_id: Uuid,
+        amount: DomainU256,
+        payment_id: Uuid,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("INSERT INTO credit_top_ups (payment_id, agent_id, amount) VALUES ($1, $2, $3)")
+            .bind(payment_id)
+            .bind(agent_id)
+            .bind(amount)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Credit pending top-ups whose payment settled, and close those whose
+    /// payment failed
+    ///
+    /// Each top-up is credited in its own transaction with its balance and
+    /// journal entries, so one that fails stays pending for the next call.
+    /// Returns how many were credited.
+    pub async fn resolve_top_ups(pool: &PgPool) -> Result<usize, sqlx::Error> {
+        sqlx::query(
+            r#"
+            UPDATE credit_top_ups t SET status = 'failed'
+            FROM payments p
+            WHERE p.id = t.payment_id AND t.status = 'pending' AND p.status = 'failed'
+            "#,
+        )
+        .execute(pool)
+        .await?;
+
+        let mut credited = 0;
+        loop {
+            let mut tx = pool.begin().await?;
+
+            let top_up: Option<(Uuid, Uuid, DomainU256, Uuid)> = sqlx::query_as(
+                r#"
+                SELECT t.id, t.agent_id, t.amount, t.payment_id
+                FROM credit_top_ups t
+                JOIN payments p ON p.id = t.payment_id
+                WHERE t.status = 'pending' AND p.status = 'settled'
+                ORDER BY t.created_at
+                LIMIT 1
+                FOR UPDATE OF t SKIP LOCKED
+                "#,
+            )
+            .fetch_optional(&mut *tx)
+            .await?;
+
+            let Some((id, agent_id, amount, payment_id)) = top_up else {
+                return Ok(credited);
+            };
+
+            Self::add(&mut tx, agent_id, "topup", amount, Some(payment_id)).await?;
+
+            let entry = NewJournalEntry::new(EntryKind::CreditTopUp, Some(agent_id))
+                .payment(Some(payment_id))
+                .debit(Account::platform(AccountKind::PlatformWallet), amount.0)
+                .credit(Account::agent(AccountKind::AgentCredit, agent_id), amount.0);
+            LedgerService::post(&mut tx, &entry).await?;
+
+            sqlx::query("UPDATE credit_top_ups SET status = 'credited', credited_at = NOW() WHERE id = $1")
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+
+            tx.commit().await?;
+            credited += 1;
+        }
+    }
+
+    /// Top-ups still waiting for their payment to settle, newest first
+    pub async fn pending_top_ups(pool: &PgPool, agent_id: Uuid) -> Result<Vec<PendingTopUp>, sqlx::Error> {
+        sqlx::query_as::<_, PendingTopUp>(
+            r#"
+            SELECT id, payment_id, amount, created_at
+            FROM credit_top_ups
+            WHERE agent_id = $1 AND status = 'pending'
+            ORDER BY created_at DESC
+            "#,
+        )
+        .bind(agent_id)
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Add to the balance inside the caller's transaction, returning the new balance
+    ///
+    /// The caller posts the matching journal entry.
+    pub async fn add(
+        conn: &mut PgConnection,
+        agent_id: Uuid,
+        kind: &str,
+        amount: DomainU256,
+        payment_id: Option<Uuid>,
+    ) -> Result<DomainU256, sqlx::Error> {
+        let (balance,): (DomainU256,) = sqlx::query_as(
+            r#"
+            INSERT INTO credit_balances (agent_id, balance)
+            VALUES ($1, $2)
+            ON CONFLICT (agent_id) DO UPDATE
+            SET balance = credit_balances.balance + EXCLUDED.balance, updated_at = NOW()
+            RETURNING balance
+            "#,
+        )
+        .bind(agent_id)
+        .bind(amount)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO credit_entries (agent_id, kind, amount, balance_after, payment_id)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+        )
+        .bind(agent_id)
+        .bind(kind)
+        .bind(amount)
+        .bind(balance)
+        .bind(payment_id)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(balance)
+    }
+
+    /// Debit a spend and post its journal entry inside the caller's transaction
+    ///
+    /// The conditional UPDATE locks the balance row, so parallel spends are
+    /// applied one at a time and can never take the balance below zero. A
+    /// balance that no longer covers the spend fails with an error that
+    /// `is_insufficient` recognizes. Returns the remaining balance.
+    pub async fn spend(
+        conn: &mut PgConnection,
+        spend: &CreditSpend,
+        entry: &NewJournalEntry,
+    ) -> Result<DomainU256, sqlx::Error> {
+        let balance: Option<(DomainU256,)> = sqlx::query_as(
+            r#"
+            UPDATE credit_balances
+            SET balance = balance - $2, updated_at = NOW()
+            WHERE agent_id = $1 AND balance >= $2
+            RETURNING balance
+            "#,
+        )
+        .bind(spend.agent_id)
+        .bind(spend.amount)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        let Some((balance,)) = balance else {
+            return Err(sqlx::Error::InvalidArgument(INSUFFICIENT_CREDIT.to_string()));
+        };
+
+        sqlx::query(
+            r#"
+            INSERT INTO credit_entries (agent_id, kind, amount, balance_after, resource)
+            VALUES ($1, 'spend', -$2, $3, $4)
+            "#,
+        )
+        .bind(spend.agent_id)
+        .bind(spend.amount)
+        .bind(balance)
+        .bind(&spend.resource)
+        .execute(&mut *conn)
+        .await?;
+
+        LedgerService::post(conn, entry).await?;
+        Ok(balance)
+    }
+
+    /// Whether `spend` failed because the balance no longer covers it
+    pub fn is_insufficient(e: &sqlx::Error) -> bool {
+        matches!(e, sqlx::Error::InvalidArgument(msg) if msg == INSUFFICIENT_CREDIT)
+    }
+
+    /// Most recent ledger entries first
+    pub async fn list_entries(
+        pool: &PgPool,
+        agent_id: Uuid,
+        limit: i64,
+    ) -> Result<Vec<CreditEntry>, sqlx::Error> {
+        sqlx::query_as::<_, CreditEntry>(
+            r#"
+            SELECT id, kind, abs(amount) AS amount, balance_after, payment_id, resource, created_at
+            FROM credit_entries
+            WHERE agent_id = $1
+            ORDER BY created_at DESC
+            LIMIT $2
+            "#,
+        )
+        .bind(agent_id)
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn create_agent(pool: &PgPool) -> Uuid {
+        let (id,): (Uuid,) = sqlx::query_as("INSERT INTO agents (api_key, name) VALUES ($1, 'spender') RETURNING id")
+            .bind(Uuid::new_v4().to_string())
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        id
+    }
+
+    async fn spend(pool: PgPool, agent_id: Uuid, amount: u64) -> Result<DomainU256, sqlx::Error> {
+        let spend = CreditSpend {
+            agent_id,
+            amount: DomainU256(U256::from(amount)),
+            resource: "/api/threads/1/replies".to_string(),
+        };
+        let entry = NewJournalEntry::new(EntryKind::Reply, Some(agent_id))
+            .debit(Account::agent(AccountKind::AgentCredit, agent_id), spend.amount.0)
+            .credit(Account::platform(AccountKind::Revenue), spend.amount.0);
+
+        let mut tx = pool.begin().await?;
+        let balance = CreditService::spend(&mut tx, &spend, &entry).await?;
+        tx.commit().await?;
+        Ok(balance)
+    }
+
+    #[sqlx::test(migrations = "../migrations")]
+    async fn test_parallel_spends_never_overdraw(pool: PgPool) {
+        let agent_id = create_agent(&pool).await;
+        let mut tx = pool.begin().await.unwrap();
+        CreditService::add(&mut tx, agent_id, "topup", DomainU256(U256::from(1000)), None).await.unwrap();
+        tx.commit().await.unwrap();
+
+        let handles: Vec<_> = (0..5)
+            .map(|_| tokio::spawn(spend(pool.clone(), agent_id, 300)))
+            .collect();
+        let mut spent = 0;
+        for handle in handles {
+            match handle.await.unwrap() {
+                Ok(_) => spent += 1,
+                Err(e) => assert!(CreditService::is_insufficient(&e), "unexpected error: {}", e),
+            }
+        }
+        assert_eq!(spent, 3);
+        assert_eq!(CreditService::balance(&pool, agent_id).await.unwrap(), DomainU256(U256::from(100)));
+
+        // Every debit that went through is on the ledger, and only those
+        let (entries,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM journal_entries WHERE kind = 'reply'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(entries, 3);
+        let (debited,): (String,) = sqlx::query_as(
+            r#"
+            SELECT SUM(l.amount)::text FROM ledger_lines l
+            JOIN ledger_accounts a ON a.id = l.account_id
+            WHERE a.kind = 'agent_credit' AND a.agent_id = $1
+            "#,
+        )
+        .bind(agent_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(debited, "900");
+        assert_eq!(CreditService::list_entries(&pool, agent_id, 10).await.unwrap().len(), 4);
+    }
+
+    async fn create_payment(pool: &PgPool) -> Uuid {
+        let (id,): (Uuid,) = sqlx::query_as(
+            r#"
+            INSERT INTO payments (payer, asset, nonce, payload_hash, resource, amount)
+            VALUES ('0xpayer', '0xtoken', $1, $1, '/api/credits', 1000)
+            RETURNING id
+            "#,
+        )
+        .bind(Uuid::new_v4().to_string())
+        .fetch_one(pool)
+        .await
+        .unwrap();
+        id
+    }
+
+    async fn set_payment_status(pool: &PgPool, payment_id: Uuid, status: &str) {
+        sqlx::query("UPDATE payments SET status = $1 WHERE id = $2")
+            .bind(status)
+            .bind(payment_id)
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+
+    #[sqlx::test(migrations = "../migrations")]
+    async fn test_top_up_credited_once_its_payment_settles(pool: PgPool) {
+        let agent_id = create_agent(&pool).await;
+        let settled = create_payment(&pool).await;
+        let failed = create_payment(&pool).await;
+        for payment_id in [settled, failed] {
+            CreditService::record_top_up(&pool, agent_id, DomainU256(U256::from(1000)), payment_id).await.unwrap();
+        }
+
+        // Nothing is credited while the payments are pending
+        assert_eq!(CreditService::resolve_top_ups(&pool).await.unwrap(), 0);
+        assert_eq!(CreditService::pending_top_ups(&pool, agent_id).await.unwrap().len(), 2);
+        assert_eq!(CreditService::balance(&pool, agent_id).await.unwrap(), DomainU256(U256::zero()));
+
+        set_payment_status(&pool, settled, "settled").await;
+        set_payment_status(&pool, failed, "failed").await;
+        assert_eq!(CreditService::resolve_top_ups(&pool).await.unwrap(), 1);
+        assert_eq!(CreditService::resolve_top_ups(&pool).await.unwrap(), 0);
+
+        assert!(CreditService::pending_top_ups(&pool, agent_id).await.unwrap().is_empty());
+        assert_eq!(CreditService::balance(&pool, agent_id).await.unwrap(), DomainU256(U256::from(1000)));
+        let entries = CreditService::list_entries(&pool, agent_id, 10).await.unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].payment_id, Some(settled));
+        let (status,): (String,) = sqlx::query_as("SELECT status FROM credit_top_ups WHERE payment_id = $1")
+            .bind(failed)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(status, "failed");
+    }
+
+    #[sqlx::test(migrations = "../migrations")]
+    async fn test_failed_spend_changes_nothing(pool: PgPool) {
+        let agent_id = create_agent(&pool).await;
+
+        let e = spend(pool.clone(), agent_id, 1).await.unwrap_err();
+        assert!(CreditService::is_insufficient(&e));
+        assert_eq!(CreditService::balance(&pool, agent_id).await.unwrap(), DomainU256(U256::zero()));
+        assert!(CreditService::list_entries(&pool, agent_id, 10).await.unwrap().is_empty());
+    }
+}
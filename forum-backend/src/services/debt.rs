@@ -0,0 +1,389 @@
+use primitive_types::U256;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::domain_types::DomainU256;
+use crate::models::{Account, AccountKind, Debt, EntryKind, NewJournalEntry, Purchase};
+use crate::services::LedgerService;
+
+pub struct DebtService;
+
+impl DebtService {
+    /// Apply the configured compensating actions for a failed settlement
+    ///
+    /// Hides the content the payment bought, revokes the reads and bumps it
+    /// bought, cancels the bounty it funded, records a debt for the paying
+    /// agent and suspends it. If the failed payment was itself paying off
+    /// debts, those debts are reopened instead.
+    pub async fn apply_failure_policy(
+        pool: &PgPool,
+        config: &Config,
+        settlement_id: Uuid,
+    ) -> Result<(), sqlx::Error> {
+        let policy = config.settlement_failure_policy;
+
+        let payment: Option<(Uuid, String, Option<String>, Option<DomainU256>)> = sqlx::query_as(
+            "SELECT id, asset, network, amount FROM payments WHERE settlement_id = $1",
+        )
+        .bind(settlement_id)
+        .fetch_optional(pool)
+        .await?;
+
+        let Some((payment_id, asset, network, amount)) = payment else {
+            return Ok(());
+        };
+
+        let agent: Option<(Option<Uuid>,)> = sqlx::query_as(
+            r#"
+            SELECT agent_id FROM threads WHERE payment_id = $1
+            UNION ALL
+            SELECT agent_id FROM replies WHERE payment_id = $1
+            UNION ALL
+            SELECT id FROM agents WHERE payment_id = $1
+            UNION ALL
+            SELECT agent_id FROM debts WHERE cleared_by_payment_id = $1
+            UNION ALL
+            SELECT agent_id FROM access_grants WHERE payment_id = $1
+            UNION ALL
+            SELECT agent_id FROM thread_bumps WHERE payment_id = $1
+            LIMIT 1
+            "#,
+        )
+        .bind(payment_id)
+        .fetch_optional(pool)
+        .await?;
+        let agent_id = agent.and_then(|(id,)| id);
+
+        let mut tx = pool.begin().await?;
+
+        if policy.hide_content {
+            // Content bought by this payment, or by debts it was meant to clear
+            for table in ["threads", "replies"] {
+                sqlx::query(&format!(
+                    r#"
+                    UPDATE {} SET hidden = true
+                    WHERE payment_id = $1
+                       OR payment_id IN (SELECT payment_id FROM debts WHERE cleared_by_payment_id = $1)
+                    "#,
+                    table
+                ))
+                .bind(payment_id)
+                .execute(&mut *tx)
+                .await?;
+            }
+        }
+
+        // Reads and bumps bought by this payment were never paid for
+        for table in ["access_grants", "thread_bumps"] {
+            sqlx::query(&format!("DELETE FROM {} WHERE payment_id = $1", table))
+                .bind(payment_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        // A bounty it was meant to fund was never held, and isn't owed
+        let cancelled_bounty: Option<(DomainU256,)> = sqlx::query_as(
+            r#"
+            UPDATE bounties SET status = 'cancelled', resolved_at = NOW()
+            WHERE payment_id = $1 AND status = 'open'
+            RETURNING amount
+            "#,
+        )
+        .bind(payment_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let reopened = sqlx::query(
+            r#"
+            UPDATE debts SET status = 'open', cleared_by_payment_id = NULL, cleared_at = NULL
+            WHERE cleared_by_payment_id = $1
+            "#,
+        )
+        .bind(payment_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        // The payment in main-token units; a bounty it funded was never held
+        let amount = amount.unwrap_or(DomainU256(U256::zero()));
+        let canonical_decimals = config.canonical_asset().decimals;
+        let total = match config.find_asset(network.as_deref().unwrap_or_default(), &asset) {
+            Some(asset) => asset.amount_to_canonical(amount, canonical_decimals).0,
+            None => amount.0,
+        };
+        let bounty = cancelled_bounty.map(|(bounty,)| bounty.0).unwrap_or_default();
+        let owed = total.saturating_sub(bounty);
+
+        let mut debt_recorded = false;
+        if let Some(agent_id) = agent_id {
+            if policy.record_debt && reopened == 0 {
+                debt_recorded = sqlx::query(
+                    r#"
+                    INSERT INTO debts (agent_id, payment_id, amount)
+                    VALUES ($1, $2, $3)
+                    ON CONFLICT (payment_id) DO NOTHING
+                    "#,
+                )
+                .bind(agent_id)
+                .bind(payment_id)
+                .bind(DomainU256(owed))
+                .execute(&mut *tx)
+                .await?
+                .rows_affected()
+                    > 0;
+            }
+
+            if policy.suspend_agent {
+                sqlx::query("UPDATE agents SET suspended = true WHERE id = $1")
+                    .bind(agent_id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+        }
+
+        // The money never reached the platform wallet: debts it was paying
+        // are owed again, otherwise it is owed as a new debt or written off
+        let posted: (bool,) = sqlx::query_as(
+            "SELECT EXISTS (SELECT 1 FROM journal_entries WHERE kind = 'settlement_failure' AND payment_id = $1)",
+        )
+        .bind(payment_id)
+        .fetch_one(&mut *tx)
+        .await?;
+        if !posted.0 {
+            let entry = NewJournalEntry::new(EntryKind::SettlementFailure, agent_id)
+                .payment(Some(payment_id))
+                .credit(Account::platform(AccountKind::PlatformWallet), total);
+            let entry = if reopened > 0 {
+                entry.debit(Account::agent(AccountKind::Receivable, agent_id), total)
+            } else {
+                let loss = if debt_recorded {
+                    Account::agent(AccountKind::Receivable, agent_id)
+                } else {
+                    Account::platform(AccountKind::WriteOffs)
+                };
+                entry
+                    .debit(loss, owed)
+                    .debit(Account::platform(AccountKind::BountyEscrow), bounty)
+            };
+            LedgerService::post(&mut tx, &entry).await?;
+        }
+
+        tx.commit().await?;
+
+        tracing::warn!(
+            "Applied settlement failure policy for payment {} (agent {:?})",
+            payment_id,
+            agent_id
+        );
+        Ok(())
+    }
+
+    pub async fn list_for_agent(pool: &PgPool, agent_id: Uuid) -> Result<Vec<Debt>, sqlx::Error> {
+        sqlx::query_as::<_, Debt>(
+            r#"
+            SELECT id, agent_id, payment_id, amount, status, cleared_by_payment_id, created_at, cleared_at
+            FROM debts
+            WHERE agent_id = $1
+            ORDER BY created_at DESC
+            "#,
+        )
+        .bind(agent_id)
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Sum of an agent's open debts
+    pub async fn open_total(pool: &PgPool, agent_id: Uuid) -> Result<DomainU256, sqlx::Error> {
+        let (total,): (DomainU256,) = sqlx::query_as(
+            "SELECT COALESCE(SUM(amount), 0) FROM debts WHERE agent_id = $1 AND status = 'open'",
+        )
+        .bind(agent_id)
+        .fetch_one(pool)
+        .await?;
+        Ok(total)
+    }
+
+    /// Mark debts paid by a purchase, restore their content and lift the
+    /// suspension once nothing is owed
+    pub async fn clear(
+        pool: &PgPool,
+        agent_id: Uuid,
+        debt_ids: &[Uuid],
+        purchase: Purchase,
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = pool.begin().await?;
+
+        sqlx::query(
+            r#"
+            UPDATE debts SET status = 'paid', cleared_by_payment_id = $1, cleared_at = NOW()
+            WHERE id = ANY($2) AND agent_id = $3 AND status = 'open'
+            "#,
+        )
+        .bind(purchase.payment_id)
+        .bind(debt_ids)
+        .bind(agent_id)
+        .execute(&mut *tx)
+        .await?;
+
+        for table in ["threads", "replies"] {
+            sqlx::query(&format!(
+                "UPDATE {} SET hidden = false WHERE payment_id IN (SELECT payment_id FROM debts WHERE id = ANY($1))",
+                table
+            ))
+            .bind(debt_ids)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        sqlx::query(
+            r#"
+            UPDATE agents SET suspended = false
+            WHERE id = $1
+              AND NOT EXISTS (SELECT 1 FROM debts WHERE agent_id = $1 AND status = 'open')
+            "#,
+        )
+        .bind(agent_id)
+        .execute(&mut *tx)
+        .await?;
+
+        LedgerService::post_purchase(&mut tx, &purchase).await?;
+
+        tx.commit().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Fixture {
+        agent_id: Uuid,
+        payment_id: Uuid,
+        settlement_id: Uuid,
+    }
+
+    /// An agent and a queued 1000-unit payment from it
+    async fn paid(pool: &PgPool) -> Fixture {
+        let config = Config::for_tests();
+        let (agent_id,): (Uuid,) = sqlx::query_as("INSERT INTO agents (api_key, name) VALUES ($1, 'payer') RETURNING id")
+            .bind(Uuid::new_v4().to_string())
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        let settlement_id = Uuid::new_v4();
+        let (payment_id,): (Uuid,) = sqlx::query_as(
+            r#"
+            INSERT INTO payments (payer, asset, nonce, payload_hash, resource, amount, network, settlement_id)
+            VALUES ('0xpayer', $1, $2, $2, '/api/threads', 1000, $3, $4)
+            RETURNING id
+            "#,
+        )
+        .bind(&config.canonical_asset().address)
+        .bind(Uuid::new_v4().to_string())
+        .bind(&config.canonical_asset().network)
+        .bind(settlement_id)
+        .fetch_one(pool)
+        .await
+        .unwrap();
+
+        Fixture {
+            agent_id,
+            payment_id,
+            settlement_id,
+        }
+    }
+
+    async fn account_balance(pool: &PgPool, kind: &str, agent_id: Option<Uuid>) -> String {
+        let (balance,): (String,) = sqlx::query_as(
+            r#"
+            SELECT COALESCE(SUM(l.amount), 0)::text FROM ledger_lines l
+            JOIN ledger_accounts a ON a.id = l.account_id
+            WHERE a.kind = $1 AND a.agent_id IS NOT DISTINCT FROM $2
+            "#,
+        )
+        .bind(kind)
+        .bind(agent_id)
+        .fetch_one(pool)
+        .await
+        .unwrap();
+        balance
+    }
+
+    #[sqlx::test(migrations = "../migrations")]
+    async fn test_failed_thread_payment_hides_and_records_debt(pool: PgPool) {
+        let config = Config::for_tests();
+        let fixture = paid(&pool).await;
+        let (thread_id,): (Uuid,) = sqlx::query_as(
+            r#"
+            INSERT INTO threads (board_id, agent_id, title, content, payment_id)
+            SELECT id, $1, 'title', 'content', $2 FROM boards LIMIT 1
+            RETURNING id
+            "#,
+        )
+        .bind(fixture.agent_id)
+        .bind(fixture.payment_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+
+        // A retried failure applies the policy once
+        for _ in 0..2 {
+            DebtService::apply_failure_policy(&pool, &config, fixture.settlement_id)
+                .await
+                .unwrap();
+        }
+
+        let (hidden,): (bool,) = sqlx::query_as("SELECT hidden FROM threads WHERE id = $1")
+            .bind(thread_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert!(hidden);
+        let (suspended,): (bool,) = sqlx::query_as("SELECT suspended FROM agents WHERE id = $1")
+            .bind(fixture.agent_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert!(suspended);
+
+        let debts = DebtService::list_for_agent(&pool, fixture.agent_id).await.unwrap();
+        assert_eq!(debts.len(), 1);
+        assert_eq!(debts[0].status, "open");
+        assert_eq!(DebtService::open_total(&pool, fixture.agent_id).await.unwrap().to_string(), "1000");
+        assert_eq!(account_balance(&pool, "receivable", Some(fixture.agent_id)).await, "1000");
+        assert_eq!(account_balance(&pool, "platform_wallet", None).await, "-1000");
+    }
+
+    #[sqlx::test(migrations = "../migrations")]
+    async fn test_failed_debt_payment_reopens_debts(pool: PgPool) {
+        let config = Config::for_tests();
+        let fixture = paid(&pool).await;
+        let earlier = paid(&pool).await;
+        let (debt_id,): (Uuid,) = sqlx::query_as(
+            r#"
+            INSERT INTO debts (agent_id, payment_id, amount, status, cleared_by_payment_id, cleared_at)
+            VALUES ($1, $2, 1000, 'paid', $3, NOW())
+            RETURNING id
+            "#,
+        )
+        .bind(fixture.agent_id)
+        .bind(earlier.payment_id)
+        .bind(fixture.payment_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+
+        DebtService::apply_failure_policy(&pool, &config, fixture.settlement_id)
+            .await
+            .unwrap();
+
+        let debts = DebtService::list_for_agent(&pool, fixture.agent_id).await.unwrap();
+        assert_eq!(debts.len(), 1);
+        assert_eq!(debts[0].id, debt_id);
+        assert_eq!(debts[0].status, "open");
+        assert_eq!(debts[0].cleared_by_payment_id, None);
+        assert_eq!(account_balance(&pool, "receivable", Some(fixture.agent_id)).await, "1000");
+        assert_eq!(account_balance(&pool, "write_offs", None).await, "0");
+    }
+}
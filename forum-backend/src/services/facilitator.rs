@@ -0,0 +1,811 @@
+//! x402 facilitator clients
+//!
+//! The middleware and the settlement worker talk to the facilitator through
+//! the `Facilitator` trait. `HttpFacilitator` calls a real facilitator;
+//! `FailoverFacilitator` tries an ordered list of them behind circuit
+//! breakers; `SimulatedFacilitator` answers in-process so the backend can
+//! run locally and in tests without a live facilitator or testnet funds.
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use utoipa::ToSchema;
+
+use crate::models::x402::{PaymentScheme, SettleResponse, VerifyRequest, VerifyResponse};
+
+/// How long the simulator hangs before reporting a timeout
+const SIMULATED_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, thiserror::Error)]
+pub enum FacilitatorError {
+    #[error("failed to contact facilitator: {0}")]
+    Transport(String),
+    #[error("facilitator returned error: {status} - {body}")]
+    Status { status: u16, body: String },
+    #[error("failed to parse facilitator response: {0}")]
+    InvalidResponse(String),
+    #[error("facilitator timed out")]
+    Timeout,
+    #[error("no facilitator available")]
+    Unavailable,
+}
+
+impl FacilitatorError {
+    /// Whether the facilitator is unreachable or failing, rather than
+    /// refusing this particular request
+    ///
+    /// Only outages count towards a circuit breaker, so clients sending
+    /// malformed payments can't open it.
+    pub fn is_outage(&self) -> bool {
+        match self {
+            Self::Transport(_) | Self::Timeout => true,
+            Self::Status { status, .. } => *status >= 500,
+            Self::InvalidResponse(_) | Self::Unavailable => false,
+        }
+    }
+}
+
+/// Verifies and settles x402 payments
+#[async_trait]
+pub trait Facilitator: Send + Sync {
+    async fn verify(&self, request: &VerifyRequest) -> Result<VerifyResponse, FacilitatorError>;
+
+    /// Settle a payment (takes the same request as verify)
+    async fn settle(&self, request: &VerifyRequest) -> Result<SettleResponse, FacilitatorError>;
+
+    /// Whether `settle_batch` submits payments together rather than one by one
+    ///
+    /// `/settle/batch` is not part of the x402 facilitator API; it is an
+    /// extension only some facilitators offer.
+    fn supports_batch(&self) -> bool {
+        false
+    }
+
+    /// Settle several payments, returning one response per request in order
+    ///
+    /// The default settles them one at a time.
+    async fn settle_batch(
+        &self,
+        requests: &[VerifyRequest],
+    ) -> Result<Vec<SettleResponse>, FacilitatorError> {
+        Ok(settle_one_by_one(self, requests).await)
+    }
+
+    /// Health of the underlying facilitators, for implementations that track it
+    fn health(&self) -> Vec<FacilitatorHealth> {
+        Vec::new()
+    }
+}
+
+/// Settle each request on its own, reporting errors as failed responses so
+/// the settlements that did go through are not lost
+async fn settle_one_by_one<F: Facilitator + ?Sized>(
+    facilitator: &F,
+    requests: &[VerifyRequest],
+) -> Vec<SettleResponse> {
+    let mut responses = Vec::with_capacity(requests.len());
+    for request in requests {
+        responses.push(match facilitator.settle(request).await {
+            Ok(response) => response,
+            Err(e) => SettleResponse {
+                success: false,
+                network: request.payment_requirements.network().to_string(),
+                transaction: None,
+                error_reason: Some(e.to_string()),
+                payer: None,
+            },
+        });
+    }
+    responses
+}
+
+/// Circuit breaker state of one facilitator
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct FacilitatorHealth {
+    pub name: String,
+    /// False while the circuit is open
+    pub available: bool,
+    pub consecutive_failures: u32,
+    /// Seconds until an open circuit lets a trial call through
+    pub retry_in_seconds: Option<u64>,
+}
+
+/// Body of `{url}/settle/batch`
+#[derive(Serialize)]
+struct BatchSettleRequest<'a> {
+    settlements: &'a [VerifyRequest],
+}
+
+#[derive(Deserialize)]
+struct BatchSettleResponse {
+    results: Vec<SettleResponse>,
+}
+
+/// Facilitator reached over HTTP (`{url}/verify`, `{url}/settle`, and
+/// optionally `{url}/settle/batch`)
+pub struct HttpFacilitator {
+    url: String,
+    http_client: reqwest::Client,
+    verify_timeout: Duration,
+    settle_timeout: Duration,
+    batch_settle: bool,
+}
+
+impl HttpFacilitator {
+    pub fn new(
+        url: String,
+        http_client: reqwest::Client,
+        verify_timeout: Duration,
+        settle_timeout: Duration,
+    ) -> Self {
+        Self {
+            url,
+            http_client,
+            verify_timeout,
+            settle_timeout,
+            batch_settle: false,
+        }
+    }
+
+    /// Settle batches through `{url}/settle/batch`
+    pub fn with_batch_settle(mut self, enabled: bool) -> Self {
+        self.batch_settle = enabled;
+        self
+    }
+
+    async fn post<T: serde::de::DeserializeOwned>(
+        &self,
+        path: &str,
+        request: &(impl Serialize + Sync),
+        timeout: Duration,
+    ) -> Result<T, FacilitatorError> {
+        let builder = self
+            .http_client
+            .post(format!("{}/{}", self.url, path))
+            .json(request)
+            .timeout(timeout);
+
+        let response = builder.send().await.map_err(|e| {
+            if e.is_timeout() {
+                FacilitatorError::Timeout
+            } else {
+                FacilitatorError::Transport(e.to_string())
+            }
+        })?;
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            let body = response.text().await.unwrap_or_default();
+            return Err(FacilitatorError::Status { status, body });
+        }
+
+        response
+            .json::<T>()
+            .await
+            .map_err(|e| FacilitatorError::InvalidResponse(e.to_string()))
+    }
+}
+
+#[async_trait]
+impl Facilitator for HttpFacilitator {
+    async fn verify(&self, request: &VerifyRequest) -> Result<VerifyResponse, FacilitatorError> {
+        self.post("verify", request, self.verify_timeout).await
+    }
+
+    async fn settle(&self, request: &VerifyRequest) -> Result<SettleResponse, FacilitatorError> {
+        self.post("settle", request, self.settle_timeout).await
+    }
+
+    fn supports_batch(&self) -> bool {
+        self.batch_settle
+    }
+
+    async fn settle_batch(
+        &self,
+        requests: &[VerifyRequest],
+    ) -> Result<Vec<SettleResponse>, FacilitatorError> {
+        if !self.batch_settle {
+            return Ok(settle_one_by_one(self, requests).await);
+        }
+
+        let response: BatchSettleResponse = self
+            .post(
+                "settle/batch",
+                &BatchSettleRequest { settlements: requests },
+                self.settle_timeout,
+            )
+            .await?;
+
+        if response.results.len() != requests.len() {
+            return Err(FacilitatorError::InvalidResponse(format!(
+                "expected {} batch results, got {}",
+                requests.len(),
+                response.results.len()
+            )));
+        }
+        Ok(response.results)
+    }
+}
+
+/// Stops calling a facilitator after repeated failures
+///
+/// Opens after `threshold` consecutive failures. Once `cooldown` has passed
+/// it is half-open: a single trial call is let through, which closes the
+/// circuit on success or reopens it for another cooldown on failure.
+pub struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    state: Mutex<BreakerState>,
+}
+
+#[derive(Default)]
+struct BreakerState {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+    /// A half-open circuit's trial call is running
+    trial_in_flight: bool,
+}
+
+impl CircuitBreaker {
+    pub fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            threshold: threshold.max(1),
+            cooldown,
+            state: Mutex::new(BreakerState::default()),
+        }
+    }
+
+    /// Whether a call may be attempted now
+    ///
+    /// On a half-open circuit only the first caller gets through, as the
+    /// trial; it must report back with `record_success` or `record_failure`.
+    pub fn try_acquire(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        match state.open_until {
+            None => true,
+            Some(until) if Instant::now() >= until && !state.trial_in_flight => {
+                state.trial_in_flight = true;
+                true
+            }
+            Some(_) => false,
+        }
+    }
+
+    /// The facilitator answered, even if it refused the request
+    pub fn record_success(&self) {
+        *self.state.lock().unwrap() = BreakerState::default();
+    }
+
+    pub fn record_failure(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.consecutive_failures += 1;
+        if state.trial_in_flight || state.consecutive_failures >= self.threshold {
+            state.open_until = Some(Instant::now() + self.cooldown);
+            state.trial_in_flight = false;
+        }
+    }
+
+    fn health(&self, name: &str) -> FacilitatorHealth {
+        let state = self.state.lock().unwrap();
+        let retry_in = state
+            .open_until
+            .and_then(|until| until.checked_duration_since(Instant::now()));
+
+        FacilitatorHealth {
+            name: name.to_string(),
+            available: retry_in.is_none() && !state.trial_in_flight,
+            consecutive_failures: state.consecutive_failures,
+            retry_in_seconds: retry_in.map(|d| d.as_secs() + 1),
+        }
+    }
+}
+
+/// Spender a permit payment was signed for: its `spender`, or else the
+/// `facilitatorSigner` it was offered. None for schemes without a spender.
+fn permit_spender(request: &VerifyRequest) -> Option<&str> {
+    if request.payment_requirements.scheme() != PaymentScheme::Permit.as_str() {
+        return None;
+    }
+
+    request
+        .payment_payload
+        .get("payload")
+        .and_then(|p| p.get("authorization"))
+        .and_then(|a| a.get("spender"))
+        .and_then(|v| v.as_str())
+        .or_else(|| {
+            request
+                .payment_requirements
+                .extra()
+                .and_then(|extra| extra.get("facilitatorSigner"))
+                .and_then(|v| v.as_str())
+        })
+}
+
+struct FailoverMember {
+    name: String,
+    facilitator: Arc<dyn Facilitator>,
+    /// Spender this facilitator's permits must name
+    signer: String,
+    breaker: CircuitBreaker,
+}
+
+impl FailoverMember {
+    /// Permits can only be verified and settled by the facilitator they name
+    /// as spender; other payments by any
+    fn can_handle(&self, request: &VerifyRequest) -> bool {
+        permit_spender(request).is_none_or(|spender| spender.eq_ignore_ascii_case(&self.signer))
+    }
+}
+
+/// Tries facilitators in order, skipping those whose circuit is open
+///
+/// Verify and settle pick independently, so a payment verified by one
+/// facilitator can be settled by another. Permit payments only fail over
+/// among facilitators sharing the permit's spender.
+pub struct FailoverFacilitator {
+    members: Vec<FailoverMember>,
+}
+
+impl FailoverFacilitator {
+    /// `facilitators` are (name, facilitator, permit signer), in order of preference
+    pub fn new(
+        facilitators: Vec<(String, Arc<dyn Facilitator>, String)>,
+        failure_threshold: u32,
+        cooldown: Duration,
+    ) -> Self {
+        Self {
+            members: facilitators
+                .into_iter()
+                .map(|(name, facilitator, signer)| FailoverMember {
+                    name,
+                    facilitator,
+                    signer,
+                    breaker: CircuitBreaker::new(failure_threshold, cooldown),
+                })
+                .collect(),
+        }
+    }
+
+    /// Call the first available member matching `filter`, falling through to
+    /// the next on errors
+    async fn call_members<T, P, F, Fut>(
+        &self,
+        operation: &str,
+        filter: P,
+        call: F,
+    ) -> Result<T, FacilitatorError>
+    where
+        P: Fn(&FailoverMember) -> bool,
+        F: Fn(Arc<dyn Facilitator>) -> Fut,
+        Fut: Future<Output = Result<T, FacilitatorError>>,
+    {
+        let mut last_error = FacilitatorError::Unavailable;
+
+        for member in self
+            .members
+            .iter()
+            .filter(|m| filter(m) && m.breaker.try_acquire())
+        {
+            match call(member.facilitator.clone()).await {
+                Ok(response) => {
+                    member.breaker.record_success();
+                    return Ok(response);
+                }
+                Err(e) => {
+                    tracing::warn!("Facilitator {} {} failed: {}", member.name, operation, e);
+                    // A refused request (4xx) says nothing about the facilitator's health
+                    if e.is_outage() {
+                        member.breaker.record_failure();
+                    } else {
+                        member.breaker.record_success();
+                    }
+                    last_error = e;
+                }
+            }
+        }
+
+        Err(last_error)
+    }
+}
+
+#[async_trait]
+impl Facilitator for FailoverFacilitator {
+    async fn verify(&self, request: &VerifyRequest) -> Result<VerifyResponse, FacilitatorError> {
+        self.call_members("verify", |m| m.can_handle(request), |f| async move { f.verify(request).await })
+            .await
+    }
+
+    async fn settle(&self, request: &VerifyRequest) -> Result<SettleResponse, FacilitatorError> {
+        self.call_members("settle", |m| m.can_handle(request), |f| async move { f.settle(request).await })
+            .await
+    }
+
+    fn supports_batch(&self) -> bool {
+        self.members.iter().any(|m| m.facilitator.supports_batch())
+    }
+
+    /// Batches go to batch-capable facilitators that can settle every payment
+    /// in them; without any, each payment fails over on its own
+    async fn settle_batch(
+        &self,
+        requests: &[VerifyRequest],
+    ) -> Result<Vec<SettleResponse>, FacilitatorError> {
+        let batchable =
+            |m: &FailoverMember| m.facilitator.supports_batch() && requests.iter().all(|r| m.can_handle(r));
+        if !self.members.iter().any(batchable) {
+            return Ok(settle_one_by_one(self, requests).await);
+        }
+
+        self.call_members("settle_batch", batchable, |f| async move { f.settle_batch(requests).await })
+            .await
+    }
+
+    fn health(&self) -> Vec<FacilitatorHealth> {
+        self.members
+            .iter()
+            .map(|m| m.breaker.health(&m.name))
+            .collect()
+    }
+}
+
+/// How the simulated facilitator responds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SimulatorBehavior {
+    /// Verify and settle every payment
+    Accept,
+    /// Report every payment as invalid
+    Reject,
+    /// Hang, then fail with a timeout
+    Timeout,
+    /// Verify payments but fail to settle them
+    FailSettlement,
+}
+
+impl std::str::FromStr for SimulatorBehavior {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "accept" => Ok(SimulatorBehavior::Accept),
+            "reject" => Ok(SimulatorBehavior::Reject),
+            "timeout" => Ok(SimulatorBehavior::Timeout),
+            "fail_settlement" => Ok(SimulatorBehavior::FailSettlement),
+            other => Err(format!("Unknown simulator behavior: {}", other)),
+        }
+    }
+}
+
+/// In-process facilitator for development and tests
+///
+/// Never touches a chain; settled payments get a deterministic fake
+/// transaction hash derived from the payload.
+pub struct SimulatedFacilitator {
+    behavior: SimulatorBehavior,
+    timeout: Duration,
+}
+
+impl SimulatedFacilitator {
+    pub fn new(behavior: SimulatorBehavior) -> Self {
+        Self {
+            behavior,
+            timeout: SIMULATED_TIMEOUT,
+        }
+    }
+
+    /// Payer address claimed by the payload (permit owner or exact from)
+    fn payer(request: &VerifyRequest) -> Option<String> {
+        let authorization = request
+            .payment_payload
+            .get("payload")
+            .and_then(|p| p.get("authorization"))?;
+
+        authorization
+            .get("owner")
+            .or_else(|| authorization.get("from"))
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+    }
+
+    fn fake_transaction(request: &VerifyRequest) -> String {
+        let digest = Keccak256::digest(request.payment_payload.to_string().as_bytes());
+        format!("0x{}", hex::encode(digest))
+    }
+}
+
+#[async_trait]
+impl Facilitator for SimulatedFacilitator {
+    async fn verify(&self, request: &VerifyRequest) -> Result<VerifyResponse, FacilitatorError> {
+        match self.behavior {
+            SimulatorBehavior::Reject => Ok(VerifyResponse {
+                is_valid: false,
+                invalid_reason: Some("simulated_rejection".to_string()),
+                payer: Self::payer(request),
+            }),
+            SimulatorBehavior::Timeout => {
+                tokio::time::sleep(self.timeout).await;
+                Err(FacilitatorError::Timeout)
+            }
+            SimulatorBehavior::Accept | SimulatorBehavior::FailSettlement => Ok(VerifyResponse {
+                is_valid: true,
+                invalid_reason: None,
+                payer: Self::payer(request),
+            }),
+        }
+    }
+
+    async fn settle(&self, request: &VerifyRequest) -> Result<SettleResponse, FacilitatorError> {
+        let network = request.payment_requirements.network().to_string();
+
+        match self.behavior {
+            SimulatorBehavior::Accept => Ok(SettleResponse {
+                success: true,
+                network,
+                transaction: Some(Self::fake_transaction(request)),
+                error_reason: None,
+                payer: Self::payer(request),
+            }),
+            SimulatorBehavior::Timeout => {
+                tokio::time::sleep(self.timeout).await;
+                Err(FacilitatorError::Timeout)
+            }
+            SimulatorBehavior::Reject | SimulatorBehavior::FailSettlement => Ok(SettleResponse {
+                success: false,
+                network,
+                transaction: None,
+                error_reason: Some("simulated_settlement_failure".to_string()),
+                payer: Self::payer(request),
+            }),
+        }
+    }
+
+    fn supports_batch(&self) -> bool {
+        true
+    }
+
+    /// Settles the whole batch in one fake transaction
+    async fn settle_batch(
+        &self,
+        requests: &[VerifyRequest],
+    ) -> Result<Vec<SettleResponse>, FacilitatorError> {
+        let mut hasher = Keccak256::new();
+        for request in requests {
+            hasher.update(request.payment_payload.to_string().as_bytes());
+        }
+        let transaction = format!("0x{}", hex::encode(hasher.finalize()));
+
+        let mut responses = Vec::with_capacity(requests.len());
+        for request in requests {
+            let mut response = self.settle(request).await?;
+            if response.success {
+                response.transaction = Some(transaction.clone());
+            }
+            responses.push(response);
+        }
+        Ok(responses)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::x402::{PaymentRequirementsV2, VersionedPaymentRequirements};
+
+    const SIGNER_A: &str = "0x00000000000000000000000000000000000000aa";
+    const SIGNER_B: &str = "0x00000000000000000000000000000000000000bb";
+
+    fn permit_request(spender: Option<&str>) -> VerifyRequest {
+        let mut request = sample_request();
+        request.payment_payload["scheme"] = "permit".into();
+        if let Some(spender) = spender {
+            request.payment_payload["payload"]["authorization"]["spender"] = spender.into();
+        }
+        if let VersionedPaymentRequirements::V2(requirements) = &mut request.payment_requirements {
+            requirements.scheme = "permit".to_string();
+            requirements.extra = Some(serde_json::json!({ "facilitatorSigner": SIGNER_A }));
+        }
+        request
+    }
+
+    fn sample_request() -> VerifyRequest {
+        VerifyRequest {
+            x402_version: 2,
+            payment_payload: serde_json::json!({
+                "scheme": "exact",
+                "payload": {
+                    "authorization": { "from": "0xpayer", "nonce": "0x01" },
+                    "signature": "0x"
+                }
+            }),
+            payment_requirements: VersionedPaymentRequirements::V2(PaymentRequirementsV2 {
+                scheme: "exact".to_string(),
+                network: "eip155:8453".to_string(),
+                amount: "1000".to_string(),
+                asset: "0xtoken".to_string(),
+                pay_to: "0xpayto".to_string(),
+                max_timeout_seconds: 300,
+                extra: None,
+            }),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_simulator_accept() {
+        let facilitator = SimulatedFacilitator::new(SimulatorBehavior::Accept);
+        let request = sample_request();
+
+        let verified = facilitator.verify(&request).await.unwrap();
+        assert!(verified.is_valid);
+        assert_eq!(verified.payer.as_deref(), Some("0xpayer"));
+
+        let settled = facilitator.settle(&request).await.unwrap();
+        assert!(settled.success);
+        assert_eq!(settled.network, "eip155:8453");
+        assert_eq!(settled.transaction.unwrap().len(), 66);
+    }
+
+    #[tokio::test]
+    async fn test_simulator_reject_and_fail_settlement() {
+        let request = sample_request();
+
+        let rejected = SimulatedFacilitator::new(SimulatorBehavior::Reject)
+            .verify(&request)
+            .await
+            .unwrap();
+        assert!(!rejected.is_valid);
+
+        let failing = SimulatedFacilitator::new(SimulatorBehavior::FailSettlement);
+        assert!(failing.verify(&request).await.unwrap().is_valid);
+        assert!(!failing.settle(&request).await.unwrap().success);
+    }
+
+    #[tokio::test]
+    async fn test_simulator_batch_shares_transaction() {
+        let mut second = sample_request();
+        second.payment_payload["payload"]["authorization"]["nonce"] = "0x02".into();
+        let requests = vec![sample_request(), second];
+
+        let facilitator = SimulatedFacilitator::new(SimulatorBehavior::Accept);
+        let responses = facilitator.settle_batch(&requests).await.unwrap();
+        assert_eq!(responses.len(), 2);
+        assert!(responses.iter().all(|r| r.success));
+        assert_eq!(responses[0].transaction, responses[1].transaction);
+
+        // Without batch support each payment gets its own transaction
+        let one_by_one = settle_one_by_one(&facilitator, &requests).await;
+        assert_ne!(one_by_one[0].transaction, one_by_one[1].transaction);
+    }
+
+    /// Refuses every request as a facilitator does a malformed payment
+    struct RefusingFacilitator;
+
+    #[async_trait]
+    impl Facilitator for RefusingFacilitator {
+        async fn verify(&self, _request: &VerifyRequest) -> Result<VerifyResponse, FacilitatorError> {
+            Err(FacilitatorError::Status { status: 400, body: "invalid payload".to_string() })
+        }
+
+        async fn settle(&self, _request: &VerifyRequest) -> Result<SettleResponse, FacilitatorError> {
+            Err(FacilitatorError::Status { status: 400, body: "invalid payload".to_string() })
+        }
+    }
+
+    #[test]
+    fn test_circuit_breaker_opens_and_recovers() {
+        let breaker = CircuitBreaker::new(2, Duration::ZERO);
+        breaker.record_failure();
+        assert_eq!(breaker.health("a").consecutive_failures, 1);
+
+        let breaker = CircuitBreaker::new(2, Duration::from_secs(60));
+        breaker.record_failure();
+        assert!(breaker.try_acquire());
+        breaker.record_failure();
+        assert!(!breaker.try_acquire());
+        assert!(!breaker.health("a").available);
+
+        breaker.record_success();
+        assert!(breaker.try_acquire());
+    }
+
+    #[test]
+    fn test_half_open_circuit_lets_one_trial_through() {
+        let breaker = CircuitBreaker::new(1, Duration::ZERO);
+        breaker.record_failure();
+
+        assert!(breaker.try_acquire());
+        assert!(!breaker.try_acquire());
+        assert!(!breaker.health("a").available);
+
+        // A failed trial reopens the circuit for another cooldown
+        breaker.record_failure();
+        assert!(breaker.try_acquire());
+        breaker.record_success();
+        assert!(breaker.try_acquire());
+        assert!(breaker.try_acquire());
+    }
+
+    #[test]
+    fn test_only_outages_count_as_failures() {
+        assert!(FacilitatorError::Timeout.is_outage());
+        assert!(FacilitatorError::Transport("refused".to_string()).is_outage());
+        assert!(FacilitatorError::Status { status: 503, body: String::new() }.is_outage());
+        assert!(!FacilitatorError::Status { status: 400, body: String::new() }.is_outage());
+        assert!(!FacilitatorError::InvalidResponse("eof".to_string()).is_outage());
+    }
+
+    #[tokio::test]
+    async fn test_refused_payments_keep_circuit_closed() {
+        let refusing: Arc<dyn Facilitator> = Arc::new(RefusingFacilitator);
+        let failover =
+            FailoverFacilitator::new(vec![("primary".to_string(), refusing, SIGNER_A.to_string())], 1, Duration::from_secs(60));
+
+        for _ in 0..3 {
+            let result = failover.verify(&sample_request()).await;
+            assert!(matches!(result, Err(FacilitatorError::Status { status: 400, .. })));
+        }
+        assert!(failover.health()[0].available);
+    }
+
+    #[tokio::test]
+    async fn test_failover_skips_failing_facilitator() {
+        let failing: Arc<dyn Facilitator> = Arc::new(SimulatedFacilitator {
+            behavior: SimulatorBehavior::Timeout,
+            timeout: Duration::from_millis(1),
+        });
+        let healthy: Arc<dyn Facilitator> = Arc::new(SimulatedFacilitator::new(SimulatorBehavior::Accept));
+        let failover = FailoverFacilitator::new(
+            vec![
+                ("primary".to_string(), failing, SIGNER_A.to_string()),
+                ("backup".to_string(), healthy, SIGNER_A.to_string()),
+            ],
+            1,
+            Duration::from_secs(60),
+        );
+
+        assert!(failover.verify(&sample_request()).await.unwrap().is_valid);
+        assert!(failover.settle(&sample_request()).await.unwrap().success);
+
+        let health = failover.health();
+        assert!(!health[0].available);
+        assert!(health[1].available);
+    }
+
+    #[tokio::test]
+    async fn test_simulator_timeout() {
+        let facilitator = SimulatedFacilitator {
+            behavior: SimulatorBehavior::Timeout,
+            timeout: Duration::from_millis(1),
+        };
+
+        let result = facilitator.verify(&sample_request()).await;
+        assert!(matches!(result, Err(FacilitatorError::Timeout)));
+    }
+
+    #[tokio::test]
+    async fn test_permits_fail_over_only_to_their_spender() {
+        let failing: Arc<dyn Facilitator> = Arc::new(SimulatedFacilitator {
+            behavior: SimulatorBehavior::Timeout,
+            timeout: Duration::from_millis(1),
+        });
+        let other_signer: Arc<dyn Facilitator> = Arc::new(SimulatedFacilitator::new(SimulatorBehavior::Accept));
+        let failover = FailoverFacilitator::new(
+            vec![
+                ("primary".to_string(), failing, SIGNER_A.to_string()),
+                ("backup".to_string(), other_signer, SIGNER_B.to_string()),
+            ],
+            1,
+            Duration::from_secs(60),
+        );
+
+        // The offered spender, and an explicit one, both belong to the primary
+        for request in [permit_request(None), permit_request(Some(SIGNER_A))] {
+            assert!(failover.settle(&request).await.is_err());
+        }
+        assert!(failover.settle(&permit_request(Some(SIGNER_B))).await.unwrap().success);
+        // exact payments have no spender and settle anywhere
+        assert!(failover.settle(&sample_request()).await.unwrap().success);
+    }
+}
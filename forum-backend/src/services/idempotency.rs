@@ -0,0 +1,117 @@
+use sqlx::PgPool;
+
+pub struct IdempotencyService;
+
+/// A previously seen idempotency key
+#[derive(Debug)]
+pub struct StoredIdempotencyKey {
+    pub request_hash: String,
+    pub status: String,
+    pub response_status: Option<i32>,
+    pub response_headers: Option<String>,
+    pub response_body: Option<Vec<u8>>,
+}
+
+type IdempotencyKeyRow = (String, String, Option<i32>, Option<String>, Option<Vec<u8>>);
+
+impl IdempotencyService {
+    /// Claim a key before running the request
+    ///
+    /// Returns false if the key already exists. In-progress claims older than
+    /// five minutes are treated as abandoned (e.g. a crash mid-request) and
+    /// can be claimed again.
+    pub async fn claim(
+        pool: &PgPool,
+        scope: &str,
+        key: &str,
+        request_hash: &str,
+    ) -> Result<bool, sqlx::Error> {
+        let claimed: Option<(String,)> = sqlx::query_as(
+            r#"
+            INSERT INTO idempotency_keys (scope, key, request_hash)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (scope, key) DO UPDATE
+                SET request_hash = EXCLUDED.request_hash, updated_at = NOW()
+                WHERE idempotency_keys.status = 'in_progress'
+                  AND idempotency_keys.updated_at < NOW() - INTERVAL '5 minutes'
+            RETURNING key
+            "#,
+        )
+        .bind(scope)
+        .bind(key)
+        .bind(request_hash)
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(claimed.is_some())
+    }
+
+    pub async fn get(
+        pool: &PgPool,
+        scope: &str,
+        key: &str,
+    ) -> Result<Option<StoredIdempotencyKey>, sqlx::Error> {
+        let row: Option<IdempotencyKeyRow> = sqlx::query_as(
+            r#"
+            SELECT request_hash, status, response_status, response_headers, response_body
+            FROM idempotency_keys
+            WHERE scope = $1 AND key = $2
+            "#,
+        )
+        .bind(scope)
+        .bind(key)
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(row.map(
+            |(request_hash, status, response_status, response_headers, response_body)| {
+                StoredIdempotencyKey {
+                    request_hash,
+                    status,
+                    response_status,
+                    response_headers,
+                    response_body,
+                }
+            },
+        ))
+    }
+
+    /// Store the response for a claimed key
+    pub async fn complete(
+        pool: &PgPool,
+        scope: &str,
+        key: &str,
+        response_status: i32,
+        response_headers: &str,
+        response_body: &[u8],
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            UPDATE idempotency_keys
+            SET status = 'completed', response_status = $3, response_headers = $4,
+                response_body = $5, updated_at = NOW()
+            WHERE scope = $1 AND key = $2
+            "#,
+        )
+        .bind(scope)
+        .bind(key)
+        .bind(response_status)
+        .bind(response_headers)
+        .bind(response_body)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Release a claimed key so the request can be retried
+    pub async fn release(pool: &PgPool, scope: &str, key: &str) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "DELETE FROM idempotency_keys WHERE scope = $1 AND key = $2 AND status = 'in_progress'",
+        )
+        .bind(scope)
+        .bind(key)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+}
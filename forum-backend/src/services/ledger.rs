@@ -0,0 +1,192 @@
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::models::{
+    Account, AccountBalance, AccountKind, AgentStatement, Earnings, NewJournalEntry, Purchase, RevenueLine,
+    StatementLine,
+};
+use crate::services::CreditService;
+
+pub struct LedgerService;
+
+impl LedgerService {
+    /// Post a journal entry inside the caller's transaction
+    ///
+    /// Empty entries are skipped. The database checks again that the entry
+    /// balances when the transaction commits.
+    pub async fn post(conn: &mut PgConnection, entry: &NewJournalEntry) -> Result<(), sqlx::Error> {
+        if entry.is_empty() {
+            return Ok(());
+        }
+        if !entry.is_balanced() {
+            return Err(sqlx::Error::InvalidArgument(format!(
+                "{:?} journal entry does not balance",
+                entry.kind
+            )));
+        }
+
+        let (entry_id,): (Uuid,) = sqlx::query_as(
+            r#"
+            INSERT INTO journal_entries (kind, agent_id, payment_id, reference_id)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id
+            "#,
+        )
+        .bind(entry.kind)
+        .bind(entry.agent_id)
+        .bind(entry.payment_id)
+        .bind(entry.reference_id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        for (account, amount) in entry.lines() {
+            let account_id = Self::account_id(conn, account).await?;
+            sqlx::query("INSERT INTO ledger_lines (entry_id, account_id, amount) VALUES ($1, $2, $3::numeric)")
+                .bind(entry_id)
+                .bind(account_id)
+                .bind(amount)
+                .execute(&mut *conn)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Post a purchase's journal entry inside the caller's transaction
+    ///
+    /// Purchases paid from credit debit the balance in the same transaction.
+    pub async fn post_purchase(conn: &mut PgConnection, purchase: &Purchase) -> Result<(), sqlx::Error> {
+        match &purchase.credit {
+            Some(spend) => CreditService::spend(conn, spend, &purchase.entry).await.map(|_| ()),
+            None => Self::post(conn, &purchase.entry).await,
+        }
+    }
+
+    /// Id of an account, opened on first use
+    ///
+    /// Two statements, so the lookup sees an account a concurrent
+    /// transaction opened while the insert waited on it.
+    async fn account_id(conn: &mut PgConnection, account: Account) -> Result<Uuid, sqlx::Error> {
+        sqlx::query("INSERT INTO ledger_accounts (kind, agent_id) VALUES ($1, $2) ON CONFLICT DO NOTHING")
+            .bind(account.kind)
+            .bind(account.agent_id)
+            .execute(&mut *conn)
+            .await?;
+
+        let (id,): (Uuid,) =
+            sqlx::query_as("SELECT id FROM ledger_accounts WHERE kind = $1 AND agent_id IS NOT DISTINCT FROM $2")
+                .bind(account.kind)
+                .bind(account.agent_id)
+                .fetch_one(conn)
+                .await?;
+        Ok(id)
+    }
+
+    /// Platform revenue by entry kind, with the trial balance
+    pub async fn earnings(pool: &PgPool) -> Result<Earnings, sqlx::Error> {
+        let breakdown = sqlx::query_as::<_, RevenueLine>(
+            r#"
+            SELECT e.kind, (-SUM(l.amount))::text AS amount, COUNT(DISTINCT e.id) AS count
+            FROM ledger_lines l
+            JOIN ledger_accounts a ON a.id = l.account_id
+            JOIN journal_entries e ON e.id = l.entry_id
+            WHERE a.kind = 'revenue'
+            GROUP BY e.kind
+            ORDER BY e.kind
+            "#,
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let accounts = sqlx::query_as::<_, AccountBalance>(
+            r#"
+            SELECT a.kind AS account,
+                   (SUM(l.amount) * CASE WHEN a.kind = ANY($1) THEN 1 ELSE -1 END)::text AS balance
+            FROM ledger_lines l
+            JOIN ledger_accounts a ON a.id = l.account_id
+            GROUP BY a.kind
+            ORDER BY a.kind
+            "#,
+        )
+        .bind(debit_normal())
+        .fetch_all(pool)
+        .await?;
+
+        let (total,): (String,) = sqlx::query_as(
+            r#"
+            SELECT (-COALESCE(SUM(l.amount), 0))::text
+            FROM ledger_lines l
+            JOIN ledger_accounts a ON a.id = l.account_id
+            WHERE a.kind IN ('revenue', 'refunds', 'write_offs')
+            "#,
+        )
+        .fetch_one(pool)
+        .await?;
+
+        let balance = |kind: AccountKind| {
+            accounts
+                .iter()
+                .find(|a| a.account == kind)
+                .map(|a| a.balance.clone())
+                .unwrap_or_else(|| "0".to_string())
+        };
+
+        Ok(Earnings {
+            total,
+            revenue: balance(AccountKind::Revenue),
+            refunds: balance(AccountKind::Refunds),
+            write_offs: balance(AccountKind::WriteOffs),
+            breakdown,
+            accounts,
+        })
+    }
+
+    /// An agent's account balances and most recent lines
+    pub async fn statement(
+        pool: &PgPool,
+        agent_id: Uuid,
+        limit: i64,
+        offset: i64,
+    ) -> Result<AgentStatement, sqlx::Error> {
+        let balances = sqlx::query_as::<_, AccountBalance>(
+            r#"
+            SELECT a.kind AS account,
+                   (COALESCE(SUM(l.amount), 0) * CASE WHEN a.kind = ANY($2) THEN 1 ELSE -1 END)::text AS balance
+            FROM ledger_accounts a
+            LEFT JOIN ledger_lines l ON l.account_id = a.id
+            WHERE a.agent_id = $1
+            GROUP BY a.kind
+            ORDER BY a.kind
+            "#,
+        )
+        .bind(agent_id)
+        .bind(debit_normal())
+        .fetch_all(pool)
+        .await?;
+
+        let lines = sqlx::query_as::<_, StatementLine>(
+            r#"
+            SELECT e.id AS entry_id, e.kind, a.kind AS account,
+                   (l.amount * CASE WHEN a.kind = ANY($2) THEN 1 ELSE -1 END)::text AS amount,
+                   e.payment_id, e.reference_id, e.created_at
+            FROM ledger_lines l
+            JOIN ledger_accounts a ON a.id = l.account_id
+            JOIN journal_entries e ON e.id = l.entry_id
+            WHERE a.agent_id = $1
+            ORDER BY e.created_at DESC, l.id
+            LIMIT $3 OFFSET $4
+            "#,
+        )
+        .bind(agent_id)
+        .bind(debit_normal())
+        .bind(limit.min(100))
+        .bind(offset)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(AgentStatement { balances, lines })
+    }
+}
+
+fn debit_normal() -> Vec<&'static str> {
+    AccountKind::DEBIT_NORMAL.iter().map(|kind| kind.as_str()).collect()
+}
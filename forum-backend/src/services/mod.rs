@@ -1,15 +1,43 @@
+mod access;
 mod agent;
 mod board;
-mod earnings;
+mod bounty;
+pub mod bounty_worker;
+mod credit;
+mod debt;
+pub mod facilitator;
+mod idempotency;
+mod ledger;
+mod payment;
+pub mod payout;
+pub mod pricing;
+pub mod payment_verifier;
 mod thread;
+mod tip;
+mod refund;
 mod reply;
 pub mod settlement_queue;
 pub mod settlement_worker;
 
+pub use access::AccessService;
 pub use agent::AgentService;
 pub use board::BoardService;
-pub use earnings::{EarningsService, EarningsBreakdown};
+pub use bounty::BountyService;
+pub use bounty_worker::BountyWorker;
+pub use credit::CreditService;
+pub use debt::DebtService;
+pub use facilitator::{
+    Facilitator, FacilitatorHealth, FailoverFacilitator, HttpFacilitator, SimulatedFacilitator,
+};
+pub use idempotency::IdempotencyService;
+pub use ledger::LedgerService;
+pub use payment::PaymentService;
+pub use payout::{FilePayoutExecutor, HttpPayoutExecutor, PayoutExecutor};
+pub use pricing::{PricedAction, PricingEngine};
+pub use payment_verifier::{ExpectedPayment, PaymentVerifier};
 pub use thread::ThreadService;
+pub use tip::TipService;
+pub use refund::RefundService;
 pub use reply::ReplyService;
 pub use settlement_queue::{SettlementQueue, StoredVerifyRequest};
 pub use settlement_worker::SettlementWorker;
@@ -0,0 +1,150 @@
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::{NewPayment, PaidFor, Payment, PaymentDetail};
+
+pub struct PaymentService;
+
+impl PaymentService {
+    /// Reserve a payment authorization before running the paid action
+    ///
+    /// Returns None if the same (payer, asset, nonce) or the same payload was
+    /// already used.
+    pub async fn reserve(pool: &PgPool, payment: &NewPayment<'_>) -> Result<Option<Uuid>, sqlx::Error> {
+        let id: Option<(Uuid,)> = sqlx::query_as(
+            r#"
+            INSERT INTO payments (payer, asset, amount, scheme, network, nonce, payload_hash, resource)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT DO NOTHING
+            RETURNING id
+            "#,
+        )
+        .bind(payment.payer.to_lowercase())
+        .bind(payment.asset.to_lowercase())
+        .bind(payment.amount)
+        .bind(payment.scheme)
+        .bind(payment.network)
+        .bind(payment.nonce.to_lowercase())
+        .bind(payment.payload_hash)
+        .bind(payment.resource)
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(id.map(|(id,)| id))
+    }
+
+    /// Drop a reservation whose settlement never went through
+    ///
+    /// Frees the authorization so the client can retry it.
+    pub async fn release(pool: &PgPool, id: Uuid) -> Result<(), sqlx::Error> {
+        sqlx::query("DELETE FROM payments WHERE id = $1 AND status = 'pending' AND settlement_id IS NULL")
+            .bind(id)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Link a payment to the queued settlement collecting it
+    pub async fn attach_settlement(
+        pool: &PgPool,
+        id: Uuid,
+        settlement_id: Uuid,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE payments SET settlement_id = $1, updated_at = NOW() WHERE id = $2")
+            .bind(settlement_id)
+            .bind(id)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Mark a payment settled during the request
+    pub async fn mark_settled(pool: &PgPool, id: Uuid, tx_hash: Option<&str>) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "UPDATE payments SET status = 'settled', tx_hash = $1, updated_at = NOW() WHERE id = $2",
+        )
+        .bind(tx_hash)
+        .bind(id)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Mark a payment failed whose settlement was withdrawn
+    pub async fn mark_failed(pool: &PgPool, id: Uuid) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE payments SET status = 'failed', updated_at = NOW() WHERE id = $1 AND status = 'pending'")
+            .bind(id)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Mirror a completed or failed settlement onto its payment
+    pub async fn update_from_settlement(
+        pool: &PgPool,
+        settlement_id: Uuid,
+        status: &str,
+        tx_hash: Option<&str>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            UPDATE payments
+            SET status = $1, tx_hash = COALESCE($2, tx_hash), updated_at = NOW()
+            WHERE settlement_id = $3
+            "#,
+        )
+        .bind(status)
+        .bind(tx_hash)
+        .bind(settlement_id)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn get_by_id(pool: &PgPool, id: Uuid) -> Result<Option<PaymentDetail>, sqlx::Error> {
+        let payment = sqlx::query_as::<_, Payment>(
+            r#"
+            SELECT id, payer, asset, amount, scheme, network, nonce, resource,
+                   settlement_id, status, tx_hash, created_at, updated_at
+            FROM payments
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(pool)
+        .await?;
+
+        let Some(payment) = payment else {
+            return Ok(None);
+        };
+
+        let paid_for: Option<(String, Uuid)> = sqlx::query_as(
+            r#"
+            SELECT 'thread', id FROM threads WHERE payment_id = $1
+            UNION ALL
+            SELECT 'reply', id FROM replies WHERE payment_id = $1
+            UNION ALL
+            SELECT 'registration', id FROM agents WHERE payment_id = $1
+            UNION ALL
+            SELECT 'debt', id FROM debts WHERE cleared_by_payment_id = $1
+            UNION ALL
+            SELECT 'credit', id FROM credit_top_ups WHERE payment_id = $1
+            UNION ALL
+            SELECT 'read', id FROM access_grants WHERE payment_id = $1
+            UNION ALL
+            SELECT 'tip', id FROM tips WHERE payment_id = $1
+            UNION ALL
+            SELECT 'bump', id FROM thread_bumps WHERE payment_id = $1
+            LIMIT 1
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(Some(PaymentDetail {
+            payment,
+            paid_for: paid_for.map(|(kind, id)| PaidFor { kind, id }),
+        }))
+    }
+}
@@ -0,0 +1,625 @@
+//! In-process verification of x402 payment payloads
+//!
+//! Rebuilds the EIP-712 digest the client signed (EIP-2612 `Permit` or
+//! EIP-3009 `TransferWithAuthorization`), recovers the signer and checks the
+//! authorization against the requirements we advertised. Payloads that fail
+//! here are rejected without a round trip to the facilitator; payloads that
+//! pass are still verified by the facilitator, which also checks balances
+//! and on-chain nonces.
+
+use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+use primitive_types::U256;
+use sha3::{Digest, Keccak256};
+
+use crate::domain_types::network::chain_id;
+use crate::models::x402::PaymentScheme;
+
+const EIP712_DOMAIN_TYPE: &str =
+    "EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)";
+const PERMIT_TYPE: &str =
+    "Permit(address owner,address spender,uint256 value,uint256 nonce,uint256 deadline)";
+const TRANSFER_WITH_AUTHORIZATION_TYPE: &str = "TransferWithAuthorization(address from,address to,uint256 value,uint256 validAfter,uint256 validBefore,bytes32 nonce)";
+
+/// Reasons a payment is rejected locally
+///
+/// `code()` returns a stable identifier clients can match on.
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum VerificationError {
+    #[error("malformed payment payload: {0}")]
+    InvalidPayload(String),
+    #[error("unsupported network: {0}")]
+    UnsupportedNetwork(String),
+    #[error("permit spender {actual} is not the facilitator signer {expected}")]
+    InvalidSpender { expected: String, actual: String },
+    #[error("authorization pays {actual} instead of {expected}")]
+    InvalidPayTo { expected: String, actual: String },
+    #[error("authorization expired at {0}")]
+    Expired(u64),
+    #[error("authorization is not valid until {0}")]
+    NotYetValid(u64),
+    #[error("authorized value {actual} is below the required {required}")]
+    InsufficientAmount { required: String, actual: String },
+    #[error("signature could not be recovered")]
+    InvalidSignature,
+    #[error("signature was made by {recovered}, not {expected}")]
+    SignerMismatch { expected: String, recovered: String },
+}
+
+impl VerificationError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            VerificationError::InvalidPayload(_) => "invalid_payload",
+            VerificationError::UnsupportedNetwork(_) => "invalid_network",
+            VerificationError::InvalidSpender { .. } => "invalid_permit_spender",
+            VerificationError::InvalidPayTo { .. } => "invalid_pay_to",
+            VerificationError::Expired(_) => "authorization_expired",
+            VerificationError::NotYetValid(_) => "authorization_not_yet_valid",
+            VerificationError::InsufficientAmount { .. } => "insufficient_amount",
+            VerificationError::InvalidSignature => "invalid_signature",
+            VerificationError::SignerMismatch { .. } => "signer_mismatch",
+        }
+    }
+}
+
+/// The requirement fields the verifier checks against
+pub struct ExpectedPayment<'a> {
+    pub scheme: PaymentScheme,
+    pub network: &'a str,
+    pub asset: &'a str,
+    pub pay_to: &'a str,
+    pub amount: &'a str,
+    /// EIP-712 domain name and version of the token
+    pub token_name: &'a str,
+    pub token_version: &'a str,
+    /// Spender that permits must name (the facilitator signer)
+    pub facilitator_signer: &'a str,
+}
+
+pub struct PaymentVerifier;
+
+impl PaymentVerifier {
+    /// Verify a decoded payment payload, returning the payer address
+    pub fn verify(
+        payment_payload: &serde_json::Value,
+        expected: &ExpectedPayment,
+        now: u64,
+    ) -> Result<String, VerificationError> {
+        let payload = payment_payload
+            .get("payload")
+            .ok_or_else(|| VerificationError::InvalidPayload("missing payload".to_string()))?;
+        let authorization = payload.get("authorization").ok_or_else(|| {
+            VerificationError::InvalidPayload("missing payload.authorization".to_string())
+        })?;
+        let signature = payload.get("signature").ok_or_else(|| {
+            VerificationError::InvalidPayload("missing payload.signature".to_string())
+        })?;
+
+        let chain_id = chain_id(expected.network)
+            .ok_or_else(|| VerificationError::UnsupportedNetwork(expected.network.to_string()))?;
+        let asset = parse_address(expected.asset, "asset")?;
+        let required = parse_uint(
+            &serde_json::Value::String(expected.amount.to_string()),
+            "amount",
+        )?;
+
+        let domain_separator = domain_separator(
+            expected.token_name,
+            expected.token_version,
+            chain_id,
+            &asset,
+        );
+
+        let (signer, struct_hash) = match expected.scheme {
+            PaymentScheme::Permit => Self::check_permit(authorization, expected, required, now)?,
+            PaymentScheme::Exact => {
+                Self::check_transfer_authorization(authorization, expected, required, now)?
+            }
+        };
+
+        let digest = typed_data_digest(&domain_separator, &struct_hash);
+        let recovered = recover_signer(&digest, signature)?;
+
+        if recovered != signer {
+            return Err(VerificationError::SignerMismatch {
+                expected: format_address(&signer),
+                recovered: format_address(&recovered),
+            });
+        }
+
+        Ok(format_address(&signer))
+    }
+
+    /// EIP-2612 permit: owner grants the facilitator an allowance
+    ///
+    /// Accepts `owner`/`deadline` as well as the `from`/`validBefore` aliases
+    /// used by older clients. A missing spender is taken to be the
+    /// facilitator signer, as the facilitator itself would assume.
+    fn check_permit(
+        authorization: &serde_json::Value,
+        expected: &ExpectedPayment,
+        required: U256,
+        now: u64,
+    ) -> Result<([u8; 20], [u8; 32]), VerificationError> {
+        let owner = parse_address_field(authorization, &["owner", "from"])?;
+        let facilitator_signer = parse_address(expected.facilitator_signer, "facilitator signer")?;
+        let spender = match field(authorization, &["spender"]) {
+            Some(value) => parse_address(value_str(value, "spender")?, "spender")?,
+            None => facilitator_signer,
+        };
+        if spender != facilitator_signer {
+            return Err(VerificationError::InvalidSpender {
+                expected: format_address(&facilitator_signer),
+                actual: format_address(&spender),
+            });
+        }
+
+        // Permits carry no recipient, but older payloads include `to`
+        if let Some(to) = field(authorization, &["to"]) {
+            check_pay_to(value_str(to, "to")?, expected.pay_to)?;
+        }
+
+        let value = parse_uint_field(authorization, &["value"])?;
+        let nonce = parse_uint_field(authorization, &["nonce"])?;
+        let deadline = parse_uint_field(authorization, &["deadline", "validBefore"])?;
+
+        check_amount(value, required)?;
+        if deadline <= U256::from(now) {
+            return Err(VerificationError::Expired(deadline.low_u64()));
+        }
+
+        let struct_hash = keccak(&[
+            &keccak(&[PERMIT_TYPE.as_bytes()]),
+            &encode_address(&owner),
+            &encode_address(&spender),
+            &encode_uint(value),
+            &encode_uint(nonce),
+            &encode_uint(deadline),
+        ]);
+
+        Ok((owner, struct_hash))
+    }
+
+    /// EIP-3009 transferWithAuthorization: from signs a transfer straight to pay_to
+    fn check_transfer_authorization(
+        authorization: &serde_json::Value,
+        expected: &ExpectedPayment,
+        required: U256,
+        now: u64,
+    ) -> Result<([u8; 20], [u8; 32]), VerificationError> {
+        let from = parse_address_field(authorization, &["from"])?;
+        let to = parse_address_field(authorization, &["to"])?;
+        check_pay_to(&format_address(&to), expected.pay_to)?;
+
+        let value = parse_uint_field(authorization, &["value"])?;
+        let valid_after = parse_uint_field(authorization, &["validAfter"])?;
+        let valid_before = parse_uint_field(authorization, &["validBefore"])?;
+        let nonce = parse_bytes32_field(authorization, "nonce")?;
+
+        check_amount(value, required)?;
+        if valid_before <= U256::from(now) {
+            return Err(VerificationError::Expired(valid_before.low_u64()));
+        }
+        if valid_after > U256::from(now) {
+            return Err(VerificationError::NotYetValid(valid_after.low_u64()));
+        }
+
+        let struct_hash = keccak(&[
+            &keccak(&[TRANSFER_WITH_AUTHORIZATION_TYPE.as_bytes()]),
+            &encode_address(&from),
+            &encode_address(&to),
+            &encode_uint(value),
+            &encode_uint(valid_after),
+            &encode_uint(valid_before),
+            &nonce,
+        ]);
+
+        Ok((from, struct_hash))
+    }
+}
+
+fn keccak(parts: &[&[u8]]) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+/// EIP-712 domain separator for a token
+fn domain_separator(name: &str, version: &str, chain_id: u64, contract: &[u8; 20]) -> [u8; 32] {
+    keccak(&[
+        &keccak(&[EIP712_DOMAIN_TYPE.as_bytes()]),
+        &keccak(&[name.as_bytes()]),
+        &keccak(&[version.as_bytes()]),
+        &encode_uint(U256::from(chain_id)),
+        &encode_address(contract),
+    ])
+}
+
+/// Final EIP-712 digest: keccak256("\x19\x01" || domainSeparator || structHash)
+fn typed_data_digest(domain_separator: &[u8; 32], struct_hash: &[u8; 32]) -> [u8; 32] {
+    keccak(&[&[0x19, 0x01], domain_separator, struct_hash])
+}
+
+fn encode_uint(value: U256) -> [u8; 32] {
+    value.to_big_endian()
+}
+
+fn encode_address(address: &[u8; 20]) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[12..].copy_from_slice(address);
+    word
+}
+
+fn format_address(address: &[u8; 20]) -> String {
+    format!("0x{}", hex::encode(address))
+}
+
+fn field<'a>(value: &'a serde_json::Value, names: &[&str]) -> Option<&'a serde_json::Value> {
+    names.iter().find_map(|name| value.get(*name))
+}
+
+fn value_str<'a>(value: &'a serde_json::Value, name: &str) -> Result<&'a str, VerificationError> {
+    value
+        .as_str()
+        .ok_or_else(|| VerificationError::InvalidPayload(format!("{} must be a string", name)))
+}
+
+fn decode_hex(value: &str, name: &str) -> Result<Vec<u8>, VerificationError> {
+    let stripped = value.strip_prefix("0x").unwrap_or(value);
+    hex::decode(stripped)
+        .map_err(|_| VerificationError::InvalidPayload(format!("{} is not valid hex", name)))
+}
+
+fn parse_address(value: &str, name: &str) -> Result<[u8; 20], VerificationError> {
+    decode_hex(value, name)?.try_into().map_err(|_| {
+        VerificationError::InvalidPayload(format!("{} is not a 20-byte address", name))
+    })
+}
+
+fn parse_address_field(
+    authorization: &serde_json::Value,
+    names: &[&str],
+) -> Result<[u8; 20], VerificationError> {
+    let value = field(authorization, names)
+        .ok_or_else(|| VerificationError::InvalidPayload(format!("missing {}", names[0])))?;
+    parse_address(value_str(value, names[0])?, names[0])
+}
+
+fn parse_uint(value: &serde_json::Value, name: &str) -> Result<U256, VerificationError> {
+    let parsed = match value {
+        serde_json::Value::Number(n) => n.as_u64().map(U256::from),
+        serde_json::Value::String(s) if s.starts_with("0x") => {
+            U256::from_str_radix(&s[2..], 16).ok()
+        }
+        serde_json::Value::String(s) => U256::from_dec_str(s).ok(),
+        _ => None,
+    };
+    parsed.ok_or_else(|| VerificationError::InvalidPayload(format!("{} is not a uint256", name)))
+}
+
+fn parse_uint_field(
+    authorization: &serde_json::Value,
+    names: &[&str],
+) -> Result<U256, VerificationError> {
+    let value = field(authorization, names)
+        .ok_or_else(|| VerificationError::InvalidPayload(format!("missing {}", names[0])))?;
+    parse_uint(value, names[0])
+}
+
+fn parse_bytes32_field(
+    authorization: &serde_json::Value,
+    name: &str,
+) -> Result<[u8; 32], VerificationError> {
+    let value = authorization
+        .get(name)
+        .ok_or_else(|| VerificationError::InvalidPayload(format!("missing {}", name)))?;
+    decode_hex(value_str(value, name)?, name)?
+        .try_into()
+        .map_err(|_| VerificationError::InvalidPayload(format!("{} is not 32 bytes", name)))
+}
+
+fn check_pay_to(actual: &str, expected: &str) -> Result<(), VerificationError> {
+    if actual.eq_ignore_ascii_case(expected) {
+        Ok(())
+    } else {
+        Err(VerificationError::InvalidPayTo {
+            expected: expected.to_string(),
+            actual: actual.to_string(),
+        })
+    }
+}
+
+fn check_amount(value: U256, required: U256) -> Result<(), VerificationError> {
+    if value < required {
+        return Err(VerificationError::InsufficientAmount {
+            required: required.to_string(),
+            actual: value.to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// Split a signature into (r || s, v)
+///
+/// Accepts a 65-byte hex string or an `{ r, s, v }` object.
+fn parse_signature(signature: &serde_json::Value) -> Result<([u8; 64], u8), VerificationError> {
+    let invalid = || VerificationError::InvalidPayload("signature is malformed".to_string());
+
+    match signature {
+        serde_json::Value::String(s) => {
+            let bytes = decode_hex(s, "signature")?;
+            if bytes.len() != 65 {
+                return Err(invalid());
+            }
+            let mut rs = [0u8; 64];
+            rs.copy_from_slice(&bytes[..64]);
+            Ok((rs, bytes[64]))
+        }
+        serde_json::Value::Object(_) => {
+            let r = decode_hex(
+                value_str(signature.get("r").ok_or_else(invalid)?, "r")?,
+                "r",
+            )?;
+            let s = decode_hex(
+                value_str(signature.get("s").ok_or_else(invalid)?, "s")?,
+                "s",
+            )?;
+            if r.len() != 32 || s.len() != 32 {
+                return Err(invalid());
+            }
+            let v = parse_uint(signature.get("v").ok_or_else(invalid)?, "v")?;
+            if v > U256::from(u8::MAX) {
+                return Err(invalid());
+            }
+
+            let mut rs = [0u8; 64];
+            rs[..32].copy_from_slice(&r);
+            rs[32..].copy_from_slice(&s);
+            Ok((rs, v.low_u32() as u8))
+        }
+        _ => Err(invalid()),
+    }
+}
+
+/// Recover the Ethereum address that signed `digest`
+fn recover_signer(
+    digest: &[u8; 32],
+    signature: &serde_json::Value,
+) -> Result<[u8; 20], VerificationError> {
+    let (rs, v) = parse_signature(signature)?;
+
+    let parity = match v {
+        0 | 27 => 0,
+        1 | 28 => 1,
+        _ => return Err(VerificationError::InvalidSignature),
+    };
+    let mut signature =
+        Signature::from_slice(&rs).map_err(|_| VerificationError::InvalidSignature)?;
+    let mut recovery_id =
+        RecoveryId::from_byte(parity).ok_or(VerificationError::InvalidSignature)?;
+
+    // Normalizing a high-s signature flips the parity of the recovered point
+    if let Some(normalized) = signature.normalize_s() {
+        signature = normalized;
+        recovery_id = RecoveryId::new(!recovery_id.is_y_odd(), recovery_id.is_x_reduced());
+    }
+
+    let key = VerifyingKey::recover_from_prehash(digest, &signature, recovery_id)
+        .map_err(|_| VerificationError::InvalidSignature)?;
+
+    Ok(address_of(&key))
+}
+
+fn address_of(key: &VerifyingKey) -> [u8; 20] {
+    let point = key.to_encoded_point(false);
+    let hash = keccak(&[&point.as_bytes()[1..]]);
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&hash[12..]);
+    address
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k256::ecdsa::SigningKey;
+
+    // Well-known development key (Hardhat/Anvil account #0)
+    const PAYER_KEY: &str = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+    const PAYER: &str = "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266";
+    const FACILITATOR: &str = "0x7ed34056de24deed07c2b78712ae491f7072c981";
+    const PAY_TO: &str = "0x1111111111111111111111111111111111111111";
+    const TOKEN: &str = "0x833589fcd6edb6e08f4c7c32d4f71b54bda02913";
+    const NOW: u64 = 1_700_000_000;
+
+    fn expected(scheme: PaymentScheme) -> ExpectedPayment<'static> {
+        ExpectedPayment {
+            scheme,
+            network: "base",
+            asset: TOKEN,
+            pay_to: PAY_TO,
+            amount: "1000",
+            token_name: "USD Coin",
+            token_version: "2",
+            facilitator_signer: FACILITATOR,
+        }
+    }
+
+    fn sign(digest: &[u8; 32]) -> String {
+        let key = SigningKey::from_slice(&hex::decode(PAYER_KEY).unwrap()).unwrap();
+        let (signature, recovery_id) = key.sign_prehash_recoverable(digest).unwrap();
+        let mut bytes = signature.to_bytes().to_vec();
+        bytes.push(27 + recovery_id.to_byte());
+        format!("0x{}", hex::encode(bytes))
+    }
+
+    fn request(payload: serde_json::Value) -> serde_json::Value {
+        serde_json::json!({ "x402Version": 1, "scheme": "permit", "payload": payload })
+    }
+
+    fn permit_digest(owner: &str, spender: &str, value: u64, deadline: u64) -> [u8; 32] {
+        let struct_hash = keccak(&[
+            &keccak(&[PERMIT_TYPE.as_bytes()]),
+            &encode_address(&parse_address(owner, "owner").unwrap()),
+            &encode_address(&parse_address(spender, "spender").unwrap()),
+            &encode_uint(U256::from(value)),
+            &encode_uint(U256::zero()),
+            &encode_uint(U256::from(deadline)),
+        ]);
+        let domain = domain_separator(
+            "USD Coin",
+            "2",
+            8453,
+            &parse_address(TOKEN, "asset").unwrap(),
+        );
+        typed_data_digest(&domain, &struct_hash)
+    }
+
+    fn signed_permit(value: u64, deadline: u64) -> serde_json::Value {
+        let digest = permit_digest(PAYER, FACILITATOR, value, deadline);
+        serde_json::json!({
+            "signature": sign(&digest),
+            "authorization": {
+                "owner": PAYER,
+                "spender": FACILITATOR,
+                "value": value.to_string(),
+                "nonce": "0",
+                "deadline": deadline.to_string()
+            }
+        })
+    }
+
+    #[test]
+    fn test_eip712_domain_separator_vector() {
+        // "Ether Mail" domain from the EIP-712 specification
+        let contract =
+            parse_address("0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC", "contract").unwrap();
+        let separator = domain_separator("Ether Mail", "1", 1, &contract);
+        assert_eq!(
+            hex::encode(separator),
+            "f2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f"
+        );
+    }
+
+    #[test]
+    fn test_address_from_known_key() {
+        let key = SigningKey::from_slice(&hex::decode(PAYER_KEY).unwrap()).unwrap();
+        assert_eq!(format_address(&address_of(key.verifying_key())), PAYER);
+    }
+
+    #[test]
+    fn test_valid_permit_recovers_owner() {
+        let req = request(signed_permit(1000, NOW + 300));
+        let payer = PaymentVerifier::verify(&req, &expected(PaymentScheme::Permit), NOW).unwrap();
+        assert_eq!(payer, PAYER);
+    }
+
+    #[test]
+    fn test_permit_signature_as_rsv_object() {
+        let mut payload = signed_permit(1000, NOW + 300);
+        let bytes = decode_hex(payload["signature"].as_str().unwrap(), "signature").unwrap();
+        payload["signature"] = serde_json::json!({
+            "r": format!("0x{}", hex::encode(&bytes[..32])),
+            "s": format!("0x{}", hex::encode(&bytes[32..64])),
+            "v": bytes[64]
+        });
+
+        let req = request(payload);
+        assert!(PaymentVerifier::verify(&req, &expected(PaymentScheme::Permit), NOW).is_ok());
+    }
+
+    #[test]
+    fn test_permit_rejections() {
+        let expected = expected(PaymentScheme::Permit);
+
+        let expired = request(signed_permit(1000, NOW - 1));
+        assert_eq!(
+            PaymentVerifier::verify(&expired, &expected, NOW)
+                .unwrap_err()
+                .code(),
+            "authorization_expired"
+        );
+
+        let underpaid = request(signed_permit(999, NOW + 300));
+        assert_eq!(
+            PaymentVerifier::verify(&underpaid, &expected, NOW)
+                .unwrap_err()
+                .code(),
+            "insufficient_amount"
+        );
+
+        let mut wrong_spender = signed_permit(1000, NOW + 300);
+        wrong_spender["authorization"]["spender"] = serde_json::json!(PAY_TO);
+        assert_eq!(
+            PaymentVerifier::verify(&request(wrong_spender), &expected, NOW)
+                .unwrap_err()
+                .code(),
+            "invalid_permit_spender"
+        );
+
+        let mut wrong_owner = signed_permit(1000, NOW + 300);
+        wrong_owner["authorization"]["owner"] = serde_json::json!(PAY_TO);
+        assert_eq!(
+            PaymentVerifier::verify(&request(wrong_owner), &expected, NOW)
+                .unwrap_err()
+                .code(),
+            "signer_mismatch"
+        );
+
+        // Signed for a different value than the one claimed
+        let mut tampered = signed_permit(1000, NOW + 300);
+        tampered["authorization"]["value"] = serde_json::json!("5000");
+        assert_eq!(
+            PaymentVerifier::verify(&request(tampered), &expected, NOW)
+                .unwrap_err()
+                .code(),
+            "signer_mismatch"
+        );
+    }
+
+    #[test]
+    fn test_transfer_with_authorization() {
+        let nonce = [7u8; 32];
+        let struct_hash = keccak(&[
+            &keccak(&[TRANSFER_WITH_AUTHORIZATION_TYPE.as_bytes()]),
+            &encode_address(&parse_address(PAYER, "from").unwrap()),
+            &encode_address(&parse_address(PAY_TO, "to").unwrap()),
+            &encode_uint(U256::from(1000)),
+            &encode_uint(U256::zero()),
+            &encode_uint(U256::from(NOW + 300)),
+            &nonce,
+        ]);
+        let domain = domain_separator(
+            "USD Coin",
+            "2",
+            8453,
+            &parse_address(TOKEN, "asset").unwrap(),
+        );
+        let digest = typed_data_digest(&domain, &struct_hash);
+
+        let mut payload = serde_json::json!({
+            "signature": sign(&digest),
+            "authorization": {
+                "from": PAYER,
+                "to": PAY_TO,
+                "value": "1000",
+                "validAfter": "0",
+                "validBefore": (NOW + 300).to_string(),
+                "nonce": format!("0x{}", hex::encode(nonce))
+            }
+        });
+        let expected = expected(PaymentScheme::Exact);
+        assert_eq!(
+            PaymentVerifier::verify(&request(payload.clone()), &expected, NOW).unwrap(),
+            PAYER
+        );
+
+        payload["authorization"]["to"] = serde_json::json!(FACILITATOR);
+        assert_eq!(
+            PaymentVerifier::verify(&request(payload), &expected, NOW)
+                .unwrap_err()
+                .code(),
+            "invalid_pay_to"
+        );
+    }
+}
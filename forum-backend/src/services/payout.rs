@@ -0,0 +1,186 @@
+//! Outgoing transfers from the platform wallet
+//!
+//! Bounty awards, bounty refunds and payment refunds are recorded first. A
+//! `PayoutExecutor`, when configured, makes the transfer: `HttpPayoutExecutor`
+//! hands it to a payout backend, `FilePayoutExecutor` is a dry run that
+//! writes it to an outbound-transfer file. Without one they stay pending for
+//! an operator to send by hand.
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+const PAYOUT_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, thiserror::Error)]
+pub enum PayoutError {
+    #[error("failed to contact payout backend: {0}")]
+    Transport(String),
+    #[error("payout backend returned error: {status} - {body}")]
+    Status { status: u16, body: String },
+    #[error("failed to parse payout backend response: {0}")]
+    InvalidResponse(String),
+    #[error("failed to write outbound transfer file: {0}")]
+    File(String),
+}
+
+/// A transfer to make, in raw units of `asset`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PayoutRequest {
+    /// Payout record id, for the backend to deduplicate retries
+    pub reference: Uuid,
+    pub network: String,
+    pub asset: String,
+    pub pay_to: String,
+    pub amount: String,
+}
+
+/// What became of a payout handed to an executor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PayoutOutcome {
+    /// Transferred on-chain in this transaction
+    Sent(String),
+    /// Written out for an operator to transfer
+    Exported,
+}
+
+impl PayoutOutcome {
+    /// Status stored on the payout record
+    pub fn status(&self) -> &'static str {
+        match self {
+            Self::Sent(_) => "sent",
+            Self::Exported => "exported",
+        }
+    }
+
+    pub fn transaction(&self) -> Option<&str> {
+        match self {
+            Self::Sent(tx) => Some(tx),
+            Self::Exported => None,
+        }
+    }
+}
+
+/// Sends payouts from the platform wallet
+#[async_trait]
+pub trait PayoutExecutor: Send + Sync {
+    async fn send(&self, request: &PayoutRequest) -> Result<PayoutOutcome, PayoutError>;
+}
+
+#[derive(Deserialize)]
+struct PayoutResponse {
+    transaction: String,
+}
+
+/// Payout backend reached over HTTP: POSTs the request to PAYOUT_URL and
+/// expects `{"transaction": "0x..."}` back
+pub struct HttpPayoutExecutor {
+    url: String,
+    http_client: reqwest::Client,
+}
+
+impl HttpPayoutExecutor {
+    pub fn new(url: String, http_client: reqwest::Client) -> Self {
+        Self { url, http_client }
+    }
+}
+
+#[async_trait]
+impl PayoutExecutor for HttpPayoutExecutor {
+    async fn send(&self, request: &PayoutRequest) -> Result<PayoutOutcome, PayoutError> {
+        let response = self
+            .http_client
+            .post(&self.url)
+            .timeout(PAYOUT_TIMEOUT)
+            .json(request)
+            .send()
+            .await
+            .map_err(|e| PayoutError::Transport(e.to_string()))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(PayoutError::Status {
+                status: status.as_u16(),
+                body,
+            });
+        }
+
+        let payout: PayoutResponse = response
+            .json()
+            .await
+            .map_err(|e| PayoutError::InvalidResponse(e.to_string()))?;
+        Ok(PayoutOutcome::Sent(payout.transaction))
+    }
+}
+
+/// Dry-run executor: appends each payout as a JSON line to an outbound
+/// transfer file instead of sending it
+pub struct FilePayoutExecutor {
+    path: String,
+    lock: Mutex<()>,
+}
+
+impl FilePayoutExecutor {
+    pub fn new(path: String) -> Self {
+        Self {
+            path,
+            lock: Mutex::new(()),
+        }
+    }
+}
+
+#[async_trait]
+impl PayoutExecutor for FilePayoutExecutor {
+    async fn send(&self, request: &PayoutRequest) -> Result<PayoutOutcome, PayoutError> {
+        let mut line = serde_json::to_vec(request).map_err(|e| PayoutError::File(e.to_string()))?;
+        line.push(b'\n');
+
+        let _guard = self.lock.lock().await;
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await
+            .map_err(|e| PayoutError::File(e.to_string()))?;
+        file.write_all(&line)
+            .await
+            .map_err(|e| PayoutError::File(e.to_string()))?;
+        file.flush().await.map_err(|e| PayoutError::File(e.to_string()))?;
+
+        Ok(PayoutOutcome::Exported)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_file_executor_appends_json_lines() {
+        let path = std::env::temp_dir().join(format!("payouts-{}.jsonl", Uuid::new_v4()));
+        let executor = FilePayoutExecutor::new(path.to_string_lossy().into_owned());
+        let request = PayoutRequest {
+            reference: Uuid::new_v4(),
+            network: "base".to_string(),
+            asset: "0xasset".to_string(),
+            pay_to: "0xpayee".to_string(),
+            amount: "1000".to_string(),
+        };
+
+        assert_eq!(executor.send(&request).await.unwrap(), PayoutOutcome::Exported);
+        assert_eq!(executor.send(&request).await.unwrap(), PayoutOutcome::Exported);
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        let lines: Vec<serde_json::Value> =
+            written.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["payTo"], "0xpayee");
+        assert_eq!(lines[0]["reference"], request.reference.to_string());
+    }
+}
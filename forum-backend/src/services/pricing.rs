@@ -0,0 +1,197 @@
+//! Prices for paid actions
+//!
+//! Paid endpoints ask `PricingEngine` for their price before building the
+//! 402 challenge, so the quoted, verified and recorded amounts all agree.
+//! Prices are in canonical (PAYMENT_TOKEN_*) raw units.
+
+use chrono::{Timelike, Utc};
+use primitive_types::U256;
+use sqlx::PgPool;
+
+use crate::config::{div_ceil, Config, PricingRules};
+use crate::domain_types::DomainU256;
+use crate::models::Board;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PricedAction {
+    Registration,
+    Thread,
+    Reply,
+    Bump,
+}
+
+/// Everything a price depends on
+#[derive(Debug, Clone, Copy)]
+pub struct PriceContext<'a> {
+    pub action: PricedAction,
+    pub board: Option<&'a str>,
+    /// Characters of content being posted
+    pub content_length: usize,
+    /// Current UTC hour
+    pub hour: u32,
+    /// Threads and replies on the board within the activity surge window
+    pub recent_posts: i64,
+}
+
+pub struct PricingEngine;
+
+impl PricingEngine {
+    /// Current price of an action, looking up board activity when an
+    /// activity surge is configured
+    pub async fn quote(
+        pool: &PgPool,
+        config: &Config,
+        action: PricedAction,
+        board: Option<&Board>,
+        content_length: usize,
+    ) -> Result<DomainU256, sqlx::Error> {
+        let recent_posts = match (&config.pricing_rules.activity_surge, board) {
+            (Some(surge), Some(board)) if action != PricedAction::Registration => {
+                Self::recent_posts(pool, board.id, surge.window.as_secs_f64()).await?
+            }
+            _ => 0,
+        };
+
+        Ok(Self::price(
+            config,
+            &PriceContext {
+                action,
+                board: board.map(|b| b.slug.as_str()),
+                content_length,
+                hour: Utc::now().hour(),
+                recent_posts,
+            },
+        ))
+    }
+
+    /// Base price (board override or COST_PER_*), plus the length surcharge,
+    /// times any active surge multipliers, rounded up
+    ///
+    /// Registration is always `COST_PER_REGISTRATION`.
+    pub fn price(config: &Config, ctx: &PriceContext) -> DomainU256 {
+        let rules = &config.pricing_rules;
+        let board = ctx.board.and_then(|slug| rules.boards.get(slug));
+
+        let base = match ctx.action {
+            PricedAction::Registration => return config.cost_per_registration,
+            PricedAction::Thread => board.and_then(|b| b.thread).unwrap_or(config.cost_per_post),
+            PricedAction::Reply => board.and_then(|b| b.reply).unwrap_or(config.cost_per_reply),
+            PricedAction::Bump => board.and_then(|b| b.bump).unwrap_or(config.cost_per_bump),
+        };
+
+        apply_rules(rules, base, ctx)
+    }
+
+    /// Threads and replies posted on a board in the last `window_secs`
+    async fn recent_posts(pool: &PgPool, board_id: i32, window_secs: f64) -> Result<i64, sqlx::Error> {
+        let count: (i64,) = sqlx::query_as(
+            r#"
+            SELECT
+                (SELECT COUNT(*) FROM threads
+                 WHERE board_id = $1 AND hidden = false
+                   AND created_at > NOW() - make_interval(secs => $2))
+              + (SELECT COUNT(*) FROM replies r JOIN threads t ON t.id = r.thread_id
+                 WHERE t.board_id = $1 AND r.hidden = false
+                   AND r.created_at > NOW() - make_interval(secs => $2))
+            "#,
+        )
+        .bind(board_id)
+        .bind(window_secs)
+        .fetch_one(pool)
+        .await?;
+
+        Ok(count.0)
+    }
+}
+
+/// Add the length surcharge to a base price and apply active surges
+fn apply_rules(rules: &PricingRules, base: DomainU256, ctx: &PriceContext) -> DomainU256 {
+    let mut price = base.0;
+
+    if let Some(surcharge) = &rules.length_surcharge {
+        let extra = ctx.content_length.saturating_sub(surcharge.free_chars);
+        let units = extra.div_ceil(surcharge.per_chars);
+        price = price.saturating_add(surcharge.amount.0.saturating_mul(U256::from(units)));
+    }
+
+    let time_surge = rules
+        .time_surges
+        .iter()
+        .find(|s| in_hours(ctx.hour, s.from_hour, s.to_hour));
+    if let Some(surge) = time_surge {
+        price = apply_multiplier(price, surge.multiplier);
+    }
+
+    if let Some(surge) = &rules.activity_surge {
+        if ctx.recent_posts >= surge.threshold {
+            price = apply_multiplier(price, surge.multiplier);
+        }
+    }
+
+    DomainU256(price)
+}
+
+/// Whether `hour` falls in `from..to`, wrapping past midnight when from > to
+fn in_hours(hour: u32, from: u32, to: u32) -> bool {
+    if from <= to {
+        (from..to).contains(&hour)
+    } else {
+        hour >= from || hour < to
+    }
+}
+
+fn apply_multiplier(amount: U256, (numerator, denominator): (U256, U256)) -> U256 {
+    div_ceil(amount.saturating_mul(numerator), denominator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ActivitySurge, LengthSurcharge, TimeSurge};
+    use std::time::Duration;
+
+    fn ctx(content_length: usize, hour: u32, recent_posts: i64) -> PriceContext<'static> {
+        PriceContext {
+            action: PricedAction::Thread,
+            board: Some("research"),
+            content_length,
+            hour,
+            recent_posts,
+        }
+    }
+
+    #[test]
+    fn test_length_surcharge_and_surges() {
+        let rules = PricingRules {
+            length_surcharge: Some(LengthSurcharge {
+                free_chars: 100,
+                per_chars: 50,
+                amount: DomainU256::from(U256::from(10)),
+            }),
+            time_surges: vec![TimeSurge {
+                from_hour: 22,
+                to_hour: 2,
+                multiplier: (U256::from(15), U256::from(10)),
+            }],
+            activity_surge: Some(ActivitySurge {
+                window: Duration::from_secs(600),
+                threshold: 20,
+                multiplier: (U256::from(2), U256::one()),
+            }),
+            ..Default::default()
+        };
+        let base = DomainU256::from(U256::from(1000));
+        let price = |ctx: PriceContext| apply_rules(&rules, base, &ctx).to_string();
+
+        assert_eq!(price(ctx(100, 12, 0)), "1000");
+        // 101 chars starts a second block of 50 over the free 100
+        assert_eq!(price(ctx(101, 12, 0)), "1010");
+        assert_eq!(price(ctx(200, 12, 0)), "1020");
+        // Night surge wraps past midnight
+        assert_eq!(price(ctx(0, 23, 0)), "1500");
+        assert_eq!(price(ctx(0, 1, 0)), "1500");
+        assert_eq!(price(ctx(0, 2, 0)), "1000");
+        // Both surges stack
+        assert_eq!(price(ctx(0, 23, 20)), "3000");
+    }
+}
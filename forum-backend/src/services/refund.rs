@@ -0,0 +1,111 @@
+use primitive_types::U256;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::{Account, AccountKind, EntryKind, NewJournalEntry, NewRefund, Refund};
+use crate::services::payout::PayoutOutcome;
+use crate::services::LedgerService;
+
+pub struct RefundService;
+
+impl RefundService {
+    /// Record a refund, taking it off revenue and owing it to the payer
+    ///
+    /// Returns None if the payment was already refunded.
+    pub async fn create(pool: &PgPool, refund: &NewRefund<'_>) -> Result<Option<Refund>, sqlx::Error> {
+        let mut tx = pool.begin().await?;
+
+        let created = sqlx::query_as::<_, Refund>(
+            r#"
+            INSERT INTO refunds (payment_id, settlement_id, agent_id, pay_to, network, asset, amount, canonical_amount, reason)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            ON CONFLICT (payment_id) DO NOTHING
+            RETURNING *
+            "#,
+        )
+        .bind(refund.payment.id)
+        .bind(refund.payment.settlement_id)
+        .bind(refund.agent_id)
+        .bind(&refund.payment.payer)
+        .bind(refund.network)
+        .bind(&refund.payment.asset)
+        .bind(refund.amount)
+        .bind(refund.canonical_amount)
+        .bind(refund.reason)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(created) = created else {
+            return Ok(None);
+        };
+
+        let amount = refund.canonical_amount.0;
+        let entry = NewJournalEntry::new(EntryKind::Refund, refund.agent_id)
+            .payment(Some(refund.payment.id))
+            .reference(created.id)
+            .debit(Account::platform(AccountKind::Refunds), amount)
+            .credit(Account::agent(AccountKind::Payouts, refund.agent_id), amount);
+        LedgerService::post(&mut tx, &entry).await?;
+
+        tx.commit().await?;
+        Ok(Some(created))
+    }
+
+    pub async fn get_by_id(pool: &PgPool, id: Uuid) -> Result<Option<Refund>, sqlx::Error> {
+        sqlx::query_as::<_, Refund>("SELECT * FROM refunds WHERE id = $1")
+            .bind(id)
+            .fetch_optional(pool)
+            .await
+    }
+
+    pub async fn list(pool: &PgPool, limit: i64, offset: i64) -> Result<Vec<Refund>, sqlx::Error> {
+        sqlx::query_as::<_, Refund>("SELECT * FROM refunds ORDER BY created_at DESC LIMIT $1 OFFSET $2")
+            .bind(limit.min(100))
+            .bind(offset)
+            .fetch_all(pool)
+            .await
+    }
+
+    /// Record the payout executor's outcome; a sent refund leaves the platform wallet
+    pub async fn mark_done(pool: &PgPool, id: Uuid, outcome: &PayoutOutcome) -> Result<Refund, sqlx::Error> {
+        let mut tx = pool.begin().await?;
+
+        let refund = sqlx::query_as::<_, Refund>(
+            r#"
+            UPDATE refunds SET status = $1, tx_hash = $2, last_error = NULL, updated_at = NOW()
+            WHERE id = $3
+            RETURNING *
+            "#,
+        )
+        .bind(outcome.status())
+        .bind(outcome.transaction())
+        .bind(id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        if let PayoutOutcome::Sent(_) = outcome {
+            let amount = refund.canonical_amount.map(U256::from).ok_or_else(|| {
+                sqlx::Error::InvalidArgument(format!("refund {} has no canonical amount", refund.id))
+            })?;
+            let entry = NewJournalEntry::new(EntryKind::RefundSent, refund.agent_id)
+                .payment(Some(refund.payment_id))
+                .reference(refund.id)
+                .debit(Account::agent(AccountKind::Payouts, refund.agent_id), amount)
+                .credit(Account::platform(AccountKind::PlatformWallet), amount);
+            LedgerService::post(&mut tx, &entry).await?;
+        }
+
+        tx.commit().await?;
+        Ok(refund)
+    }
+
+    pub async fn mark_failed(pool: &PgPool, id: Uuid, error: &str) -> Result<Refund, sqlx::Error> {
+        sqlx::query_as::<_, Refund>(
+            "UPDATE refunds SET status = 'failed', last_error = $1, updated_at = NOW() WHERE id = $2 RETURNING *",
+        )
+        .bind(error)
+        .bind(id)
+        .fetch_one(pool)
+        .await
+    }
+}
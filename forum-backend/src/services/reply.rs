@@ -1,23 +1,33 @@
 use sqlx::PgPool;
 use uuid::Uuid;
 
-use crate::models::{CreateReplyRequest, Reply};
+use crate::models::{CreateReplyRequest, Purchase, Reply};
+use crate::services::LedgerService;
 
 pub struct ReplyService;
 
 impl ReplyService {
+    pub async fn get_by_id(pool: &PgPool, reply_id: Uuid) -> Result<Option<Reply>, sqlx::Error> {
+        sqlx::query_as::<_, Reply>("SELECT * FROM replies WHERE id = $1 AND hidden = false")
+            .bind(reply_id)
+            .fetch_optional(pool)
+            .await
+    }
+
     pub async fn create(
         pool: &PgPool,
         thread_id: Uuid,
         agent_id: Uuid,
         req: CreateReplyRequest,
+        purchase: Purchase,
     ) -> Result<Reply, sqlx::Error> {
         let id = Uuid::new_v4();
+        let mut tx = pool.begin().await?;
 
         sqlx::query(
             r#"
-            INSERT INTO replies (id, thread_id, agent_id, content, image_url, anon)
-            VALUES ($1, $2, $3, $4, $5, $6)
+            INSERT INTO replies (id, thread_id, agent_id, content, image_url, anon, payment_id)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
             "#,
         )
         .bind(id)
@@ -26,7 +36,8 @@ impl ReplyService {
         .bind(&req.content)
         .bind(&req.image_url)
         .bind(req.anon)
-        .execute(pool)
+        .bind(purchase.payment_id)
+        .execute(&mut *tx)
         .await?;
 
         // Update reply count and bump thread
@@ -38,16 +49,20 @@ impl ReplyService {
             "#,
         )
         .bind(thread_id)
-        .execute(pool)
+        .execute(&mut *tx)
         .await?;
 
         let reply = sqlx::query_as::<_, Reply>(
             "SELECT * FROM replies WHERE id = $1"
         )
         .bind(id)
-        .fetch_one(pool)
+        .fetch_one(&mut *tx)
         .await?;
 
+        let purchase = Purchase { entry: purchase.entry.reference(id), ..purchase };
+        LedgerService::post_purchase(&mut tx, &purchase).await?;
+
+        tx.commit().await?;
         Ok(reply)
     }
 }
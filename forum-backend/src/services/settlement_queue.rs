@@ -3,6 +3,9 @@
 //! This module provides a FIFO queue for pending settlements that allows
 //! the HTTP request to return immediately after payment verification,
 //! while settlement is processed asynchronously by a background worker.
+//!
+//! With batching enabled, pending settlements from the same payer and asset
+//! are claimed together and linked to a row in `settlement_batches`.
 
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
@@ -12,37 +15,23 @@ use tokio::sync::Notify;
 use tracing::{debug, info, warn};
 use uuid::Uuid;
 
-/// Status of a settlement
-#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type)]
-#[sqlx(type_name = "TEXT", rename_all = "snake_case")]
-pub enum SettlementStatus {
-    Pending,
-    InProgress,
-    Completed,
-    Failed,
-}
-
-impl SettlementStatus {
-    pub fn as_str(&self) -> &'static str {
-        match self {
-            SettlementStatus::Pending => "pending",
-            SettlementStatus::InProgress => "in_progress",
-            SettlementStatus::Completed => "completed",
-            SettlementStatus::Failed => "failed",
-        }
-    }
-}
+use crate::config::SettlementBatching;
+use crate::domain_types::DomainU256;
+use crate::models::x402::PaymentScheme;
 
 /// A pending settlement stored in the database
 #[derive(Debug, Clone)]
 pub struct StoredSettlement {
     pub id: Uuid,
     pub nonce: String,
+    pub scheme: String,
     pub verify_request_json: String,
     pub status: String,
     pub retry_count: i32,
     pub last_error: Option<String>,
     pub tx_hash: Option<String>,
+    /// Batch the settlement was last claimed in
+    pub batch_id: Option<Uuid>,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
 }
@@ -55,6 +44,41 @@ pub struct StoredVerifyRequest {
     pub payment_requirements: serde_json::Value,
 }
 
+/// Row returned when claiming a settlement
+type SettlementRow = (
+    Uuid,
+    String,
+    String,
+    String,
+    String,
+    i32,
+    Option<String>,
+    Option<String>,
+    Option<Uuid>,
+    chrono::DateTime<chrono::Utc>,
+    chrono::DateTime<chrono::Utc>,
+);
+
+impl From<SettlementRow> for StoredSettlement {
+    fn from(row: SettlementRow) -> Self {
+        let (id, nonce, scheme, verify_request_json, status, retry_count, last_error, tx_hash, batch_id, created_at, updated_at) =
+            row;
+        Self {
+            id,
+            nonce,
+            scheme,
+            verify_request_json,
+            status,
+            retry_count,
+            last_error,
+            tx_hash,
+            batch_id,
+            created_at,
+            updated_at,
+        }
+    }
+}
+
 /// FIFO queue for pending settlements backed by Postgres
 pub struct SettlementQueue {
     pool: PgPool,
@@ -84,6 +108,13 @@ impl SettlementQueue {
         .execute(&pool)
         .await?;
 
+        // Scheme column added after the table was introduced
+        sqlx::query(
+            "ALTER TABLE settlements ADD COLUMN IF NOT EXISTS scheme TEXT NOT NULL DEFAULT 'permit'",
+        )
+        .execute(&pool)
+        .await?;
+
         // Create index on status
         sqlx::query(
             "CREATE INDEX IF NOT EXISTS idx_settlements_status ON settlements(status)",
@@ -91,6 +122,47 @@ impl SettlementQueue {
         .execute(&pool)
         .await?;
 
+        // Batching: who pays what, and the batch each settlement went out in
+        for column in ["payer TEXT", "asset TEXT", "amount NUMERIC(78, 0)", "batch_id UUID"] {
+            sqlx::query(&format!(
+                "ALTER TABLE settlements ADD COLUMN IF NOT EXISTS {}",
+                column
+            ))
+            .execute(&pool)
+            .await?;
+        }
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS settlement_batches (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                payer TEXT NOT NULL,
+                asset TEXT NOT NULL,
+                item_count INTEGER NOT NULL,
+                total_amount NUMERIC(78, 0) NOT NULL,
+                status TEXT NOT NULL DEFAULT 'in_progress',
+                tx_hash TEXT,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_settlements_batch_id ON settlements(batch_id)",
+        )
+        .execute(&pool)
+        .await?;
+
+        // Batches cut short by a restart; their settlements are re-batched
+        sqlx::query(
+            "UPDATE settlement_batches SET status = 'requeued', updated_at = NOW() WHERE status = 'in_progress'",
+        )
+        .execute(&pool)
+        .await?;
+
         // Recover any in_progress settlements from previous run
         let recovered = sqlx::query(
             "UPDATE settlements SET status = 'pending', updated_at = NOW() WHERE status = 'in_progress'",
@@ -120,35 +192,70 @@ impl SettlementQueue {
     }
 
     /// Push a settlement to the queue
-    pub async fn push(&self, nonce: &str, verify_request: &StoredVerifyRequest) -> Result<bool, sqlx::Error> {
+    ///
+    /// `amount` is in the paid asset's raw units.
+    /// Returns the settlement id, or None if the nonce is already queued
+    pub async fn push(
+        &self,
+        nonce: &str,
+        scheme: PaymentScheme,
+        payer: &str,
+        asset: &str,
+        amount: DomainU256,
+        verify_request: &StoredVerifyRequest,
+    ) -> Result<Option<Uuid>, sqlx::Error> {
         let json = serde_json::to_string(verify_request).unwrap();
 
-        let result = sqlx::query(
+        let id: Option<(Uuid,)> = sqlx::query_as(
             r#"
-            INSERT INTO settlements (nonce, verify_request_json, status)
-            VALUES ($1, $2, 'pending')
+            INSERT INTO settlements (nonce, scheme, payer, asset, amount, verify_request_json, status)
+            VALUES ($1, $2, $3, $4, $5, $6, 'pending')
             ON CONFLICT (nonce) DO NOTHING
+            RETURNING id
             "#,
         )
         .bind(nonce)
+        .bind(scheme.as_str())
+        .bind(payer.to_lowercase())
+        .bind(asset.to_lowercase())
+        .bind(amount)
         .bind(&json)
-        .execute(&self.pool)
+        .fetch_optional(&self.pool)
         .await?;
 
-        if result.rows_affected() > 0 {
-            self.len.fetch_add(1, Ordering::SeqCst);
-            debug!("Queued settlement for nonce {}", nonce);
-            self.notify.notify_one();
-            Ok(true)
-        } else {
-            debug!("Settlement for nonce {} already exists", nonce);
-            Ok(false)
+        match id {
+            Some((id,)) => {
+                self.len.fetch_add(1, Ordering::SeqCst);
+                debug!("Queued settlement for nonce {}", nonce);
+                self.notify.notify_one();
+                Ok(Some(id))
+            }
+            None => {
+                debug!("Settlement for nonce {} already exists", nonce);
+                Ok(None)
+            }
         }
     }
 
+    /// Get a settlement by id
+    pub async fn get(&self, id: Uuid) -> Result<Option<StoredSettlement>, sqlx::Error> {
+        let row = sqlx::query_as::<_, SettlementRow>(
+            r#"
+            SELECT id, nonce, scheme, verify_request_json, status, retry_count, last_error, tx_hash, batch_id, created_at, updated_at
+            FROM settlements
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(StoredSettlement::from))
+    }
+
     /// Claim the next pending settlement (FIFO)
     pub async fn claim_next(&self) -> Result<Option<StoredSettlement>, sqlx::Error> {
-        let result: Option<StoredSettlement> = sqlx::query_as::<_, (Uuid, String, String, String, i32, Option<String>, Option<String>, chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>)>(
+        let result: Option<StoredSettlement> = sqlx::query_as::<_, SettlementRow>(
             r#"
             UPDATE settlements
             SET status = 'in_progress', updated_at = NOW()
@@ -159,24 +266,12 @@ impl SettlementQueue {
                 LIMIT 1
                 FOR UPDATE SKIP LOCKED
             )
-            RETURNING id, nonce, verify_request_json, status, retry_count, last_error, tx_hash, created_at, updated_at
+            RETURNING id, nonce, scheme, verify_request_json, status, retry_count, last_error, tx_hash, batch_id, created_at, updated_at
             "#,
         )
         .fetch_optional(&self.pool)
         .await?
-        .map(|(id, nonce, verify_request_json, status, retry_count, last_error, tx_hash, created_at, updated_at)| {
-            StoredSettlement {
-                id,
-                nonce,
-                verify_request_json,
-                status,
-                retry_count,
-                last_error,
-                tx_hash,
-                created_at,
-                updated_at,
-            }
-        });
+        .map(StoredSettlement::from);
 
         if result.is_some() {
             self.len.fetch_sub(1, Ordering::SeqCst);
@@ -185,6 +280,146 @@ impl SettlementQueue {
         Ok(result)
     }
 
+    /// Claim the oldest batch that is ready to settle
+    ///
+    /// Pending settlements are grouped by payer and asset. A group is ready
+    /// once its oldest settlement has waited the batching window, or when it
+    /// has reached the item or amount limit. `max_amounts` is the amount
+    /// limit in each asset's raw units (`Config::batch_max_amounts`); assets
+    /// without one are only flushed by the window and item limit. Returns the
+    /// new batch id and its settlements, oldest first.
+    pub async fn claim_batch(
+        &self,
+        batching: &SettlementBatching,
+        max_amounts: &[(String, DomainU256)],
+    ) -> Result<Option<(Uuid, Vec<StoredSettlement>)>, sqlx::Error> {
+        let (limit_assets, limit_amounts): (Vec<String>, Vec<String>) = max_amounts
+            .iter()
+            .map(|(asset, amount)| (asset.clone(), amount.to_string()))
+            .unzip();
+
+        let mut tx = self.pool.begin().await?;
+
+        let group: Option<(String, String)> = sqlx::query_as(
+            r#"
+            SELECT COALESCE(s.payer, ''), COALESCE(s.asset, '')
+            FROM settlements s
+            LEFT JOIN unnest($3::text[], $4::text[]) AS l(asset, max_amount) ON l.asset = s.asset
+            WHERE s.status = 'pending'
+            GROUP BY COALESCE(s.payer, ''), COALESCE(s.asset, '')
+            HAVING MIN(s.created_at) <= NOW() - make_interval(secs => $1)
+                OR COUNT(*) >= $2
+                OR SUM(COALESCE(s.amount, 0)) >= MIN(l.max_amount::numeric)
+            ORDER BY MIN(s.created_at) ASC
+            LIMIT 1
+            "#,
+        )
+        .bind(batching.window.as_secs_f64())
+        .bind(batching.max_items)
+        .bind(&limit_assets)
+        .bind(&limit_amounts)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some((payer, asset)) = group else {
+            return Ok(None);
+        };
+
+        let (batch_id,): (Uuid,) = sqlx::query_as(
+            "INSERT INTO settlement_batches (payer, asset, item_count, total_amount) VALUES ($1, $2, 0, 0) RETURNING id",
+        )
+        .bind(&payer)
+        .bind(&asset)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let mut settlements: Vec<StoredSettlement> = sqlx::query_as::<_, SettlementRow>(
+            r#"
+            UPDATE settlements
+            SET status = 'in_progress', batch_id = $1, updated_at = NOW()
+            WHERE id IN (
+                SELECT id FROM settlements
+                WHERE status = 'pending' AND COALESCE(payer, '') = $2 AND COALESCE(asset, '') = $3
+                ORDER BY created_at ASC
+                LIMIT $4
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING id, nonce, scheme, verify_request_json, status, retry_count, last_error, tx_hash, batch_id, created_at, updated_at
+            "#,
+        )
+        .bind(batch_id)
+        .bind(&payer)
+        .bind(&asset)
+        .bind(batching.max_items)
+        .fetch_all(&mut *tx)
+        .await?
+        .into_iter()
+        .map(StoredSettlement::from)
+        .collect();
+
+        if settlements.is_empty() {
+            // Another worker took the group first
+            tx.rollback().await?;
+            return Ok(None);
+        }
+
+        sqlx::query(
+            r#"
+            UPDATE settlement_batches
+            SET item_count = $2,
+                total_amount = (SELECT COALESCE(SUM(amount), 0) FROM settlements WHERE batch_id = $1)
+            WHERE id = $1
+            "#,
+        )
+        .bind(batch_id)
+        .bind(settlements.len() as i32)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        settlements.sort_by_key(|s| s.created_at);
+        self.len.fetch_sub(settlements.len(), Ordering::SeqCst);
+        debug!("Claimed batch {} of {} settlements for {}", batch_id, settlements.len(), payer);
+        Ok(Some((batch_id, settlements)))
+    }
+
+    /// Record how a batch ended: completed, partial or failed
+    pub async fn finish_batch(
+        &self,
+        batch_id: Uuid,
+        status: &str,
+        tx_hash: Option<&str>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "UPDATE settlement_batches SET status = $1, tx_hash = $2, updated_at = NOW() WHERE id = $3",
+        )
+        .bind(status)
+        .bind(tx_hash)
+        .bind(batch_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Put a batch's unfinished settlements back in the queue
+    pub async fn requeue_batch(&self, batch_id: Uuid) -> Result<(), sqlx::Error> {
+        let requeued = sqlx::query(
+            r#"
+            UPDATE settlements SET status = 'pending', updated_at = NOW()
+            WHERE batch_id = $1 AND status = 'in_progress'
+            "#,
+        )
+        .bind(batch_id)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+
+        self.finish_batch(batch_id, "requeued", None).await?;
+        self.len.fetch_add(requeued as usize, Ordering::SeqCst);
+        Ok(())
+    }
+
     /// Mark settlement as completed
     pub async fn mark_completed(&self, id: Uuid, tx_hash: &str) -> Result<(), sqlx::Error> {
         sqlx::query(
@@ -213,6 +448,28 @@ impl SettlementQueue {
         Ok(())
     }
 
+    /// Withdraw a settlement whose paid action failed
+    ///
+    /// Only settlements still waiting in the queue can be withdrawn; returns
+    /// false if a worker already picked it up.
+    pub async fn cancel(&self, id: Uuid, reason: &str) -> Result<bool, sqlx::Error> {
+        let cancelled = sqlx::query(
+            "UPDATE settlements SET status = 'failed', last_error = $1, updated_at = NOW() WHERE id = $2 AND status = 'pending'",
+        )
+        .bind(reason)
+        .bind(id)
+        .execute(&self.pool)
+        .await?
+        .rows_affected()
+            > 0;
+
+        if cancelled {
+            self.len.fetch_sub(1, Ordering::SeqCst);
+            debug!("Cancelled settlement {}: {}", id, reason);
+        }
+        Ok(cancelled)
+    }
+
     /// Record a retry attempt
     pub async fn record_retry(&self, id: Uuid, error: &str) -> Result<(), sqlx::Error> {
         sqlx::query(
@@ -248,3 +505,106 @@ impl SettlementQueue {
         &self.pool
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use primitive_types::U256;
+    use std::time::Duration;
+
+    async fn push(queue: &SettlementQueue, payer: &str, asset: &str, amount: u64) -> Uuid {
+        let request = StoredVerifyRequest {
+            x402_version: 2,
+            payment_payload: serde_json::json!({}),
+            payment_requirements: serde_json::json!({}),
+        };
+        queue
+            .push(&Uuid::new_v4().to_string(), PaymentScheme::Permit, payer, asset, DomainU256(U256::from(amount)), &request)
+            .await
+            .unwrap()
+            .unwrap()
+    }
+
+    #[sqlx::test(migrations = "../migrations")]
+    async fn test_parallel_batch_claims_are_disjoint(pool: PgPool) {
+        let queue = SettlementQueue::new(pool).await.unwrap();
+        let mut queued = Vec::new();
+        for _ in 0..4 {
+            queued.push(push(&queue, "0xA", "0xtoken", 100).await);
+        }
+        push(&queue, "0xB", "0xtoken", 100).await;
+
+        // Groups fill up at two items; a lone payment waits out the window
+        let batching = SettlementBatching {
+            window: Duration::from_secs(3600),
+            max_items: 2,
+            max_amount: None,
+        };
+        let (first, second) = tokio::join!(queue.claim_batch(&batching, &[]), queue.claim_batch(&batching, &[]));
+        let mut claimed = Vec::new();
+        for (batch_id, settlements) in [first.unwrap().unwrap(), second.unwrap().unwrap()] {
+            assert_eq!(settlements.len(), 2);
+            assert!(settlements.iter().all(|s| s.batch_id == Some(batch_id) && s.status == "in_progress"));
+            let (total,): (DomainU256,) = sqlx::query_as("SELECT total_amount FROM settlement_batches WHERE id = $1")
+                .bind(batch_id)
+                .fetch_one(queue.pool())
+                .await
+                .unwrap();
+            assert_eq!(total, DomainU256(U256::from(200)));
+            claimed.extend(settlements.into_iter().map(|s| s.id));
+        }
+        claimed.sort();
+        queued.sort();
+        assert_eq!(claimed, queued);
+
+        assert!(queue.claim_batch(&batching, &[]).await.unwrap().is_none());
+        assert_eq!(queue.len(), 1);
+    }
+
+    #[sqlx::test(migrations = "../migrations")]
+    async fn test_batch_amount_limit_is_per_asset_units(pool: PgPool) {
+        // A 1:1 stablecoin with 6 decimals next to the 18-decimal canonical token
+        let mut config = Config::for_tests();
+        let canonical = config.canonical_asset().clone();
+        let mut usdc = canonical.clone();
+        usdc.address = "0x00000000000000000000000000000000000000C6".to_string();
+        usdc.decimals = 6;
+        config.payment_assets.push(usdc.clone());
+        let batching = SettlementBatching {
+            window: Duration::from_secs(3600),
+            max_items: 50,
+            // One whole token
+            max_amount: Some(DomainU256(U256::exp10(18))),
+        };
+        config.settlement_batching = Some(batching);
+        let max_amounts = config.batch_max_amounts();
+
+        let queue = SettlementQueue::new(pool).await.unwrap();
+        // 0.8 canonical tokens: below the limit
+        for _ in 0..2 {
+            push(&queue, "0xA", &canonical.address, 400_000_000_000_000_000).await;
+        }
+        // 1.2 six-decimal tokens: over the limit, though far below 10^18 raw units
+        for _ in 0..2 {
+            push(&queue, "0xB", &usdc.address, 600_000).await;
+        }
+
+        let (batch_id, settlements) = queue.claim_batch(&batching, &max_amounts).await.unwrap().unwrap();
+        assert_eq!(settlements.len(), 2);
+        let (asset, total): (String, DomainU256) =
+            sqlx::query_as("SELECT asset, total_amount FROM settlement_batches WHERE id = $1")
+                .bind(batch_id)
+                .fetch_one(queue.pool())
+                .await
+                .unwrap();
+        assert_eq!(asset, usdc.address.to_lowercase());
+        assert_eq!(total, DomainU256(U256::from(1_200_000)));
+        assert!(queue.claim_batch(&batching, &max_amounts).await.unwrap().is_none());
+
+        // Another 0.4 canonical tokens reaches the limit
+        push(&queue, "0xA", &canonical.address, 400_000_000_000_000_000).await;
+        let (_, settlements) = queue.claim_batch(&batching, &max_amounts).await.unwrap().unwrap();
+        assert_eq!(settlements.len(), 3);
+    }
+}
@@ -1,6 +1,12 @@
 //! Background worker for processing settlement queue
 
-use super::settlement_queue::{SettlementQueue, StoredVerifyRequest};
+use super::credit::CreditService;
+use super::debt::DebtService;
+use super::facilitator::Facilitator;
+use super::payment::PaymentService;
+use super::settlement_queue::{SettlementQueue, StoredSettlement, StoredVerifyRequest};
+use crate::config::{Config, SettlementBatching};
+use crate::models::x402::VerifyRequest;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::broadcast;
@@ -9,28 +15,45 @@ use tracing::{debug, error, info, warn};
 /// Maximum retry attempts for a single settlement
 const MAX_RETRIES: i32 = 5;
 
+/// How often batching mode checks for batches that became ready
+const BATCH_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A settlement in a batch that is still being settled
+struct BatchItem {
+    id: uuid::Uuid,
+    attempts: i32,
+    request: VerifyRequest,
+}
+
 /// Background worker that processes settlements from the queue
 pub struct SettlementWorker {
     queue: Arc<SettlementQueue>,
-    facilitator_url: String,
-    http_client: reqwest::Client,
+    facilitator: Arc<dyn Facilitator>,
+    config: Config,
 }
 
 impl SettlementWorker {
-    pub fn new(
-        queue: Arc<SettlementQueue>,
-        facilitator_url: String,
-        http_client: reqwest::Client,
-    ) -> Self {
+    pub fn new(queue: Arc<SettlementQueue>, facilitator: Arc<dyn Facilitator>, config: Config) -> Self {
         Self {
             queue,
-            facilitator_url,
-            http_client,
+            facilitator,
+            config,
         }
     }
 
     /// Run the worker until shutdown signal
     pub async fn run(&self, mut shutdown: broadcast::Receiver<()>) {
+        // Top-ups whose payment settled before a restart
+        self.resolve_top_ups().await;
+
+        if let Some(batching) = self.config.settlement_batching {
+            // Grouping only saves gas if the facilitator submits a batch as one transaction
+            if self.facilitator.supports_batch() {
+                return self.run_batched(batching, shutdown).await;
+            }
+            warn!("Settlement batching needs a batch-capable facilitator (FACILITATOR_BATCH_SETTLE); settling one by one");
+        }
+
         info!("Settlement worker started");
 
         loop {
@@ -85,22 +108,96 @@ impl SettlementWorker {
         info!("Settlement worker stopped");
     }
 
-    async fn process_settlement(&self, settlement: super::settlement_queue::StoredSettlement) {
+    /// Run in batching mode, settling ready batches until shutdown
+    async fn run_batched(&self, batching: SettlementBatching, mut shutdown: broadcast::Receiver<()>) {
+        info!("Settlement worker started (batching)");
+
+        let max_amounts = self.config.batch_max_amounts();
+
+        loop {
+            let batch = match self.queue.claim_batch(&batching, &max_amounts).await {
+                Ok(batch) => batch,
+                Err(e) => {
+                    error!("Failed to claim settlement batch: {}", e);
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    continue;
+                }
+            };
+
+            if let Some((batch_id, settlements)) = batch {
+                tokio::select! {
+                    biased;
+
+                    _ = shutdown.recv() => {
+                        // Put back whatever has not settled yet
+                        if let Err(e) = self.queue.requeue_batch(batch_id).await {
+                            error!("Failed to re-queue batch on shutdown: {}", e);
+                        }
+                        info!("Settlement worker shutting down");
+                        break;
+                    }
+
+                    _ = self.process_batch(batch_id, settlements) => {
+                        debug!("Processed settlement batch {}", batch_id);
+                    }
+                }
+            } else {
+                // Nothing ready yet; batches become ready as their window passes
+                tokio::select! {
+                    biased;
+
+                    _ = shutdown.recv() => {
+                        info!("Settlement worker received shutdown");
+                        break;
+                    }
+
+                    _ = self.queue.wait_for_items() => {}
+
+                    _ = tokio::time::sleep(BATCH_POLL_INTERVAL) => {}
+                }
+            }
+        }
+
+        info!("Settlement worker stopped");
+    }
+
+    /// Parse the stored request into a settle request (same format as verify)
+    ///
+    /// Unparseable settlements are marked failed.
+    async fn settle_request(&self, settlement: &StoredSettlement) -> Option<VerifyRequest> {
         let id = settlement.id;
-        let nonce = &settlement.nonce;
 
-        // Parse the stored request
-        let verify_request: StoredVerifyRequest = match serde_json::from_str(&settlement.verify_request_json) {
-            Ok(r) => r,
+        let parsed = serde_json::from_str::<StoredVerifyRequest>(&settlement.verify_request_json)
+            .map_err(|e| format!("Failed to parse verify request: {}", e))
+            .and_then(|stored| {
+                serde_json::from_value(stored.payment_requirements)
+                    .map(|payment_requirements| VerifyRequest {
+                        x402_version: stored.x402_version,
+                        payment_payload: stored.payment_payload,
+                        payment_requirements,
+                    })
+                    .map_err(|e| format!("Failed to parse payment requirements: {}", e))
+            });
+
+        match parsed {
+            Ok(request) => Some(request),
             Err(e) => {
-                error!("Failed to parse verify request for {}: {}", id, e);
+                error!("{} for {}", e, id);
                 let _ = self.queue.mark_failed(id, &format!("Parse error: {}", e)).await;
-                return;
+                self.handle_failure(id).await;
+                None
             }
-        };
+        }
+    }
 
-        // Build settle request (same format as verify)
-        let settle_url = format!("{}/settle", self.facilitator_url);
+    async fn process_settlement(&self, settlement: StoredSettlement) {
+        let id = settlement.id;
+        let nonce = &settlement.nonce;
+        let scheme = &settlement.scheme;
+
+        let Some(settle_request) = self.settle_request(&settlement).await else {
+            return;
+        };
 
         let mut attempts = settlement.retry_count;
         let mut backoff = Duration::from_secs(2);
@@ -108,56 +205,27 @@ impl SettlementWorker {
         loop {
             attempts += 1;
 
-            let result = self
-                .http_client
-                .post(&settle_url)
-                .json(&serde_json::json!({
-                    "x402Version": verify_request.x402_version,
-                    "paymentPayload": verify_request.payment_payload,
-                    "paymentRequirements": verify_request.payment_requirements,
-                }))
-                .timeout(Duration::from_secs(60))
-                .send()
-                .await;
-
-            match result {
+            match self.facilitator.settle(&settle_request).await {
                 Ok(response) => {
-                    if response.status().is_success() {
-                        match response.json::<serde_json::Value>().await {
-                            Ok(json) => {
-                                let success = json.get("success").and_then(|v| v.as_bool()).unwrap_or(false);
-                                if success {
-                                    let tx_hash = json
-                                        .get("transaction")
-                                        .and_then(|v| v.as_str())
-                                        .unwrap_or("unknown");
-                                    info!("Settlement succeeded for nonce {}: tx {}", nonce, tx_hash);
-                                    let _ = self.queue.mark_completed(id, tx_hash).await;
-                                    return;
-                                } else {
-                                    let error = json
-                                        .get("errorReason")
-                                        .and_then(|v| v.as_str())
-                                        .unwrap_or("Unknown error");
-
-                                    if attempts >= MAX_RETRIES {
-                                        error!("Settlement failed for nonce {} after {} attempts: {}", nonce, attempts, error);
-                                        let _ = self.queue.mark_failed(id, error).await;
-                                        return;
-                                    }
-
-                                    warn!("Settlement attempt {} failed for nonce {}: {}", attempts, nonce, error);
-                                }
-                            }
-                            Err(e) => {
-                                warn!("Failed to parse settle response: {}", e);
-                            }
-                        }
-                    } else {
-                        let status = response.status();
-                        let body = response.text().await.unwrap_or_default();
-                        warn!("Settlement HTTP error for nonce {}: {} - {}", nonce, status, body);
+                    if response.success {
+                        let tx_hash = response.transaction.as_deref().unwrap_or("unknown");
+                        info!("Settlement succeeded for {} nonce {}: tx {}", scheme, nonce, tx_hash);
+                        let _ = self.queue.mark_completed(id, tx_hash).await;
+                        self.update_payment(id, "settled", Some(tx_hash)).await;
+                        self.resolve_top_ups().await;
+                        return;
                     }
+
+                    let error = response.error_reason.as_deref().unwrap_or("Unknown error");
+
+                    if attempts >= MAX_RETRIES {
+                        error!("Settlement failed for {} nonce {} after {} attempts: {}", scheme, nonce, attempts, error);
+                        let _ = self.queue.mark_failed(id, error).await;
+                        self.handle_failure(id).await;
+                        return;
+                    }
+
+                    warn!("Settlement attempt {} failed for nonce {}: {}", attempts, nonce, error);
                 }
                 Err(e) => {
                     warn!("Settlement request failed for nonce {}: {}", nonce, e);
@@ -168,6 +236,7 @@ impl SettlementWorker {
             if attempts >= MAX_RETRIES {
                 error!("Settlement exhausted retries for nonce {}", nonce);
                 let _ = self.queue.mark_failed(id, "Max retries exceeded").await;
+                self.handle_failure(id).await;
                 return;
             }
 
@@ -175,4 +244,107 @@ impl SettlementWorker {
             backoff = std::cmp::min(backoff * 2, Duration::from_secs(60));
         }
     }
+
+    /// Settle a batch with one facilitator call per round, retrying the
+    /// payments that failed with backoff
+    async fn process_batch(&self, batch_id: uuid::Uuid, settlements: Vec<StoredSettlement>) {
+        let mut pending = Vec::with_capacity(settlements.len());
+        for settlement in &settlements {
+            if let Some(request) = self.settle_request(settlement).await {
+                pending.push(BatchItem {
+                    id: settlement.id,
+                    attempts: settlement.retry_count,
+                    request,
+                });
+            }
+        }
+
+        let mut settled = 0;
+        let mut failed = settlements.len() - pending.len();
+        let mut tx_hash: Option<String> = None;
+        let mut backoff = Duration::from_secs(2);
+
+        while !pending.is_empty() {
+            let requests: Vec<VerifyRequest> = pending.iter().map(|item| item.request.clone()).collect();
+            let responses = self.facilitator.settle_batch(&requests).await;
+            if let Err(e) = &responses {
+                warn!("Batch settlement request failed for batch {}: {}", batch_id, e);
+            }
+
+            let mut retry = Vec::new();
+            for (i, mut item) in pending.into_iter().enumerate() {
+                item.attempts += 1;
+
+                let error = match responses.as_ref().map(|r| r.get(i)) {
+                    Ok(Some(response)) if response.success => {
+                        let tx = response.transaction.as_deref().unwrap_or("unknown");
+                        let _ = self.queue.mark_completed(item.id, tx).await;
+                        self.update_payment(item.id, "settled", Some(tx)).await;
+                        tx_hash.get_or_insert_with(|| tx.to_string());
+                        settled += 1;
+                        continue;
+                    }
+                    Ok(Some(response)) => response.error_reason.clone().unwrap_or_else(|| "Unknown error".to_string()),
+                    Ok(None) => "Missing batch result".to_string(),
+                    Err(e) => e.to_string(),
+                };
+
+                if item.attempts >= MAX_RETRIES {
+                    error!("Settlement {} in batch {} failed after {} attempts: {}", item.id, batch_id, item.attempts, error);
+                    let _ = self.queue.mark_failed(item.id, &error).await;
+                    self.handle_failure(item.id).await;
+                    failed += 1;
+                } else {
+                    retry.push(item);
+                }
+            }
+
+            pending = retry;
+            if !pending.is_empty() {
+                tokio::time::sleep(backoff).await;
+                backoff = std::cmp::min(backoff * 2, Duration::from_secs(60));
+            }
+        }
+
+        let status = match (settled, failed) {
+            (_, 0) => "completed",
+            (0, _) => "failed",
+            _ => "partial",
+        };
+        info!("Batch {} {}: {} settled, {} failed, tx {:?}", batch_id, status, settled, failed, tx_hash);
+        self.resolve_top_ups().await;
+        if let Err(e) = self.queue.finish_batch(batch_id, status, tx_hash.as_deref()).await {
+            error!("Failed to record outcome of batch {}: {}", batch_id, e);
+        }
+    }
+
+    /// Mirror the settlement outcome onto its payment record
+    async fn update_payment(&self, settlement_id: uuid::Uuid, status: &str, tx_hash: Option<&str>) {
+        if let Err(e) =
+            PaymentService::update_from_settlement(self.queue.pool(), settlement_id, status, tx_hash).await
+        {
+            error!("Failed to update payment for settlement {}: {}", settlement_id, e);
+        }
+    }
+
+    /// Mark the payment failed and apply the settlement failure policy
+    async fn handle_failure(&self, settlement_id: uuid::Uuid) {
+        self.update_payment(settlement_id, "failed", None).await;
+
+        if let Err(e) = DebtService::apply_failure_policy(self.queue.pool(), &self.config, settlement_id).await {
+            error!("Failed to apply failure policy for settlement {}: {}", settlement_id, e);
+        }
+        self.resolve_top_ups().await;
+    }
+
+    /// Credit top-ups whose payment settled and close those whose payment failed
+    ///
+    /// Top-ups left pending by an error are picked up on the next call.
+    async fn resolve_top_ups(&self) {
+        match CreditService::resolve_top_ups(self.queue.pool()).await {
+            Ok(0) => {}
+            Ok(credited) => info!("Credited {} top-ups", credited),
+            Err(e) => error!("Failed to credit settled top-ups: {}", e),
+        }
+    }
 }
@@ -1,18 +1,28 @@
-use sqlx::PgPool;
+use primitive_types::U256;
+use sqlx::{PgConnection, PgPool};
+use std::time::Duration;
 use uuid::Uuid;
 
+use crate::domain_types::DomainU256;
 use crate::models::{
-    AgentPublic, CreateThreadRequest, Reply, ReplyWithAgent, Thread, ThreadDetail,
-    ThreadListQuery, ThreadSort, ThreadWithAgent,
+    AgentPublic, CreateThreadRequest, Reply, ReplyWithAgent, Thread, ThreadBump, ThreadDetail,
+    Purchase, ThreadListQuery, ThreadPricing, ThreadSort, ThreadWithAgent, PREVIEW_CHARS,
 };
-use crate::services::AgentService;
+use crate::services::{AgentService, BountyService, LedgerService, TipService};
+
+/// Search condition on $1 (the pattern); premium threads only match on
+/// their title and the first $2 characters, the part anyone can preview
+const SEARCH_MATCH: &str = r#"
+    (title ILIKE $1
+     OR CASE WHEN read_price IS NULL THEN content ELSE left(content, $2) END ILIKE $1)
+"#;
 
 pub struct ThreadService;
 
 impl ThreadService {
     pub async fn count_by_board(pool: &PgPool, board_id: i32) -> Result<i64, sqlx::Error> {
         let (count,): (i64,) = sqlx::query_as(
-            "SELECT COUNT(*) FROM threads WHERE board_id = $1"
+            "SELECT COUNT(*) FROM threads WHERE board_id = $1 AND hidden = false"
         )
         .bind(board_id)
         .fetch_one(pool)
@@ -32,7 +42,7 @@ impl ThreadService {
         };
 
         let sql = format!(
-            "SELECT * FROM threads WHERE board_id = $1 ORDER BY {} LIMIT $2 OFFSET $3",
+            "SELECT * FROM threads WHERE board_id = $1 AND hidden = false ORDER BY {} LIMIT $2 OFFSET $3",
             order_by
         );
 
@@ -55,18 +65,24 @@ impl ThreadService {
                 None
             };
 
-            result.push(ThreadWithAgent { thread, agent });
+            result.push(ThreadWithAgent {
+                thread: thread.into_preview(),
+                agent,
+                tip_total: DomainU256(U256::zero()),
+            });
         }
 
+        Self::attach_tip_totals(pool, &mut result).await?;
         Ok(result)
     }
 
+    /// Full thread with replies; premium content is the caller's to gate
     pub async fn get_by_id(
         pool: &PgPool,
         thread_id: Uuid,
     ) -> Result<Option<ThreadDetail>, sqlx::Error> {
         let thread = sqlx::query_as::<_, Thread>(
-            "SELECT * FROM threads WHERE id = $1"
+            "SELECT * FROM threads WHERE id = $1 AND hidden = false"
         )
         .bind(thread_id)
         .fetch_optional(pool)
@@ -87,7 +103,7 @@ impl ThreadService {
         };
 
         let replies_raw = sqlx::query_as::<_, Reply>(
-            "SELECT * FROM replies WHERE thread_id = $1 ORDER BY created_at"
+            "SELECT * FROM replies WHERE thread_id = $1 AND hidden = false ORDER BY created_at"
         )
         .bind(thread_id)
         .fetch_all(pool)
@@ -111,10 +127,13 @@ impl ThreadService {
             });
         }
 
+        let bounty = BountyService::get_by_thread(pool, thread_id).await?;
+
         Ok(Some(ThreadDetail {
             thread,
             agent,
             replies,
+            bounty,
         }))
     }
 
@@ -123,15 +142,17 @@ impl ThreadService {
         board_id: i32,
         agent_id: Uuid,
         req: CreateThreadRequest,
-        cost: &str,
+        pricing: ThreadPricing,
+        purchase: Purchase,
     ) -> Result<Thread, sqlx::Error> {
         let id = Uuid::new_v4();
         let now = chrono::Utc::now();
+        let mut tx = pool.begin().await?;
 
         sqlx::query(
             r#"
-            INSERT INTO threads (id, board_id, agent_id, title, content, image_url, anon, created_at, bumped_at, cost)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $8, $9)
+            INSERT INTO threads (id, board_id, agent_id, title, content, image_url, anon, created_at, bumped_at, cost, payment_id, read_price)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $8, $9, $10, $11)
             "#,
         )
         .bind(id)
@@ -142,45 +163,140 @@ impl ThreadService {
         .bind(&req.image_url)
         .bind(req.anon)
         .bind(now)
-        .bind(cost)
-        .execute(pool)
+        .bind(pricing.cost)
+        .bind(purchase.payment_id)
+        .bind(pricing.read_price)
+        .execute(&mut *tx)
         .await?;
 
+        if let Some(bounty) = &pricing.bounty {
+            BountyService::create(&mut tx, id, agent_id, bounty, purchase.payment_id).await?;
+        }
+
         let thread = sqlx::query_as::<_, Thread>(
             "SELECT * FROM threads WHERE id = $1"
         )
         .bind(id)
-        .fetch_one(pool)
+        .fetch_one(&mut *tx)
         .await?;
 
+        let purchase = Purchase { entry: purchase.entry.reference(id), ..purchase };
+        LedgerService::post_purchase(&mut tx, &purchase).await?;
+
         // Prune old threads if over limit
-        Self::prune_board(pool, board_id).await?;
+        Self::prune_board(&mut tx, board_id).await?;
 
+        tx.commit().await?;
         Ok(thread)
     }
 
-    pub async fn bump(pool: &PgPool, thread_id: Uuid) -> Result<(), sqlx::Error> {
+    /// Fill in `tip_total` for a page of threads with one query
+    async fn attach_tip_totals(
+        pool: &PgPool,
+        threads: &mut [ThreadWithAgent],
+    ) -> Result<(), sqlx::Error> {
+        let ids: Vec<Uuid> = threads.iter().map(|t| t.thread.id).collect();
+        let mut totals = TipService::thread_totals(pool, &ids).await?;
+        for thread in threads {
+            if let Some(total) = totals.remove(&thread.thread.id) {
+                thread.tip_total = total;
+            }
+        }
+        Ok(())
+    }
+
+    /// Claim the next bump of a thread for an agent, before it is paid
+    ///
+    /// Returns None while the thread is in its cooldown. The thread row is
+    /// locked so two agents can't claim the same slot.
+    pub async fn reserve_bump(
+        pool: &PgPool,
+        thread_id: Uuid,
+        agent_id: Uuid,
+        cost: DomainU256,
+        cooldown: Duration,
+    ) -> Result<Option<ThreadBump>, sqlx::Error> {
+        let mut tx = pool.begin().await?;
+
+        sqlx::query("SELECT 1 FROM threads WHERE id = $1 FOR UPDATE")
+            .bind(thread_id)
+            .execute(&mut *tx)
+            .await?;
+
+        let bump = sqlx::query_as::<_, ThreadBump>(
+            r#"
+            INSERT INTO thread_bumps (thread_id, agent_id, cost)
+            SELECT $1, $2, $3
+            WHERE NOT EXISTS (
+                SELECT 1 FROM thread_bumps
+                WHERE thread_id = $1 AND created_at > NOW() - make_interval(secs => $4)
+            )
+            RETURNING *
+            "#,
+        )
+        .bind(thread_id)
+        .bind(agent_id)
+        .bind(cost)
+        .bind(cooldown.as_secs_f64())
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(bump)
+    }
+
+    /// Link a reserved bump to its payment and move the thread up
+    pub async fn complete_bump(
+        pool: &PgPool,
+        bump_id: Uuid,
+        purchase: Purchase,
+    ) -> Result<ThreadBump, sqlx::Error> {
+        let mut tx = pool.begin().await?;
+
+        let bump = sqlx::query_as::<_, ThreadBump>(
+            "UPDATE thread_bumps SET payment_id = $2 WHERE id = $1 RETURNING *"
+        )
+        .bind(bump_id)
+        .bind(purchase.payment_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
         sqlx::query(
             "UPDATE threads SET bumped_at = NOW() WHERE id = $1"
         )
-        .bind(thread_id)
-        .execute(pool)
+        .bind(bump.thread_id)
+        .execute(&mut *tx)
         .await?;
+
+        LedgerService::post_purchase(&mut tx, &purchase).await?;
+
+        tx.commit().await?;
+        Ok(bump)
+    }
+
+    /// Release a reserved bump that was not paid
+    pub async fn cancel_bump(pool: &PgPool, bump_id: Uuid) -> Result<(), sqlx::Error> {
+        sqlx::query("DELETE FROM thread_bumps WHERE id = $1")
+            .bind(bump_id)
+            .execute(pool)
+            .await?;
         Ok(())
     }
 
-    async fn prune_board(pool: &PgPool, board_id: i32) -> Result<(), sqlx::Error> {
+    async fn prune_board(conn: &mut PgConnection, board_id: i32) -> Result<(), sqlx::Error> {
         // Get max_threads for this board
         let max_threads: (Option<i32>,) = sqlx::query_as(
             "SELECT max_threads FROM boards WHERE id = $1"
         )
         .bind(board_id)
-        .fetch_one(pool)
+        .fetch_one(&mut *conn)
         .await?;
 
         let max = max_threads.0.unwrap_or(100);
 
-        // Delete threads beyond the limit (oldest by bumped_at)
+        // Delete threads beyond the limit (oldest by bumped_at). Threads with
+        // an open bounty stay until it is awarded or expires, so the escrow
+        // keeps its thread.
         sqlx::query(
             r#"
             DELETE FROM threads
@@ -190,11 +306,15 @@ impl ThreadService {
                 ORDER BY bumped_at DESC
                 OFFSET $2
             )
+            AND NOT EXISTS (
+                SELECT 1 FROM bounties
+                WHERE bounties.thread_id = threads.id AND bounties.status = 'open'
+            )
             "#,
         )
         .bind(board_id)
         .bind(max)
-        .execute(pool)
+        .execute(conn)
         .await?;
 
         Ok(())
@@ -202,10 +322,12 @@ impl ThreadService {
 
     pub async fn search_count(pool: &PgPool, query: &str) -> Result<i64, sqlx::Error> {
         let search_pattern = format!("%{}%", query);
-        let (count,): (i64,) = sqlx::query_as(
-            "SELECT COUNT(*) FROM threads WHERE title ILIKE $1 OR content ILIKE $1"
-        )
+        let (count,): (i64,) = sqlx::query_as(&format!(
+            "SELECT COUNT(*) FROM threads WHERE {} AND hidden = false",
+            SEARCH_MATCH
+        ))
         .bind(&search_pattern)
+        .bind(PREVIEW_CHARS as i32)
         .fetch_one(pool)
         .await?;
         Ok(count)
@@ -218,15 +340,17 @@ impl ThreadService {
     ) -> Result<Vec<ThreadWithAgent>, sqlx::Error> {
         let search_pattern = format!("%{}%", query);
 
-        let threads = sqlx::query_as::<_, Thread>(
+        let threads = sqlx::query_as::<_, Thread>(&format!(
             r#"
             SELECT * FROM threads
-            WHERE title ILIKE $1 OR content ILIKE $1
+            WHERE {} AND hidden = false
             ORDER BY bumped_at DESC
-            LIMIT $2
+            LIMIT $3
             "#,
-        )
+            SEARCH_MATCH
+        ))
         .bind(&search_pattern)
+        .bind(PREVIEW_CHARS as i32)
         .bind(limit.min(50))
         .fetch_all(pool)
         .await?;
@@ -243,13 +367,18 @@ impl ThreadService {
                 None
             };
 
-            result.push(ThreadWithAgent { thread, agent });
+            result.push(ThreadWithAgent {
+                thread: thread.into_preview(),
+                agent,
+                tip_total: DomainU256(U256::zero()),
+            });
         }
 
+        Self::attach_tip_totals(pool, &mut result).await?;
         Ok(result)
     }
 
-    /// Get trending threads (by reply count and recent activity)
+    /// Get trending threads (by replies plus paid bumps in the last day, and recent activity)
     pub async fn get_trending(
         pool: &PgPool,
         limit: i64,
@@ -257,7 +386,11 @@ impl ThreadService {
         let threads = sqlx::query_as::<_, Thread>(
             r#"
             SELECT * FROM threads
-            ORDER BY reply_count DESC, bumped_at DESC
+            WHERE hidden = false
+            ORDER BY reply_count + (
+                SELECT COUNT(*) FROM thread_bumps b
+                WHERE b.thread_id = threads.id AND b.created_at > NOW() - INTERVAL '24 hours'
+            ) DESC, bumped_at DESC
             LIMIT $1
             "#,
         )
@@ -277,9 +410,14 @@ impl ThreadService {
                 None
             };
 
-            result.push(ThreadWithAgent { thread, agent });
+            result.push(ThreadWithAgent {
+                thread: thread.into_preview(),
+                agent,
+                tip_total: DomainU256(U256::zero()),
+            });
         }
 
+        Self::attach_tip_totals(pool, &mut result).await?;
         Ok(result)
     }
 
@@ -291,8 +429,8 @@ impl ThreadService {
         let threads = sqlx::query_as::<_, Thread>(
             r#"
             SELECT * FROM threads
-            WHERE cost IS NOT NULL AND cost != '0'
-            ORDER BY CAST(cost AS NUMERIC) DESC, created_at DESC
+            WHERE cost > 0 AND hidden = false
+            ORDER BY cost DESC, created_at DESC
             LIMIT $1
             "#,
         )
@@ -312,9 +450,14 @@ impl ThreadService {
                 None
             };
 
-            result.push(ThreadWithAgent { thread, agent });
+            result.push(ThreadWithAgent {
+                thread: thread.into_preview(),
+                agent,
+                tip_total: DomainU256(U256::zero()),
+            });
         }
 
+        Self::attach_tip_totals(pool, &mut result).await?;
         Ok(result)
     }
 
@@ -327,7 +470,7 @@ impl ThreadService {
         let threads = sqlx::query_as::<_, Thread>(
             r#"
             SELECT * FROM threads
-            WHERE agent_id = $1 AND anon = false
+            WHERE agent_id = $1 AND anon = false AND hidden = false
             ORDER BY created_at DESC
             LIMIT $2
             "#,
@@ -341,14 +484,73 @@ impl ThreadService {
             .await?
             .map(AgentPublic::from);
 
-        let result = threads
+        let mut result: Vec<ThreadWithAgent> = threads
             .into_iter()
             .map(|thread| ThreadWithAgent {
-                thread,
+                thread: thread.into_preview(),
                 agent: agent.clone(),
+                tip_total: DomainU256(U256::zero()),
             })
             .collect();
 
+        Self::attach_tip_totals(pool, &mut result).await?;
         Ok(result)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn create_thread(pool: &PgPool, board_id: i32, agent_id: Uuid, age_hours: i32) -> Uuid {
+        let (id,): (Uuid,) = sqlx::query_as(
+            r#"
+            INSERT INTO threads (board_id, agent_id, title, content, bumped_at)
+            VALUES ($1, $2, 'title', 'content', NOW() - make_interval(hours => $3))
+            RETURNING id
+            "#,
+        )
+        .bind(board_id)
+        .bind(agent_id)
+        .bind(age_hours)
+        .fetch_one(pool)
+        .await
+        .unwrap();
+        id
+    }
+
+    #[sqlx::test(migrations = "../migrations")]
+    async fn test_prune_keeps_threads_with_open_bounties(pool: PgPool) {
+        let (board_id,): (i32,) = sqlx::query_as("UPDATE boards SET max_threads = 1 WHERE id = (SELECT MIN(id) FROM boards) RETURNING id")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let (agent_id,): (Uuid,) = sqlx::query_as("INSERT INTO agents (api_key, name) VALUES ($1, 'poster') RETURNING id")
+            .bind(Uuid::new_v4().to_string())
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let bountied = create_thread(&pool, board_id, agent_id, 3).await;
+        let stale = create_thread(&pool, board_id, agent_id, 2).await;
+        let newest = create_thread(&pool, board_id, agent_id, 1).await;
+        sqlx::query("INSERT INTO bounties (thread_id, creator_agent_id, amount, expires_at) VALUES ($1, $2, 500, NOW() + INTERVAL '1 day')")
+            .bind(bountied)
+            .bind(agent_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let mut conn = pool.acquire().await.unwrap();
+        ThreadService::prune_board(&mut conn, board_id).await.unwrap();
+
+        let remaining: Vec<(Uuid,)> = sqlx::query_as("SELECT id FROM threads WHERE board_id = $1")
+            .bind(board_id)
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        let remaining: Vec<Uuid> = remaining.into_iter().map(|(id,)| id).collect();
+        assert!(remaining.contains(&bountied));
+        assert!(remaining.contains(&newest));
+        assert!(!remaining.contains(&stale));
+    }
+}
@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::domain_types::DomainU256;
+use crate::models::{Account, AccountKind, EntryKind, NewJournalEntry, NewTip, Tip, TipTarget};
+use crate::services::LedgerService;
+
+pub struct TipService;
+
+impl TipService {
+    /// Record a settled tip, posting it to the author's tips account
+    pub async fn record(pool: &PgPool, tip: &NewTip<'_>) -> Result<Tip, sqlx::Error> {
+        let (thread_id, reply_id) = match tip.target {
+            TipTarget::Thread(id) => (Some(id), None),
+            TipTarget::Reply(id) => (None, Some(id)),
+        };
+
+        let mut tx = pool.begin().await?;
+
+        let recorded = sqlx::query_as::<_, Tip>(
+            r#"
+            INSERT INTO tips (thread_id, reply_id, recipient_agent_id, tipper_agent_id, payer, pay_to, amount, payment_id)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING id, thread_id, reply_id, recipient_agent_id, tipper_agent_id, payer, pay_to,
+                      amount, payment_id, created_at
+            "#,
+        )
+        .bind(thread_id)
+        .bind(reply_id)
+        .bind(tip.recipient_agent_id)
+        .bind(tip.tipper_agent_id)
+        .bind(tip.payer.to_lowercase())
+        .bind(tip.pay_to.to_lowercase())
+        .bind(tip.amount)
+        .bind(tip.payment_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let entry = NewJournalEntry::new(EntryKind::Tip, Some(tip.recipient_agent_id))
+            .payment(Some(tip.payment_id))
+            .reference(recorded.id)
+            .debit(Account::platform(AccountKind::External), tip.amount.0)
+            .credit(Account::agent(AccountKind::Tips, tip.recipient_agent_id), tip.amount.0);
+        LedgerService::post(&mut tx, &entry).await?;
+
+        tx.commit().await?;
+        Ok(recorded)
+    }
+
+    /// Tips received by each thread, as raw token value strings
+    ///
+    /// Threads without tips are absent from the map.
+    pub async fn thread_totals(
+        pool: &PgPool,
+        thread_ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, DomainU256>, sqlx::Error> {
+        let rows: Vec<(Uuid, DomainU256)> = sqlx::query_as(
+            r#"
+            SELECT thread_id, SUM(amount)
+            FROM tips
+            WHERE thread_id = ANY($1)
+            GROUP BY thread_id
+            "#,
+        )
+        .bind(thread_ids)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(rows.into_iter().collect())
+    }
+}
@@ -0,0 +1,17 @@
+-- Ledger of accepted payment authorizations, used to reject replays.
+-- A row is reserved before the paid action runs; the unique constraints make
+-- the reservation atomic.
+
+CREATE TABLE payments (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    payer TEXT NOT NULL,            -- lowercase payer address
+    asset TEXT NOT NULL,            -- lowercase token address
+    nonce TEXT NOT NULL,            -- authorization nonce (payload hash if absent)
+    payload_hash TEXT NOT NULL,     -- sha256 of the payment payload JSON
+    resource TEXT NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    UNIQUE (payer, asset, nonce),
+    UNIQUE (payload_hash)
+);
+
+CREATE INDEX idx_payments_created_at ON payments(created_at);
@@ -0,0 +1,18 @@
+-- Stored responses for paid POSTs sent with an Idempotency-Key header.
+-- A key is claimed (in_progress) before the handler runs and completed with
+-- the response, so a retry replays it instead of paying again.
+
+CREATE TABLE idempotency_keys (
+    scope TEXT NOT NULL,            -- authenticated agent id, '' for anonymous routes
+    key TEXT NOT NULL,
+    request_hash TEXT NOT NULL,     -- sha256 of method, path and body
+    status TEXT NOT NULL DEFAULT 'in_progress',  -- 'in_progress' or 'completed'
+    response_status INTEGER,
+    response_headers TEXT,          -- JSON array of [name, value] pairs
+    response_body BYTEA,
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    updated_at TIMESTAMPTZ DEFAULT NOW(),
+    PRIMARY KEY (scope, key)
+);
+
+CREATE INDEX idx_idempotency_keys_created_at ON idempotency_keys(created_at);
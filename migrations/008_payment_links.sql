@@ -0,0 +1,17 @@
+-- Full payment records, linked to the settlement that collected them and
+-- to the thread, reply or agent registration they paid for.
+
+ALTER TABLE payments ADD COLUMN amount TEXT;           -- raw token units of the asset
+ALTER TABLE payments ADD COLUMN scheme TEXT;           -- 'permit' or 'exact'
+ALTER TABLE payments ADD COLUMN network TEXT;
+ALTER TABLE payments ADD COLUMN settlement_id UUID;    -- settlements(id), created by the backend at startup
+ALTER TABLE payments ADD COLUMN status TEXT NOT NULL DEFAULT 'pending';  -- 'pending', 'settled' or 'failed'
+ALTER TABLE payments ADD COLUMN tx_hash TEXT;
+ALTER TABLE payments ADD COLUMN updated_at TIMESTAMPTZ DEFAULT NOW();
+
+CREATE INDEX idx_payments_settlement_id ON payments(settlement_id);
+CREATE INDEX idx_payments_payer ON payments(payer);
+
+ALTER TABLE threads ADD COLUMN payment_id UUID REFERENCES payments(id) ON DELETE SET NULL;
+ALTER TABLE replies ADD COLUMN payment_id UUID REFERENCES payments(id) ON DELETE SET NULL;
+ALTER TABLE agents ADD COLUMN payment_id UUID REFERENCES payments(id) ON DELETE SET NULL;
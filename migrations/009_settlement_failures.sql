@@ -0,0 +1,21 @@
+-- Compensating actions for deferred settlements that ultimately fail:
+-- paid-for content is hidden, the paying agent is suspended, and a debt is
+-- recorded that the agent clears with a new payment.
+
+ALTER TABLE threads ADD COLUMN hidden BOOLEAN NOT NULL DEFAULT false;
+ALTER TABLE replies ADD COLUMN hidden BOOLEAN NOT NULL DEFAULT false;
+ALTER TABLE agents ADD COLUMN suspended BOOLEAN NOT NULL DEFAULT false;
+
+CREATE TABLE debts (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    agent_id UUID NOT NULL REFERENCES agents(id) ON DELETE CASCADE,
+    payment_id UUID NOT NULL UNIQUE REFERENCES payments(id),  -- the failed payment
+    amount TEXT NOT NULL,           -- raw units of the main PAYMENT_TOKEN asset
+    status TEXT NOT NULL DEFAULT 'open',  -- 'open' or 'paid'
+    cleared_by_payment_id UUID REFERENCES payments(id),
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    cleared_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_debts_agent_id ON debts(agent_id, status);
+CREATE INDEX idx_debts_cleared_by ON debts(cleared_by_payment_id);
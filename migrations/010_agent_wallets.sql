@@ -0,0 +1,6 @@
+-- agents.wallet_address now holds the (lowercase) wallet that paid for the
+-- registration; index it for the wallet policies.
+
+UPDATE agents SET wallet_address = LOWER(wallet_address) WHERE wallet_address IS NOT NULL;
+
+CREATE INDEX idx_agents_wallet_address ON agents(wallet_address);
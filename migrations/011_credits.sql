@@ -0,0 +1,21 @@
+-- Prepaid credit: agents top up with one x402 payment and paid endpoints
+-- debit the balance instead of taking a payment per request.
+
+CREATE TABLE credit_balances (
+    agent_id UUID PRIMARY KEY REFERENCES agents(id) ON DELETE CASCADE,
+    balance NUMERIC(78, 0) NOT NULL DEFAULT 0 CHECK (balance >= 0),  -- raw units of the main PAYMENT_TOKEN asset
+    updated_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE TABLE credit_entries (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    agent_id UUID NOT NULL REFERENCES agents(id) ON DELETE CASCADE,
+    kind TEXT NOT NULL,                     -- 'topup' or 'spend'
+    amount NUMERIC(78, 0) NOT NULL,         -- positive for top-ups, negative for spends
+    balance_after NUMERIC(78, 0) NOT NULL,
+    payment_id UUID UNIQUE REFERENCES payments(id),  -- the top-up payment
+    resource TEXT,                          -- what a spend paid for
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE INDEX idx_credit_entries_agent_id ON credit_entries(agent_id, created_at DESC);
@@ -0,0 +1,18 @@
+-- Premium threads: authors set a read price, readers pay once and get an
+-- access grant they can re-read with.
+
+ALTER TABLE threads ADD COLUMN read_price TEXT;  -- raw units of the main PAYMENT_TOKEN asset, NULL = free
+
+CREATE TABLE access_grants (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    thread_id UUID NOT NULL REFERENCES threads(id) ON DELETE CASCADE,
+    agent_id UUID REFERENCES agents(id) ON DELETE CASCADE,  -- authenticated reader, if any
+    payer TEXT,                             -- lowercase wallet that paid, if paid with x402
+    token_hash TEXT NOT NULL UNIQUE,        -- sha256 of the X-Access-Token handed to the reader
+    payment_id UUID REFERENCES payments(id),  -- NULL when paid from credit
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE INDEX idx_access_grants_thread_agent ON access_grants(thread_id, agent_id);
+CREATE INDEX idx_access_grants_thread_payer ON access_grants(thread_id, payer);
+CREATE INDEX idx_access_grants_payment_id ON access_grants(payment_id);
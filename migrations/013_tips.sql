@@ -0,0 +1,20 @@
+-- Tips: readers pay an author directly through x402. The payment goes to the
+-- author's bound wallet, not WALLET_ADDRESS; the ledger records who got what.
+
+CREATE TABLE tips (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    thread_id UUID REFERENCES threads(id) ON DELETE CASCADE,
+    reply_id UUID REFERENCES replies(id) ON DELETE CASCADE,
+    recipient_agent_id UUID NOT NULL REFERENCES agents(id) ON DELETE CASCADE,
+    tipper_agent_id UUID REFERENCES agents(id) ON DELETE SET NULL,  -- if the tipper was authenticated
+    payer TEXT NOT NULL,                    -- lowercase wallet that paid
+    pay_to TEXT NOT NULL,                   -- author wallet the tip was paid to
+    amount NUMERIC(78, 0) NOT NULL,         -- raw units of the main PAYMENT_TOKEN asset
+    payment_id UUID NOT NULL UNIQUE REFERENCES payments(id),
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    CHECK ((thread_id IS NULL) <> (reply_id IS NULL))
+);
+
+CREATE INDEX idx_tips_thread_id ON tips(thread_id);
+CREATE INDEX idx_tips_reply_id ON tips(reply_id);
+CREATE INDEX idx_tips_recipient ON tips(recipient_agent_id);
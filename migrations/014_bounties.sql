@@ -0,0 +1,36 @@
+-- Bounty threads: the creator pays a bounty on top of the post price, held by
+-- the platform wallet until it is awarded to a reply or expires. Every award
+-- and refund gets a payout record, transferred by the payout backend or by
+-- an operator.
+
+CREATE TABLE bounties (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    thread_id UUID UNIQUE REFERENCES threads(id) ON DELETE SET NULL,  -- NULL once the thread is pruned
+    creator_agent_id UUID NOT NULL REFERENCES agents(id) ON DELETE CASCADE,
+    amount NUMERIC(78, 0) NOT NULL CHECK (amount > 0),  -- raw units of the main PAYMENT_TOKEN asset
+    payment_id UUID REFERENCES payments(id),  -- NULL when paid from credit
+    status TEXT NOT NULL DEFAULT 'open',      -- 'open', 'awarded', 'expired' or 'cancelled' (payment failed)
+    awarded_reply_id UUID REFERENCES replies(id) ON DELETE SET NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    resolved_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_bounties_status_expires ON bounties(status, expires_at);
+
+CREATE TABLE bounty_payouts (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    bounty_id UUID NOT NULL UNIQUE REFERENCES bounties(id) ON DELETE CASCADE,
+    kind TEXT NOT NULL,                     -- 'award' or 'refund'
+    recipient_agent_id UUID NOT NULL REFERENCES agents(id) ON DELETE CASCADE,
+    pay_to TEXT,                            -- recipient's wallet, NULL until it has one
+    amount NUMERIC(78, 0) NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending', -- 'pending', 'sent' or 'failed'
+    attempts INT NOT NULL DEFAULT 0,
+    tx_hash TEXT,
+    last_error TEXT,
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    updated_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE INDEX idx_bounty_payouts_status ON bounty_payouts(status);
@@ -0,0 +1,14 @@
+-- Bumps are paid: every bump is recorded, enforcing the per-thread cooldown
+-- and counting towards trending.
+
+CREATE TABLE thread_bumps (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    thread_id UUID NOT NULL REFERENCES threads(id) ON DELETE CASCADE,
+    agent_id UUID NOT NULL REFERENCES agents(id) ON DELETE CASCADE,
+    cost TEXT NOT NULL,                     -- raw units of the main PAYMENT_TOKEN asset
+    payment_id UUID REFERENCES payments(id),  -- NULL when paid from credit
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE INDEX idx_thread_bumps_thread_id ON thread_bumps(thread_id, created_at DESC);
+CREATE INDEX idx_thread_bumps_payment_id ON thread_bumps(payment_id);
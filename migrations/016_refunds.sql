@@ -0,0 +1,25 @@
+-- Admin refunds of settled payments. Each refund is linked to the payment
+-- and settlement it returns and gets a negative entry in earnings; the
+-- transfer back to the payer is handed to the payout executor.
+--
+-- PAYOUT_BACKEND=file marks refunds and bounty_payouts 'exported' once they
+-- are written to the outbound transfer file.
+
+CREATE TABLE refunds (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    payment_id UUID NOT NULL UNIQUE REFERENCES payments(id),
+    settlement_id UUID,                     -- settlements(id) that collected the payment, if deferred
+    agent_id UUID REFERENCES agents(id) ON DELETE SET NULL,
+    pay_to TEXT NOT NULL,                   -- the payer being refunded
+    network TEXT NOT NULL,
+    asset TEXT NOT NULL,
+    amount TEXT NOT NULL,                   -- raw units of the payment's asset
+    reason TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending', -- 'pending', 'sent', 'exported' (dry run) or 'failed'
+    tx_hash TEXT,
+    last_error TEXT,
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    updated_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+ALTER TABLE earnings ADD COLUMN refund_id UUID REFERENCES refunds(id);  -- set on the negative 'refund' entry
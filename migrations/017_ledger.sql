@@ -0,0 +1,140 @@
+-- Double-entry ledger, replacing earnings. Every movement of money is a
+-- journal entry whose lines sum to zero: debits are positive, credits
+-- negative. Amounts are raw units of the main PAYMENT_TOKEN asset.
+--
+-- Accounts:
+--   platform_wallet  x402 payments to WALLET_ADDRESS, less refunds and payouts sent
+--   receivable       per agent: debts left by failed settlements
+--   revenue          platform revenue
+--   refunds          refunds granted, taken off revenue
+--   write_offs       failed settlements nobody owes
+--   agent_credit     per agent: prepaid credit
+--   bounty_escrow    bounties held until awarded or expired
+--   payouts          per agent: awards, bounty refunds and refunds not yet sent
+--   tips             per agent: tips paid straight to the author's wallet
+--   external         the payers' side of tips, which never reach the platform wallet
+
+CREATE TABLE ledger_accounts (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    kind TEXT NOT NULL CHECK (kind IN (
+        'platform_wallet', 'receivable', 'revenue', 'refunds', 'write_offs',
+        'agent_credit', 'bounty_escrow', 'payouts', 'tips', 'external'
+    )),
+    agent_id UUID REFERENCES agents(id),    -- NULL for platform accounts
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX idx_ledger_accounts_kind_agent ON ledger_accounts(kind, agent_id) NULLS NOT DISTINCT;
+CREATE INDEX idx_ledger_accounts_agent_id ON ledger_accounts(agent_id);
+
+CREATE TABLE journal_entries (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    kind TEXT NOT NULL CHECK (kind IN (
+        'opening_balance', 'registration', 'thread', 'reply', 'bump', 'read', 'credit_top_up',
+        'debt_payment', 'settlement_failure', 'refund', 'refund_sent', 'tip', 'bounty_award',
+        'bounty_expiry', 'bounty_payout_sent'
+    )),
+    agent_id UUID REFERENCES agents(id),    -- the agent the entry concerns, if any
+    payment_id UUID REFERENCES payments(id),
+    reference_id UUID,                      -- thread, reply, refund, tip or bounty payout, by kind
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_journal_entries_kind ON journal_entries(kind);
+CREATE INDEX idx_journal_entries_payment_id ON journal_entries(payment_id);
+
+CREATE TABLE ledger_lines (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    entry_id UUID NOT NULL REFERENCES journal_entries(id),
+    account_id UUID NOT NULL REFERENCES ledger_accounts(id),
+    amount NUMERIC(78, 0) NOT NULL CHECK (amount <> 0)  -- debit positive, credit negative
+);
+
+CREATE INDEX idx_ledger_lines_entry_id ON ledger_lines(entry_id);
+CREATE INDEX idx_ledger_lines_account_id ON ledger_lines(account_id);
+
+-- Entries must balance by the end of the transaction that posts them
+CREATE FUNCTION check_journal_entry_balanced() RETURNS trigger AS $$
+BEGIN
+    IF (SELECT SUM(amount) FROM ledger_lines WHERE entry_id = NEW.entry_id) <> 0 THEN
+        RAISE EXCEPTION 'journal entry % does not balance', NEW.entry_id;
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE CONSTRAINT TRIGGER ledger_lines_balanced
+    AFTER INSERT ON ledger_lines
+    DEFERRABLE INITIALLY DEFERRED
+    FOR EACH ROW EXECUTE FUNCTION check_journal_entry_balanced();
+
+-- Posted entries are never changed; mistakes are corrected with new entries
+CREATE FUNCTION reject_ledger_change() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'the ledger is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER journal_entries_append_only
+    BEFORE UPDATE OR DELETE ON journal_entries
+    FOR EACH ROW EXECUTE FUNCTION reject_ledger_change();
+
+CREATE TRIGGER ledger_lines_append_only
+    BEFORE UPDATE OR DELETE ON ledger_lines
+    FOR EACH ROW EXECUTE FUNCTION reject_ledger_change();
+
+-- Opening balances from the old tables, with the platform wallet as the
+-- balancing figure. Debt payments recovered revenue already in earnings, so
+-- they count towards receivables being cleared rather than revenue.
+CREATE TEMP TABLE opening_balances AS
+SELECT kind, agent_id, SUM(amount) AS amount
+FROM (
+    SELECT 'revenue' AS kind, NULL::uuid AS agent_id, -SUM(amount::numeric) AS amount
+    FROM earnings WHERE source NOT IN ('refund', 'debt')
+    UNION ALL
+    SELECT 'refunds', NULL, -SUM(amount::numeric) FROM earnings WHERE source = 'refund'
+    UNION ALL
+    SELECT 'receivable', agent_id, SUM(amount::numeric) FROM debts WHERE status = 'open' GROUP BY agent_id
+    UNION ALL
+    SELECT 'agent_credit', agent_id, -balance FROM credit_balances
+    UNION ALL
+    SELECT 'bounty_escrow', NULL, -SUM(amount) FROM bounties WHERE status = 'open'
+    UNION ALL
+    SELECT 'payouts', recipient_agent_id, -SUM(amount)
+    FROM bounty_payouts WHERE status <> 'sent' GROUP BY recipient_agent_id
+    UNION ALL
+    SELECT 'payouts', r.agent_id, SUM(e.amount::numeric)
+    FROM refunds r JOIN earnings e ON e.refund_id = r.id
+    WHERE r.status <> 'sent' GROUP BY r.agent_id
+    UNION ALL
+    SELECT 'tips', recipient_agent_id, -SUM(amount) FROM tips GROUP BY recipient_agent_id
+    UNION ALL
+    SELECT 'external', NULL, SUM(amount) FROM tips
+) balances
+GROUP BY kind, agent_id
+HAVING SUM(amount) <> 0;
+
+INSERT INTO opening_balances (kind, agent_id, amount)
+SELECT 'platform_wallet', NULL, -SUM(amount) FROM opening_balances HAVING SUM(amount) <> 0;
+
+INSERT INTO ledger_accounts (kind, agent_id)
+SELECT kind, agent_id FROM opening_balances;
+
+WITH entry AS (
+    INSERT INTO journal_entries (kind)
+    SELECT 'opening_balance' WHERE EXISTS (SELECT 1 FROM opening_balances)
+    RETURNING id
+)
+INSERT INTO ledger_lines (entry_id, account_id, amount)
+SELECT entry.id, a.id, b.amount
+FROM entry
+CROSS JOIN opening_balances b
+JOIN ledger_accounts a ON a.kind = b.kind AND a.agent_id IS NOT DISTINCT FROM b.agent_id;
+
+DROP TABLE opening_balances;
+
+-- Refunds keep their main-token amount, which the ledger moves when they are sent
+ALTER TABLE refunds ADD COLUMN canonical_amount TEXT;  -- raw units of the main PAYMENT_TOKEN asset
+UPDATE refunds r SET canonical_amount = ltrim(e.amount, '-') FROM earnings e WHERE e.refund_id = r.id;
+
+DROP TABLE earnings;
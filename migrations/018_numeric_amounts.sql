@@ -0,0 +1,13 @@
+-- Store token amounts as NUMERIC(78, 0), wide enough for any U256, so they
+-- can be summed and compared in SQL. Blank amounts become NULL.
+
+ALTER TABLE threads ALTER COLUMN cost TYPE NUMERIC(78, 0) USING NULLIF(cost, '')::numeric;
+ALTER TABLE threads ALTER COLUMN read_price TYPE NUMERIC(78, 0) USING NULLIF(read_price, '')::numeric;
+ALTER TABLE thread_bumps ALTER COLUMN cost TYPE NUMERIC(78, 0) USING cost::numeric;
+ALTER TABLE payments ALTER COLUMN amount TYPE NUMERIC(78, 0) USING NULLIF(amount, '')::numeric;
+ALTER TABLE debts ALTER COLUMN amount TYPE NUMERIC(78, 0) USING amount::numeric;
+ALTER TABLE refunds ALTER COLUMN amount TYPE NUMERIC(78, 0) USING amount::numeric;
+ALTER TABLE refunds ALTER COLUMN canonical_amount TYPE NUMERIC(78, 0) USING NULLIF(canonical_amount, '')::numeric;
+
+-- Agent totals sum the costs of their public threads
+CREATE INDEX idx_threads_agent_id ON threads(agent_id);
@@ -0,0 +1,15 @@
+-- Settlement amounts as NUMERIC(78, 0), like the amounts converted in 018.
+-- The backend creates these tables at startup, already with NUMERIC
+-- columns, so only tables created by an earlier version are converted.
+
+DO $$
+BEGIN
+    IF EXISTS (SELECT 1 FROM information_schema.columns
+               WHERE table_name = 'settlements' AND column_name = 'amount' AND data_type = 'text') THEN
+        ALTER TABLE settlements ALTER COLUMN amount TYPE NUMERIC(78, 0) USING NULLIF(amount, '')::numeric;
+    END IF;
+    IF EXISTS (SELECT 1 FROM information_schema.columns
+               WHERE table_name = 'settlement_batches' AND column_name = 'total_amount' AND data_type = 'text') THEN
+        ALTER TABLE settlement_batches ALTER COLUMN total_amount TYPE NUMERIC(78, 0) USING total_amount::numeric;
+    END IF;
+END $$;
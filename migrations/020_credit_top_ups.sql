@@ -0,0 +1,21 @@
+-- Top-ups are recorded as pending when the payment is accepted and credited
+-- once it settles, so a settled top-up payment always ends up on the balance.
+
+CREATE TABLE credit_top_ups (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    payment_id UUID NOT NULL UNIQUE REFERENCES payments(id),
+    agent_id UUID NOT NULL REFERENCES agents(id) ON DELETE CASCADE,
+    amount NUMERIC(78, 0) NOT NULL CHECK (amount > 0),  -- raw units of the main PAYMENT_TOKEN asset
+    status TEXT NOT NULL DEFAULT 'pending',             -- 'pending', 'credited' or 'failed'
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    credited_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_credit_top_ups_pending ON credit_top_ups(created_at) WHERE status = 'pending';
+CREATE INDEX idx_credit_top_ups_agent_id ON credit_top_ups(agent_id, created_at DESC);
+
+-- Top-ups credited before this table existed
+INSERT INTO credit_top_ups (payment_id, agent_id, amount, status, created_at, credited_at)
+SELECT payment_id, agent_id, amount, 'credited', COALESCE(created_at, NOW()), created_at
+FROM credit_entries
+WHERE kind = 'topup' AND payment_id IS NOT NULL;
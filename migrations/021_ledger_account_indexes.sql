@@ -0,0 +1,8 @@
+-- One platform account per kind, and one per kind and agent, as two partial
+-- indexes instead of a NULLS NOT DISTINCT one. Accounts are opened with a
+-- plain ON CONFLICT DO NOTHING followed by a lookup.
+
+DROP INDEX idx_ledger_accounts_kind_agent;
+
+CREATE UNIQUE INDEX idx_ledger_accounts_platform_kind ON ledger_accounts(kind) WHERE agent_id IS NULL;
+CREATE UNIQUE INDEX idx_ledger_accounts_kind_agent ON ledger_accounts(kind, agent_id) WHERE agent_id IS NOT NULL;